@@ -0,0 +1,17 @@
+//! Generates the gRPC service in proto/grrs.proto for the `grpc` feature,
+//! with protox so protoc needn't be installed.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/grrs.proto");
+        let files = protox::compile(["proto/grrs.proto"], ["proto"])?;
+        // The client's generated for edition 2021, so it isn't built.
+        tonic_build::configure()
+            .build_client(false)
+            .compile_fds(files)?;
+    }
+
+    Ok(())
+}
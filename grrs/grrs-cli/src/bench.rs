@@ -0,0 +1,358 @@
+//! Timing each way grrs can search on this machine, for `grrs bench`, so the
+//! cost of an option can be measured rather than guessed.
+//!
+//! Given a file or directory, it also times reading it through each buffer
+//! size, which is where disks differ: a spinning disk pays for every seek, so
+//! bigger buffers read big files faster, while an NVMe drive is quick either
+//! way and many tiny files gain nothing from buffers bigger than they are.
+//! Only the first run reads from the disk rather than the page cache, so run
+//! with `--runs 1` after dropping the cache, as with
+//! `echo 3 > /proc/sys/vm/drop_caches`, to compare disks.
+
+use anyhow::{anyhow, Context, Result};
+use grrs_core::matcher::Matcher;
+use grrs_core::sink::WriterSink;
+use grrs_core::{open_input_with, search_tree, walk, BufferSize, SearchSummary, Searcher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+/// How often a generated line contains the pattern.
+const MATCH_EVERY: u64 = 64;
+
+/// About how much of the text goes in each file of the tree it's written out
+/// as, and how many files go in each directory.
+const FILE_SIZE: usize = 256 * 1024;
+const FILES_PER_DIR: usize = 16;
+
+/// The buffer sizes to time reading a file or directory through.
+const BUFFER_SIZES: &[BufferSize] = &[
+    BufferSize::Fixed(4 * 1024),
+    BufferSize::Fixed(64 * 1024),
+    BufferSize::Fixed(1024 * 1024),
+    BufferSize::Adaptive,
+];
+
+/// Words the generated text is made from.
+const WORDS: &[&str] = &[
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "consectetur",
+    "adipiscing",
+    "elit",
+    "sed",
+    "do",
+    "eiusmod",
+    "tempor",
+    "incididunt",
+    "ut",
+    "labore",
+    "et",
+    "dolore",
+    "magna",
+    "aliqua",
+];
+
+/// What `grrs bench` searches, and how.
+#[derive(Debug, StructOpt)]
+pub struct Command {
+    /// The path to a file to search instead of generated text, or a directory
+    /// of files to time reading through each buffer size only
+    #[structopt(parse(from_os_str))]
+    path: Option<PathBuf>,
+
+    /// How many MiB of text to generate
+    #[structopt(long, value_name = "MiB", default_value = "32")]
+    size: usize,
+
+    /// The pattern to look for, which is put in one generated line in 64
+    #[structopt(long, default_value = "needle")]
+    pattern: String,
+
+    /// How many times to time each engine, keeping the fastest
+    #[structopt(long, default_value = "3")]
+    runs: usize,
+}
+
+/// A way of searching to time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Engine {
+    Literal,
+    Regex,
+    /// The literal matcher, through the text written out as a tree of files,
+    /// walked and searched by grrs_core::search_tree() on this many threads
+    /// as `grrs files` does
+    Tree(usize),
+}
+
+impl Engine {
+    fn name(&self) -> String {
+        match self {
+            Engine::Literal => "literal".to_string(),
+            Engine::Regex => "regex".to_string(),
+            Engine::Tree(threads) => format!("tree, -j {}", threads),
+        }
+    }
+}
+
+/// Runs `grrs bench`.
+pub fn run(command: &Command) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    if let Some(dir) = command.path.as_ref().filter(|path| path.is_dir()) {
+        return time_buffers(&mut out, command, dir);
+    }
+    let text = match &command.path {
+        Some(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("could not read file `{}`", path.display()))?,
+        None => generate(command.size * 1024 * 1024, &command.pattern),
+    };
+    let tree = tempfile::tempdir()?;
+    write_tree(&text, tree.path())?;
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let engines = [Engine::Literal, Engine::Regex, Engine::Tree(threads)];
+
+    writeln!(
+        out,
+        "searching {:.1} MiB for `{}`, best of {} runs",
+        mib(text.len()),
+        command.pattern,
+        command.runs
+    )?;
+    for engine in engines {
+        let runs = command.runs.max(1);
+        let (elapsed, summary) = time(engine, &command.pattern, &text, tree.path(), runs)?;
+        writeln!(
+            out,
+            "{:<20} {:>9.1} MiB/s {:>10} matching lines",
+            engine.name(),
+            mib(text.len()) / elapsed.as_secs_f64().max(1e-9),
+            summary.matched_lines
+        )?;
+    }
+    if let Some(path) = &command.path {
+        time_buffers(&mut out, command, path)?;
+    }
+
+    Ok(())
+}
+
+/// Times reading and searching the files at a path through each buffer size.
+fn time_buffers(out: &mut dyn Write, command: &Command, path: &Path) -> Result<()> {
+    let files = walk([path]).collect::<Result<Vec<_>, _>>()?;
+    let bytes = (files.iter())
+        .map(|file| std::fs::metadata(file).map_or(0, |metadata| metadata.len()))
+        .sum::<u64>() as usize;
+    writeln!(
+        out,
+        "reading {} files, {:.1} MiB, best of {} runs",
+        files.len(),
+        mib(bytes),
+        command.runs
+    )?;
+    let searcher = Searcher::default();
+    let matcher = searcher.literal(&command.pattern);
+    for &buffer in BUFFER_SIZES {
+        let mut best = Duration::MAX;
+        let mut summary = SearchSummary::default();
+        for _ in 0..command.runs.max(1) {
+            let start = Instant::now();
+            summary = read_files(&searcher, &matcher, &files, buffer)?;
+            best = best.min(start.elapsed());
+        }
+        let name = match buffer {
+            BufferSize::Fixed(size) => format!("{} KiB buffer", size / 1024),
+            BufferSize::Adaptive => "adaptive buffer".to_string(),
+        };
+        writeln!(
+            out,
+            "{:<20} {:>9.1} MiB/s {:>10} matching lines",
+            name,
+            mib(bytes) / best.as_secs_f64().max(1e-9),
+            summary.matched_lines
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Searches each file in turn, reading it through a buffer of the given size.
+fn read_files(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    files: &[PathBuf],
+    buffer: BufferSize,
+) -> Result<SearchSummary> {
+    files
+        .iter()
+        .try_fold(SearchSummary::default(), |total, file| {
+            let reader = open_input_with(file, buffer)?;
+            let mut sink = WriterSink::new(std::io::sink());
+            let summary = searcher.search_sink(matcher, reader, &mut sink)?;
+            Ok(SearchSummary {
+                total_lines: total.total_lines + summary.total_lines,
+                matched_lines: total.matched_lines + summary.matched_lines,
+            })
+        })
+}
+
+/// Generates about `size` bytes of lines of words, putting the pattern in
+/// every MATCH_EVERY-th line on average.
+fn generate(size: usize, pattern: &str) -> String {
+    // A fixed xorshift sequence keeps runs comparable between machines.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let mut text = String::with_capacity(size + 128);
+    while text.len() < size {
+        let words = 6 + next() % 10;
+        let matching = next() % MATCH_EVERY == 0;
+        let at = next() % words;
+        for i in 0..words {
+            if i > 0 {
+                text.push(' ');
+            }
+            match matching && i == at {
+                true => text.push_str(pattern),
+                false => text.push_str(WORDS[(next() % WORDS.len() as u64) as usize]),
+            }
+        }
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Writes the text out as files of about FILE_SIZE bytes, split at line
+/// breaks, FILES_PER_DIR to a directory.
+fn write_tree(text: &str, dir: &Path) -> Result<()> {
+    let mut rest = text;
+    for file in 0.. {
+        if rest.is_empty() {
+            break;
+        }
+        let at = FILE_SIZE.min(rest.len());
+        let end = (rest.as_bytes()[at..].iter().position(|&byte| byte == b'\n'))
+            .map_or(rest.len(), |end| at + end + 1);
+        let (part, tail) = rest.split_at(end);
+        let sub = dir.join((file / FILES_PER_DIR).to_string());
+        if file % FILES_PER_DIR == 0 {
+            std::fs::create_dir(&sub)?;
+        }
+        std::fs::write(sub.join(format!("{}.txt", file)), part)?;
+        rest = tail;
+    }
+
+    Ok(())
+}
+
+/// Times the fastest of several searches of the text, or the tree it's been
+/// written out as, with an engine.
+fn time(
+    engine: Engine,
+    pattern: &str,
+    text: &str,
+    tree: &Path,
+    runs: usize,
+) -> Result<(Duration, SearchSummary)> {
+    let searcher = Searcher::builder().regex(engine == Engine::Regex).build();
+    let matcher = searcher.matcher(pattern)?;
+    let mut best = Duration::MAX;
+    let mut summary = SearchSummary::default();
+    for _ in 0..runs {
+        let start = Instant::now();
+        summary = match engine {
+            Engine::Tree(threads) => search_files(&searcher, &*matcher, tree, threads)?,
+            _ => search(&searcher, &*matcher, text)?,
+        };
+        best = best.min(start.elapsed());
+    }
+
+    Ok((best, summary))
+}
+
+/// Searches text as `grrs search` would, discarding what it writes.
+fn search(searcher: &Searcher, matcher: &dyn Matcher, text: &str) -> Result<SearchSummary> {
+    let mut sink = WriterSink::new(std::io::sink());
+    Ok(searcher.search_sink(matcher, text.as_bytes(), &mut sink)?)
+}
+
+/// Searches the files under a directory as `grrs files` would, on this many
+/// threads, discarding what it writes.
+fn search_files(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    dir: &Path,
+    threads: usize,
+) -> Result<SearchSummary> {
+    let results = search_tree(
+        [dir],
+        threads,
+        &Default::default(),
+        |_| true,
+        |_, reader| searcher.search_sink(matcher, reader, &mut WriterSink::new(std::io::sink())),
+    );
+    if let Some((path, err)) = results.errors.first() {
+        return Err(anyhow!("could not search `{}`: {}", path.display(), err));
+    }
+
+    Ok(
+        (results.searched.iter()).fold(SearchSummary::default(), |total, (_, summary)| {
+            SearchSummary {
+                total_lines: total.total_lines + summary.total_lines,
+                matched_lines: total.matched_lines + summary.matched_lines,
+            }
+        }),
+    )
+}
+
+fn mib(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engines_agree() -> Result<()> {
+        let text = generate(FILE_SIZE * FILES_PER_DIR * 2, "needle");
+        let tree = tempfile::tempdir()?;
+        write_tree(&text, tree.path())?;
+        assert!(walk([tree.path()]).count() >= FILES_PER_DIR * 2);
+        assert!(tree.path().join("1").is_dir());
+        let mut found = Vec::new();
+        for engine in [Engine::Literal, Engine::Regex, Engine::Tree(3)] {
+            found.push(time(engine, "needle", &text, tree.path(), 1)?.1);
+        }
+        assert!(found[0].matched_lines > 0);
+        assert!(found.iter().all(|summary| *summary == found[0]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn buffer_sizes_agree() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("big.txt"), generate(256 * 1024, "needle"))?;
+        std::fs::write(dir.path().join("tiny.txt"), "a needle\n")?;
+        let files = walk([dir.path()]).collect::<Result<Vec<_>, _>>()?;
+        let searcher = Searcher::default();
+        let matcher = searcher.literal("needle");
+        let found = (BUFFER_SIZES.iter())
+            .map(|&buffer| read_files(&searcher, &matcher, &files, buffer))
+            .collect::<Result<Vec<_>>>()?;
+        assert!(found[0].matched_lines > 1);
+        assert!(found.iter().all(|summary| *summary == found[0]));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+//! Copying results to the system clipboard for `--clipboard`, rather than
+//! selecting them in the terminal.
+//!
+//! On Linux the clipboard is only kept once grrs exits while a clipboard
+//! manager is running, as with most desktops.
+
+use anyhow::{Context, Result};
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+/// The text written for the clipboard, which is copied all at once.
+#[derive(Debug, Clone, Default)]
+pub struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+impl Buffer {
+    /// Copies what's been written to the clipboard.
+    pub fn copy(&self) -> Result<()> {
+        let text = String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned();
+        copy(text)
+    }
+}
+
+impl Write for Buffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Replaces what's on the clipboard with the text.
+pub fn copy(text: String) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("could not open the clipboard")?;
+    clipboard
+        .set_text(text)
+        .context("could not copy to the clipboard")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_what_is_written() -> io::Result<()> {
+        let buffer = Buffer::default();
+        let mut writer = buffer.clone();
+        writer.write_all(b"LINE# 1: one\n")?;
+        writer.write_all(b"LINE# 4: four\n")?;
+        assert_eq!(&*buffer.0.lock().unwrap(), b"LINE# 1: one\nLINE# 4: four\n");
+
+        Ok(())
+    }
+}
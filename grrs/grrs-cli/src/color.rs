@@ -0,0 +1,58 @@
+//! `--color`, for highlighting matches when writing to a terminal.
+
+use anyhow::{anyhow, Result};
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// When to highlight matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Never,
+    /// Only when writing to a terminal, and `NO_COLOR` isn't set
+    Auto,
+    Always,
+}
+
+impl Color {
+    /// Reports whether matches written to stdout should be highlighted.
+    pub fn enabled(self) -> bool {
+        match self {
+            Color::Never => false,
+            Color::Auto => {
+                std::io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            }
+            Color::Always => true,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Color> {
+        match input {
+            "never" => Ok(Color::Never),
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            _ => Err(anyhow!(
+                "unknown color choice `{}`, expected `never`, `auto` or `always`",
+                input
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_when_to_color() -> Result<()> {
+        assert!("always".parse::<Color>()?.enabled());
+        assert!(!"never".parse::<Color>()?.enabled());
+        assert!("sometimes".parse::<Color>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+//! Comparing the lines two files match, for `grrs diff`, as sets of lines
+//! that may be in any order and have different spacing.
+
+use std::collections::HashSet;
+
+/// Returns a line as it's compared: trimmed, with each run of whitespace
+/// within it taken as a single space.
+pub fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the numbered lines of the first list that aren't in the other once
+/// normalized, in order, leaving out lines that repeat an earlier one.
+pub fn only_in<'l>(lines: &'l [(u64, String)], other: &[(u64, String)]) -> Vec<&'l (u64, String)> {
+    let other: HashSet<_> = other.iter().map(|(_, line)| normalize(line)).collect();
+    let mut seen = HashSet::new();
+
+    (lines.iter())
+        .filter(|(_, line)| {
+            let line = normalize(line);
+            !other.contains(&line) && seen.insert(line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(lines: &[&str]) -> Vec<(u64, String)> {
+        (1..)
+            .zip(lines.iter().map(|line| line.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn compare_normalized_lines() {
+        assert_eq!(normalize("  port =\t8080 "), "port = 8080");
+
+        let a = numbered(&["port = 8080", "host = a", "host = a", "debug = true"]);
+        let b = numbered(&["debug  = true", "port = 8080 ", "host = b"]);
+        assert_eq!(only_in(&a, &b), [&(2, "host = a".to_string())]);
+        assert_eq!(only_in(&b, &a), [&(3, "host = b".to_string())]);
+    }
+}
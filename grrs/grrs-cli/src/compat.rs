@@ -0,0 +1,34 @@
+//! `--compat`, for dropping grrs into scripts written for another tool.
+
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+
+/// The tool whose output and exit codes to imitate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compat {
+    /// `grep -Hn`: `path:line:text`, `Binary file path matches`, and exiting
+    /// with 1 when nothing matched or 2 on errors
+    Grep,
+}
+
+impl FromStr for Compat {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Compat> {
+        match input {
+            "grep" => Ok(Compat::Grep),
+            _ => Err(anyhow!("unknown tool `{}`, expected `grep`", input)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_tools() {
+        assert_eq!(Compat::from_str("grep").ok(), Some(Compat::Grep));
+        assert!(Compat::from_str("ack").is_err());
+    }
+}
@@ -0,0 +1,97 @@
+//! Opening matches in the user's editor.
+
+use anyhow::{anyhow, Context, Result};
+use grrs_core::sink::{Sink, SinkEvent};
+use grrs_core::GrrsError;
+use std::io::BufRead;
+use std::path::Path;
+use std::process::Command;
+
+/// Passes events on to another sink, remembering the line numbers of the
+/// matches among them.
+pub struct Recorder<S: Sink> {
+    sink: S,
+    pub matches: Vec<u64>,
+}
+
+impl<S: Sink> Recorder<S> {
+    pub fn new(sink: S) -> Recorder<S> {
+        Recorder {
+            sink,
+            matches: Vec::new(),
+        }
+    }
+}
+
+impl<S: Sink> Sink for Recorder<S> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { line_number, .. } = event {
+            self.matches.push(line_number);
+        }
+        self.sink.event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.sink.finish()
+    }
+}
+
+/// Asks which of several matches to open, counting from 1, and reads the
+/// answer from `input`; an empty answer picks the first. Returns the line
+/// number of the chosen match, or `None` if there were no matches.
+pub fn choose(matches: &[u64], mut input: impl BufRead) -> Result<Option<u64>> {
+    if matches.len() < 2 {
+        return Ok(matches.first().copied());
+    }
+    eprint!("Open which match (1-{}, default 1)? ", matches.len());
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let index = match answer.trim() {
+        "" => 1,
+        answer => answer
+            .parse()
+            .ok()
+            .filter(|index| (1..=matches.len()).contains(index))
+            .ok_or_else(|| {
+                anyhow!(
+                    "`{}` isn't a match number from 1 to {}",
+                    answer,
+                    matches.len()
+                )
+            })?,
+    };
+
+    Ok(Some(matches[index - 1]))
+}
+
+/// Opens a file at a line in `$EDITOR`, or in `vi` without one.
+pub fn open(path: &Path, line_number: u64) -> Result<()> {
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+    let mut words = editor.split_whitespace();
+    let status = Command::new(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(format!("+{}", line_number))
+        .arg(path)
+        .status()
+        .with_context(|| format!("could not run `{}`", editor))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(anyhow!("`{}` exited with {}", editor, status)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn choose_a_match() -> Result<()> {
+        assert_eq!(choose(&[], &b""[..])?, None);
+        assert_eq!(choose(&[4], &b""[..])?, Some(4));
+        assert_eq!(choose(&[4, 9], &b"\n"[..])?, Some(4));
+        assert_eq!(choose(&[4, 9], &b"2\n"[..])?, Some(9));
+        assert!(choose(&[4, 9], &b"3\n"[..]).is_err());
+
+        Ok(())
+    }
+}
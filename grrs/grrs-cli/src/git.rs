@@ -0,0 +1,426 @@
+//! Asking git which files to search, for `grrs files --git`, reading files
+//! as they were at a revision, for `--baseline-rev`, and searching the
+//! changes in its history, for `grrs log`.
+
+use anyhow::{anyhow, Context, Result};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// Which of the files git knows about to search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Every file in the index
+    Tracked,
+    /// Files added, copied, modified or renamed in the index, which is what
+    /// the next commit will change
+    Staged,
+    /// Tracked files whose working tree copy differs from the index
+    Modified,
+}
+
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Selection> {
+        match input {
+            "tracked" => Ok(Selection::Tracked),
+            "staged" => Ok(Selection::Staged),
+            "modified" => Ok(Selection::Modified),
+            _ => Err(anyhow!(
+                "unknown selection `{}`, expected `tracked`, `staged` or `modified`",
+                input
+            )),
+        }
+    }
+}
+
+/// Lists the selected files under the current directory, relative to it,
+/// limited to the supplied paths if there are any. Files are listed as git
+/// has them, but read from the working tree when searched.
+pub fn files(selection: Selection, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    files_in(Path::new("."), selection, paths)
+}
+
+fn files_in(dir: &Path, selection: Selection, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let args: &[&str] = match selection {
+        Selection::Tracked => &["ls-files", "-z"],
+        Selection::Staged => &[
+            "diff",
+            "--cached",
+            "--name-only",
+            "-z",
+            "--relative",
+            "--diff-filter=ACMR",
+        ],
+        Selection::Modified => &["diff", "--name-only", "-z", "--relative"],
+    };
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .arg("--")
+        .args(paths)
+        .output()
+        .context("could not run git")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], stderr.trim()));
+    }
+
+    Ok(parse(&output.stdout))
+}
+
+/// Splits git's NUL-separated list of paths.
+fn parse(output: &[u8]) -> Vec<PathBuf> {
+    output
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+        .collect()
+}
+
+/// A file as git has it at some revision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Blob {
+    /// The path the file would have in the working tree, under the path it
+    /// was listed for
+    pub path: PathBuf,
+    /// The directory git was run in to list it
+    dir: PathBuf,
+    id: String,
+}
+
+impl Blob {
+    /// Reads what the file held at the revision.
+    pub fn read(&self) -> Result<Vec<u8>> {
+        let output = Command::new("git")
+            .current_dir(&self.dir)
+            .args(["cat-file", "blob", &self.id])
+            .output()
+            .context("could not run git")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("git cat-file failed: {}", stderr.trim()));
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+/// Lists the files under each path as they were at a revision, in git's
+/// order. A path that didn't exist then lists nothing.
+pub fn files_at(revision: &str, paths: &[PathBuf]) -> Result<Vec<Blob>> {
+    let mut blobs = Vec::new();
+    for path in paths {
+        // git lists paths relative to the directory it's run in, which is
+        // the path itself for a directory, so they can be joined onto it.
+        let (base, spec) = match (path.is_dir(), path.parent(), path.file_name()) {
+            (false, Some(parent), Some(name)) => (parent, name),
+            _ => (path.as_path(), ".".as_ref()),
+        };
+        let dir = match base.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => base,
+        };
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["ls-tree", "-r", "-z", revision, "--"])
+            .arg(spec)
+            .output()
+            .context("could not run git")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("git ls-tree failed: {}", stderr.trim()));
+        }
+        // Each entry is `mode type id\tpath`, leaving out submodules.
+        for entry in output.stdout.split(|&byte| byte == 0) {
+            let entry = String::from_utf8_lossy(entry);
+            let blob = entry.split_once('\t').and_then(|(info, name)| {
+                let mut info = info.split(' ').skip(1);
+                match (info.next(), info.next()) {
+                    (Some("blob"), Some(id)) => Some(Blob {
+                        path: base.join(name),
+                        dir: dir.to_path_buf(),
+                        id: id.to_string(),
+                    }),
+                    _ => None,
+                }
+            });
+            blobs.extend(blob);
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// A commit that added or removed lines matching a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub id: String,
+    pub author: String,
+    /// The author date, as `YYYY-MM-DD`
+    pub date: String,
+    pub subject: String,
+    /// The matching lines, in the order of the commit's diff
+    pub changes: Vec<Change>,
+}
+
+/// A line added or removed by a commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub path: PathBuf,
+    pub added: bool,
+    pub text: String,
+}
+
+/// Which commits grrs::git::log() looks through, as git log would take them.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Only commits after this date, in any form git understands
+    pub since: Option<String>,
+    /// Only commits by authors matching this
+    pub author: Option<String>,
+}
+
+/// Walks the history of the current branch, newest first, calling back with
+/// each commit that added or removed a line the pattern matches. Merges are
+/// left out, as their changes are already in the commits merged.
+pub fn log<F>(filter: &LogFilter, matches: impl Fn(&str) -> bool, found: F) -> Result<()>
+where
+    F: FnMut(Commit) -> Result<()>,
+{
+    log_in(Path::new("."), filter, matches, found)
+}
+
+fn log_in<F>(
+    dir: &Path,
+    filter: &LogFilter,
+    matches: impl Fn(&str) -> bool,
+    mut found: F,
+) -> Result<()>
+where
+    F: FnMut(Commit) -> Result<()>,
+{
+    let mut command = Command::new("git");
+    command
+        .current_dir(dir)
+        .args([
+            "log",
+            "-p",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-renames",
+        ])
+        .args(["--date=short", "--format=%x00%H%x00%an%x00%ad%x00%s"]);
+    if let Some(since) = &filter.since {
+        command.arg(format!("--since={}", since));
+    }
+    if let Some(author) = &filter.author {
+        command.arg(format!("--author={}", author));
+    }
+    let mut child = (command.stdout(Stdio::piped()).stderr(Stdio::piped()))
+        .spawn()
+        .context("could not run git")?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+
+    let mut commit: Option<Commit> = None;
+    let mut path = PathBuf::new();
+    let mut in_hunk = false;
+    for line in BufReader::new(stdout).split(b'\n') {
+        let line = String::from_utf8_lossy(&line?).into_owned();
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        // Commits are introduced by the format above, which no diff line
+        // can start with.
+        if let Some(header) = line.strip_prefix('\0') {
+            if let Some(done) = commit.take().filter(|c| !c.changes.is_empty()) {
+                found(done)?;
+            }
+            let mut fields = header.splitn(4, '\0').map(str::to_string);
+            let mut field = || fields.next().unwrap_or_default();
+            commit = Some(Commit {
+                id: field(),
+                author: field(),
+                date: field(),
+                subject: field(),
+                changes: Vec::new(),
+            });
+            in_hunk = false;
+        } else if line.starts_with("diff --git ") {
+            in_hunk = false;
+        } else if line.starts_with("@@") {
+            in_hunk = true;
+        } else if !in_hunk {
+            // A deleted file is only named on the `---` line.
+            if let Some(name) =
+                (line.strip_prefix("--- a/")).or_else(|| line.strip_prefix("+++ b/"))
+            {
+                path = PathBuf::from(name);
+            }
+        } else if let Some((added, text)) = (line.strip_prefix('+').map(|text| (true, text)))
+            .or_else(|| line.strip_prefix('-').map(|text| (false, text)))
+        {
+            if let Some(commit) = commit.as_mut().filter(|_| matches(text)) {
+                commit.changes.push(Change {
+                    path: path.clone(),
+                    added,
+                    text: text.to_string(),
+                });
+            }
+        }
+    }
+    if let Some(done) = commit.filter(|c| !c.changes.is_empty()) {
+        found(done)?;
+    }
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git log failed: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn git(dir: &Path, args: &[&str]) -> Result<()> {
+        let status = Command::new("git").current_dir(dir).args(args).status()?;
+        match status.success() {
+            true => Ok(()),
+            false => Err(anyhow!("git {:?} failed", args)),
+        }
+    }
+
+    #[test]
+    fn list_selected_files() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), &["init", "-q"])?;
+        for name in [
+            "committed.txt",
+            "changed.txt",
+            "staged.txt",
+            "untracked.txt",
+        ] {
+            fs::write(dir.path().join(name), "before\n")?;
+        }
+        git(dir.path(), &["add", "committed.txt", "changed.txt"])?;
+        let identity = ["-c", "user.name=grrs", "-c", "user.email=grrs@example.com"];
+        git(
+            dir.path(),
+            &[&identity[..], &["commit", "-q", "-m", "first"]].concat(),
+        )?;
+        fs::write(dir.path().join("changed.txt"), "after\n")?;
+        git(dir.path(), &["add", "staged.txt"])?;
+
+        let list = |selection| files_in(dir.path(), selection, &[]);
+        assert_eq!(
+            list(Selection::Tracked)?,
+            [
+                PathBuf::from("changed.txt"),
+                "committed.txt".into(),
+                "staged.txt".into()
+            ]
+        );
+        assert_eq!(list(Selection::Staged)?, [PathBuf::from("staged.txt")]);
+        assert_eq!(list(Selection::Modified)?, [PathBuf::from("changed.txt")]);
+        let only = files_in(dir.path(), Selection::Tracked, &["c*".into()])?;
+        assert_eq!(only.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_files_at_a_revision() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), &["init", "-q"])?;
+        fs::create_dir(dir.path().join("sub"))?;
+        fs::write(dir.path().join("sub/a.txt"), "before\n")?;
+        fs::write(dir.path().join("b.txt"), "before\n")?;
+        git(dir.path(), &["add", "-A"])?;
+        let identity = ["-c", "user.name=grrs", "-c", "user.email=grrs@example.com"];
+        git(
+            dir.path(),
+            &[&identity[..], &["commit", "-q", "-m", "first"]].concat(),
+        )?;
+        fs::write(dir.path().join("sub/a.txt"), "after\n")?;
+        fs::remove_file(dir.path().join("b.txt"))?;
+
+        let paths = [dir.path().to_path_buf(), dir.path().join("b.txt")];
+        let blobs = files_at("HEAD", &paths)?;
+        let listed: Vec<_> = blobs.iter().map(|blob| blob.path.clone()).collect();
+        let (b, a) = (dir.path().join("b.txt"), dir.path().join("sub/a.txt"));
+        assert_eq!(listed, [b.clone(), a, b]);
+        assert_eq!(blobs[1].read()?, b"before\n");
+        assert!(files_at("HEAD", &[dir.path().join("new.txt")])?.is_empty());
+        assert!(files_at("no-such-revision", &paths).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_changes_in_history() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        git(dir.path(), &["init", "-q"])?;
+        let commit = |author: &str, message: &str| {
+            let name = format!("user.name={}", author);
+            let args = ["-c", &name, "-c", "user.email=grrs@example.com"];
+            git(dir.path(), &["add", "-A"])?;
+            git(
+                dir.path(),
+                &[&args[..], &["commit", "-q", "-m", message]].concat(),
+            )
+        };
+        fs::write(dir.path().join("notes.txt"), "keep\nTODO: tidy\n")?;
+        commit("ana", "Add notes")?;
+        fs::write(dir.path().join("notes.txt"), "keep\ntidied\n")?;
+        fs::write(dir.path().join("other.txt"), "no match\n")?;
+        commit("bo", "Tidy up")?;
+
+        let mut commits = Vec::new();
+        let filter = LogFilter::default();
+        log_in(
+            dir.path(),
+            &filter,
+            |line| line.contains("TODO"),
+            |c| {
+                commits.push(c);
+                Ok(())
+            },
+        )?;
+        let subjects: Vec<_> = commits.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["Tidy up", "Add notes"]);
+        assert_eq!(
+            commits[0].changes,
+            [Change {
+                path: "notes.txt".into(),
+                added: false,
+                text: "TODO: tidy".into()
+            }]
+        );
+        assert!(commits[1].changes[0].added);
+        assert_eq!(commits[1].author, "ana");
+
+        let mut commits = Vec::new();
+        let filter = LogFilter {
+            author: Some("ana".into()),
+            ..LogFilter::default()
+        };
+        log_in(
+            dir.path(),
+            &filter,
+            |line| line.contains("TODO"),
+            |c| {
+                commits.push(c);
+                Ok(())
+            },
+        )?;
+        assert_eq!(commits.len(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+//! The gRPC service of `grrs serve --grpc`, as proto/grrs.proto defines it,
+//! streaming each line found while the files are searched.
+
+use crate::metrics::Transport;
+use crate::serve::{search_cached, State};
+use grrs_core::sink::{Sink, SinkEvent};
+use grrs_core::{GrrsError, SearchOptions, Searcher};
+use proto::grrs_server::{Grrs, GrrsServer};
+use proto::search_response::Event;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+#[allow(clippy::all)]
+mod proto {
+    tonic::include_proto!("grrs");
+}
+
+/// How many responses are queued for a client before searching waits for it
+/// to read them.
+const QUEUED: usize = 64;
+
+/// Answers gRPC requests on a listener until accepting a connection fails.
+pub fn serve(listener: TcpListener, state: Arc<State>) -> anyhow::Result<()> {
+    listener.set_nonblocking(true)?;
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let incoming = TcpListenerStream::new(tokio::net::TcpListener::from_std(listener)?);
+        tonic::transport::Server::builder()
+            .add_service(GrrsServer::new(Service { state }))
+            .serve_with_incoming(incoming)
+            .await?;
+
+        Ok(())
+    })
+}
+
+struct Service {
+    state: Arc<State>,
+}
+
+#[tonic::async_trait]
+impl Grrs for Service {
+    type SearchStream = ReceiverStream<Result<proto::SearchResponse, Status>>;
+
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<Self::SearchStream>, Status> {
+        let start = Instant::now();
+        let request = request.into_inner();
+        let searcher = Searcher::new(options(request.options.unwrap_or_default()));
+        let matcher = (searcher.matcher(&request.pattern))
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+        let (sender, receiver) = mpsc::channel(QUEUED);
+        let state = self.state.clone();
+        tokio::task::spawn_blocking(move || {
+            for path in &request.paths {
+                let mut responses = Responses {
+                    path,
+                    sender: &sender,
+                };
+                let event = match search_cached(
+                    &state,
+                    &searcher,
+                    &*matcher,
+                    Path::new(path),
+                    &mut responses,
+                ) {
+                    Ok(summary) => Event::Summary(proto::Summary {
+                        matched_lines: summary.matched_lines,
+                        total_lines: summary.total_lines,
+                    }),
+                    Err(err) => Event::Error(err.to_string()),
+                };
+                // A client that's gone needn't have the rest searched.
+                if responses.send(event).is_err() {
+                    break;
+                }
+            }
+            state.metrics.searched(Transport::Grpc, start.elapsed());
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Sends each event of a file's search to the client as it's found.
+struct Responses<'a> {
+    path: &'a str,
+    sender: &'a mpsc::Sender<Result<proto::SearchResponse, Status>>,
+}
+
+impl Responses<'_> {
+    fn send(&self, event: Event) -> Result<(), GrrsError> {
+        let response = proto::SearchResponse {
+            path: self.path.to_string(),
+            event: Some(event),
+        };
+        self.sender.blocking_send(Ok(response)).map_err(|_| {
+            GrrsError::Io(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "the client stopped reading the search",
+            ))
+        })
+    }
+}
+
+impl Sink for Responses<'_> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        self.send(match event {
+            SinkEvent::Match { line_number, line } => Event::Match(proto::Line {
+                line_number,
+                line: line.into_owned(),
+            }),
+            SinkEvent::Context { line_number, line } => Event::Context(proto::Line {
+                line_number,
+                line: line.into_owned(),
+            }),
+            SinkEvent::Break => Event::Break(proto::Break {}),
+            SinkEvent::Heading { line_number, line } => Event::Heading(proto::Line {
+                line_number,
+                line: line.into_owned(),
+            }),
+        })
+    }
+}
+
+fn options(options: proto::Options) -> SearchOptions {
+    SearchOptions {
+        ignore_case: options.ignore_case,
+        smart_case: options.smart_case,
+        regex: options.regex,
+        word_regexp: options.word_regexp,
+        invert_match: options.invert_match,
+        before_context: options.before_context as usize,
+        after_context: options.after_context as usize,
+        context: options.context.map(|lines| lines as usize),
+        max_count: options.max_count,
+        no_simd: false,
+        after_context_until: None,
+        passthru: false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_search_results() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("notes.txt");
+        std::fs::write(&path, "A test\nActual content\n")?;
+        let service = Service {
+            state: Arc::new(State::new(dir.path().canonicalize()?)),
+        };
+        let search = |pattern: &str, options: proto::Options| {
+            service.search(Request::new(proto::SearchRequest {
+                pattern: pattern.to_string(),
+                paths: vec![path.to_string_lossy().into_owned(), "missing.txt".into()],
+                options: Some(options),
+            }))
+        };
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let (events, invalid) = runtime.block_on(async {
+            let ignoring_case = proto::Options {
+                ignore_case: true,
+                ..Default::default()
+            };
+            let mut responses = search("TEST", ignoring_case)
+                .await?
+                .into_inner()
+                .into_inner();
+            let mut events = Vec::new();
+            while let Some(response) = responses.recv().await {
+                events.push(response?.event);
+            }
+            let regex = proto::Options {
+                regex: true,
+                ..Default::default()
+            };
+
+            Ok::<_, Status>((events, search("(", regex).await.unwrap_err()))
+        })?;
+
+        assert_eq!(
+            events,
+            [
+                Event::Match(proto::Line {
+                    line_number: 1,
+                    line: "A test".into(),
+                }),
+                Event::Summary(proto::Summary {
+                    matched_lines: 1,
+                    total_lines: 2,
+                }),
+                Event::Error("`missing.txt` does not exist".into()),
+            ]
+            .map(Some)
+        );
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+
+        Ok(())
+    }
+}
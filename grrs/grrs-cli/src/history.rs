@@ -0,0 +1,84 @@
+//! The opt-in record of searched patterns, kept one per line in
+//! `~/.local/share/grrs/history` and recalled by number with `!N`.
+
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Returns where the history is kept: `grrs/history` under `$XDG_DATA_HOME`,
+/// or under `~/.local/share` when that isn't set.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(env::var_os("HOME")?).join(".local/share"),
+    };
+
+    Some(dir.join("grrs").join("history"))
+}
+
+/// Reads the recorded patterns, oldest first, or none if there's no history.
+pub fn load(path: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.lines().map(str::to_string).collect()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => {
+            Err(err).with_context(|| format!("could not read history `{}`", path.display()))
+        }
+    }
+}
+
+/// Adds a pattern to the end of the history. Patterns spanning several lines
+/// can't be told apart from several patterns, so they aren't recorded.
+pub fn record(path: &Path, pattern: &str) -> Result<()> {
+    if pattern.contains('\n') {
+        return Ok(());
+    }
+    let context = || format!("could not write history `{}`", path.display());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(context)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(context)?;
+    writeln!(file, "{}", pattern).with_context(context)
+}
+
+/// Looks up a pattern written as `!N`, the Nth recorded pattern counting from
+/// 1. Returns `None` for any other pattern, which is used as it is.
+pub fn recall<'h>(history: &'h [String], pattern: &str) -> Result<Option<&'h str>> {
+    let number = match pattern.strip_prefix('!').map(str::parse::<usize>) {
+        Some(Ok(number)) => number,
+        _ => return Ok(None),
+    };
+    match number.checked_sub(1).and_then(|index| history.get(index)) {
+        Some(pattern) => Ok(Some(pattern)),
+        None => Err(anyhow!("there's no pattern {} in the history", number)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_and_recall() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("grrs/history");
+        assert!(load(&path)?.is_empty());
+        record(&path, "first")?;
+        record(&path, "two\nlines")?;
+        record(&path, r"\d+ (second|2nd)")?;
+        let history = load(&path)?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(recall(&history, "!2")?, Some(r"\d+ (second|2nd)"));
+        assert_eq!(recall(&history, "!important")?, None);
+        assert!(recall(&history, "!0").is_err());
+        assert!(recall(&history, "!3").is_err());
+
+        Ok(())
+    }
+}
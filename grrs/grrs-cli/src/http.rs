@@ -0,0 +1,240 @@
+//! Just enough HTTP/1.1 for `grrs serve --http`: one request per connection,
+//! with a body of known length, answered whole or in chunks.
+
+use std::io::{self, BufRead, Read, Write};
+
+/// The largest request body read, as search requests are only ever small.
+const MAX_BODY: u64 = 1024 * 1024;
+
+/// The longest request line or header line read, including its line ending.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// A request that's been read in full.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// What follows the `?` in the target, if anything
+    pub query: String,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Reports whether the query has a parameter set to `true` or `1`.
+    pub fn flag(&self, name: &str) -> bool {
+        self.query
+            .split('&')
+            .any(|pair| match pair.split_once('=') {
+                Some((key, value)) => key == name && (value == "true" || value == "1"),
+                None => pair == name,
+            })
+    }
+}
+
+/// Why a request couldn't be read, as the status to answer with.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub status: u16,
+    pub message: String,
+}
+
+impl Rejection {
+    pub fn new(status: u16, message: impl Into<String>) -> Rejection {
+        Rejection {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<io::Error> for Rejection {
+    fn from(err: io::Error) -> Rejection {
+        Rejection::new(400, format!("could not read the request: {}", err))
+    }
+}
+
+/// Reads a request's head and its body.
+pub fn read_request(reader: &mut impl BufRead) -> Result<Request, Rejection> {
+    let mut line = String::new();
+    let too_long = Rejection::new(400, "the request line is too long");
+    read_line(reader, &mut line, too_long)?;
+    let mut parts = line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method.to_string(), target.to_string())
+        }
+        _ => return Err(Rejection::new(400, "malformed request line")),
+    };
+
+    let mut length = None;
+    loop {
+        line.clear();
+        let too_long = Rejection::new(431, "a header is too long");
+        if read_line(reader, &mut line, too_long)? == 0 {
+            return Err(Rejection::new(400, "the headers didn't end"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = (header.split_once(':'))
+            .ok_or_else(|| Rejection::new(400, format!("malformed header `{}`", header)))?;
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                let value = value.trim().parse::<u64>();
+                length = Some(value.map_err(|_| Rejection::new(400, "invalid Content-Length"))?);
+            }
+            "transfer-encoding" => {
+                return Err(Rejection::new(411, "send the body with a Content-Length"));
+            }
+            _ => {}
+        }
+    }
+    let length = length.unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(Rejection::new(413, "the body is too large"));
+    }
+    let mut body = Vec::with_capacity(length as usize);
+    reader.take(length).read_to_end(&mut body)?;
+    if body.len() as u64 != length {
+        return Err(Rejection::new(400, "the body ended early"));
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query: query.to_string(),
+        body,
+    })
+}
+
+/// Reads a line of the head, up to MAX_LINE bytes, rejecting a longer one
+/// as `too_long`.
+fn read_line(
+    reader: &mut impl BufRead,
+    line: &mut String,
+    too_long: Rejection,
+) -> Result<usize, Rejection> {
+    let read = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    match read as u64 == MAX_LINE && !line.ends_with('\n') {
+        true => Err(too_long),
+        false => Ok(read),
+    }
+}
+
+/// Writes a whole response, closing the connection after it.
+pub fn respond(
+    out: &mut impl Write,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        reason(status),
+        content_type,
+        body.len()
+    )?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+/// Starts a response whose body is sent in chunks as it's written, one per
+/// call to write(). It ends once Chunked::finish() is called.
+pub struct Chunked<W: Write>(W);
+
+impl<W: Write> Chunked<W> {
+    pub fn start(mut out: W, content_type: &str) -> io::Result<Chunked<W>> {
+        write!(
+            out,
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+            content_type
+        )?;
+        Ok(Chunked(out))
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.0.write_all(b"0\r\n\r\n")?;
+        self.0.flush()
+    }
+}
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // An empty chunk would end the body.
+        if !buf.is_empty() {
+            write!(self.0, "{:x}\r\n", buf.len())?;
+            self.0.write_all(buf)?;
+            self.0.write_all(b"\r\n")?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_and_answer_requests() -> io::Result<()> {
+        let mut input =
+            &b"POST /search?stream=1 HTTP/1.1\r\nHost: x\r\nContent-Length: 2\r\n\r\n{}"[..];
+        let request = read_request(&mut input).unwrap();
+        assert_eq!(
+            (
+                request.method.as_str(),
+                request.path.as_str(),
+                &request.body[..]
+            ),
+            ("POST", "/search", &b"{}"[..])
+        );
+        assert!(request.flag("stream") && !request.flag("pretty"));
+
+        let mut input = &b"POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n{}"[..];
+        assert_eq!(read_request(&mut input).unwrap_err().status, 400);
+        let mut input = &b"GET /\r\n\r\n"[..];
+        assert_eq!(read_request(&mut input).unwrap_err().status, 400);
+
+        // Lines of the head longer than MAX_LINE aren't read in full.
+        let long = "x".repeat(MAX_LINE as usize);
+        let input = format!("GET /{} HTTP/1.1\r\n\r\n", long);
+        assert_eq!(read_request(&mut input.as_bytes()).unwrap_err().status, 400);
+        let input = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", long);
+        assert_eq!(read_request(&mut input.as_bytes()).unwrap_err().status, 431);
+        let input = format!("GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n", &long[..100]);
+        assert!(read_request(&mut input.as_bytes()).is_ok());
+
+        let mut output = Vec::new();
+        respond(&mut output, 404, "text/plain", b"gone")?;
+        assert!(output.starts_with(b"HTTP/1.1 404 Not Found\r\n"));
+        assert!(output.ends_with(b"Content-Length: 4\r\nConnection: close\r\n\r\ngone"));
+
+        let mut output = Vec::new();
+        let mut chunked = Chunked::start(&mut output, "application/x-ndjson")?;
+        chunked.write_all(b"{}\n")?;
+        chunked.finish()?;
+        assert!(output.ends_with(b"\r\n\r\n3\r\n{}\n\r\n0\r\n\r\n"));
+
+        Ok(())
+    }
+}
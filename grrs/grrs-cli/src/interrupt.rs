@@ -0,0 +1,63 @@
+//! Stopping a search cleanly on Ctrl-C, so what was found so far is still
+//! written out, along with a note of how far the search got.
+
+use anyhow::{Context, Result};
+use std::fmt::Display;
+use std::io::{self, BufRead, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches Ctrl-C from now on, so a search stops before its next line. A
+/// second Ctrl-C exits straight away.
+pub fn catch() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    })
+    .context("could not catch Ctrl-C")
+}
+
+/// Reports whether Ctrl-C has been pressed.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Exits as if killed by Ctrl-C, saying how far the search got, if it was
+/// interrupted. Output must already be flushed.
+pub fn exit_if_interrupted(progress: impl Display) {
+    if interrupted() {
+        eprintln!("grrs: interrupted after {}", progress);
+        std::process::exit(130);
+    }
+}
+
+/// Reads as the inner reader does until Ctrl-C, and then as if at the end, so
+/// a search of a buffer at a time stops before its next buffer.
+pub struct Reader<R>(pub R);
+
+impl<R: Read> Read for Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.0.read(buf)?;
+        match interrupted() {
+            true => Ok(0),
+            false => Ok(read),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for Reader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        // Reading may have waited for input until after Ctrl-C.
+        let buffer = self.0.fill_buf()?;
+        match interrupted() {
+            true => Ok(&[]),
+            false => Ok(buffer),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
@@ -0,0 +1,1975 @@
+use anyhow::{anyhow, Context, Error, Result};
+use clap_verbosity_flag::Verbosity;
+use color::Color;
+use compat::Compat;
+use grrs_core::config::Config;
+use grrs_core::encode::Encoding;
+use grrs_core::fields::{json_path, Condition, FieldFilter, FieldMatcher, LineFormat};
+use grrs_core::format::{
+    ColorFormatter, ColumnFormatter, ColumnUnit, CrlfFormatter, Formatter, FzfFormatter,
+    GrepFormatter, JsonFormatter, LineFormatter, OnlyMatchingFormatter, PatternJsonFormatter,
+    RegionJsonFormatter, TimestampFormatter,
+};
+use grrs_core::glob::Glob;
+use grrs_core::heading;
+use grrs_core::index;
+use grrs_core::matcher::{Matcher, Overlapping, PatternSet, RegexMatcher};
+use grrs_core::record;
+use grrs_core::sink::{
+    CollectSink, FileSink, LineStats, PatternHits, Sink, SinkEvent, StatsSink, WriterSink,
+};
+use grrs_core::timestamp::{parse_bound, TimeWindow, DEFAULT_FORMAT, DEFAULT_REGEX};
+use grrs_core::window::WindowSearcher;
+use grrs_core::{
+    display_path, numbered_lines, numbered_lines_lossy, open_bytes, open_input_with, open_sniffed,
+    preserve_case, purge_file, replace_matches_with, search_files, search_tree, walk, BufferPool,
+    BufferSize, FilePolicy, GrrsError, OpenFailure, SearchOptions, SearchSummary, Searcher,
+};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io::{BufRead, BufWriter, ErrorKind, Read, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use structopt::clap::{App, AppSettings, ArgMatches};
+use structopt::StructOpt;
+
+mod bench;
+#[cfg(feature = "clipboard")]
+mod clipboard;
+mod color;
+mod compare;
+mod compat;
+mod editor;
+mod git;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod history;
+mod http;
+mod interrupt;
+mod metrics;
+#[cfg(feature = "notify")]
+mod notify;
+#[cfg(feature = "plugins")]
+mod plugin;
+#[cfg(feature = "pretty")]
+mod pretty;
+mod progress;
+mod saved;
+#[cfg(feature = "script")]
+mod script;
+mod serve;
+mod session;
+mod throttle;
+#[cfg(feature = "trace")]
+mod trace;
+#[cfg(feature = "tui")]
+mod tui;
+
+/// Search for patterns in files.
+const ABOUT: &str =
+    "Search for patterns in files. `grrs <pattern> <path>` is short for `grrs search <pattern> <path>`.";
+
+/// The numbered lines of a file.
+type Lines = Box<dyn Iterator<Item = Result<(u64, String), GrrsError>>>;
+
+/// A subcommand's name, about text and parser.
+type Subcommand = (&'static str, &'static str, fn() -> App<'static, 'static>);
+
+/// Every subcommand, the first being the default.
+const SUBCOMMANDS: &[Subcommand] = &[
+    (
+        "search",
+        "Display the lines of a file that contain a pattern",
+        Search::clap,
+    ),
+    (
+        "count",
+        "Display how many lines of a file contain a pattern",
+        Count::clap,
+    ),
+    (
+        "replace",
+        "Display a file with every match of a pattern replaced",
+        Replace::clap,
+    ),
+    (
+        "files",
+        "Display which of the supplied files contain a pattern",
+        Files::clap,
+    ),
+    (
+        "find",
+        "Display the files whose names match a glob, looking through directories",
+        Find::clap,
+    ),
+    (
+        "index",
+        "Index the files under some paths, so `files --index` can skip those that can't match",
+        Index::clap,
+    ),
+    (
+        "diff",
+        "Display the matching lines found in only one of two files",
+        Diff::clap,
+    ),
+    (
+        "log",
+        "Display the commits that added or removed lines containing a pattern, like `git log -S`. \
+         Not `history`, which lists the recorded patterns",
+        Log::clap,
+    ),
+    (
+        "interactive",
+        "Read a file once, then search it for each pattern typed at the prompt",
+        Interactive::clap,
+    ),
+    (
+        "session",
+        "Display, filter or compare the results saved by --save-session",
+        session::Command::clap,
+    ),
+    (
+        "bench",
+        "Time each way of searching on generated text or a file",
+        bench::Command::clap,
+    ),
+    (
+        "serve",
+        "Answer searches sent over a Unix socket or HTTP, keeping the files read in memory",
+        serve::Command::clap,
+    ),
+    (
+        "history",
+        "List the recorded patterns, numbered as they're recalled with `!N`",
+        History::clap,
+    ),
+    (
+        "pattern",
+        "Save patterns under names, to search for with `--use <name>`",
+        saved::Command::clap,
+    ),
+    #[cfg(feature = "tui")]
+    (
+        "tui",
+        "Browse the matches in a file in full screen, searching again as the pattern is typed",
+        Tui::clap,
+    ),
+];
+
+/// The subcommands that may also be given as a leading flag, like
+/// `--interactive`.
+const AS_FLAGS: &[&str] = &["interactive", "history", "tui"];
+
+/// The other arguments that may come first without being taken for a search
+/// pattern.
+const TOP_LEVEL: &[&str] = &["help", "-h", "--help", "-V", "--version"];
+
+// The parser is put together by app(), as the about texts of flattened option
+// structs would otherwise override those of the subcommands.
+#[derive(Debug, StructOpt)]
+enum Cli {
+    Search(Search),
+    Count(Count),
+    Replace(Replace),
+    Files(Files),
+    Find(Find),
+    Index(Index),
+    Diff(Diff),
+    Log(Log),
+    Interactive(Interactive),
+    Session(session::Command),
+    History(History),
+    Pattern(saved::Command),
+    Bench(bench::Command),
+    Serve(serve::Command),
+    #[cfg(feature = "tui")]
+    Tui(Tui),
+}
+
+#[derive(Debug, StructOpt)]
+struct Search {
+    /// The pattern to look for
+    pattern: String,
+    /// The path to the file to read
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    #[structopt(flatten)]
+    common: Common,
+    #[structopt(flatten)]
+    filters: Filters,
+
+    /// Also look for this pattern. Each pattern is highlighted in its own
+    /// color, and JSON output lists which patterns each match is for
+    #[structopt(
+        short = "e",
+        long = "regexp",
+        value_name = "pattern",
+        number_of_values = 1,
+        conflicts_with_all = &["save-session", "baseline", "baseline-rev"]
+    )]
+    patterns: Vec<String>,
+
+    /// Find matches that overlap, such as both `aba`s in `ababa`, for
+    /// --only-matching and highlighting
+    #[structopt(long)]
+    overlapping: bool,
+
+    /// Display each match on a line of its own instead of the whole line
+    #[structopt(long, conflicts_with_all = &["json", "fzf", "column", "window"])]
+    only_matching: bool,
+
+    /// The path to the output file to write to
+    #[structopt(short, long, parse(from_os_str))]
+    outfile: Option<PathBuf>,
+
+    /// Write the output file in `utf-16le`, which Windows tools call
+    /// Unicode, `utf-16be` or `latin1` rather than `utf-8`
+    #[structopt(long, value_name = "encoding", requires = "outfile")]
+    output_encoding: Option<Encoding>,
+
+    /// When to highlight matches: `never`, `auto` for when writing to a
+    /// terminal, or `always`
+    #[structopt(long, value_name = "when", default_value = "auto")]
+    color: Color,
+
+    /// Write each match as a JSON object on its own line
+    #[structopt(long)]
+    json: bool,
+
+    /// Write each block of matches and their context as a JSON object on its
+    /// own line, with the range of lines it covers, merging blocks whose
+    /// context overlaps
+    #[structopt(long, conflicts_with_all = &["json", "fzf", "column", "only-matching"])]
+    json_regions: bool,
+
+    /// Write each match as `path:line:column:text` for piping into fzf
+    #[structopt(long, conflicts_with = "json")]
+    fzf: bool,
+
+    /// Display the column of the first match on each line, as `LINE# 4:7: text`
+    #[structopt(long, conflicts_with_all = &["json", "fzf"])]
+    column: bool,
+
+    /// Count columns in bytes instead of characters, for --column and --fzf
+    #[structopt(long)]
+    byte_columns: bool,
+
+    /// Read lines of any length in bounded memory, displaying this many bytes
+    /// either side of each match instead of the whole line
+    #[structopt(long, value_name = "bytes", conflicts_with_all = &["fzf", "column"])]
+    window: Option<usize>,
+
+    /// Display the nearest heading above each group of matches, such as the
+    /// function or section they're in
+    #[structopt(short = "p", long, conflicts_with = "window")]
+    show_function: bool,
+
+    /// The regex matching the lines taken as headings, for --show-function
+    #[structopt(long, default_value = heading::DEFAULT_REGEX)]
+    heading_regex: String,
+
+    /// Search paragraphs, separated by blank lines, displaying the whole of
+    /// each with a line that matches
+    #[structopt(long, conflicts_with_all = &["window", "show-function"])]
+    paragraph: bool,
+
+    /// Search records separated by the lines matching this regex, such as
+    /// `^%$`, displaying the whole of each with a line that matches
+    #[structopt(
+        long,
+        value_name = "regex",
+        conflicts_with_all = &["window", "show-function", "paragraph"]
+    )]
+    record_separator: Option<String>,
+
+    /// Start each line written with the local time it was written, for lines
+    /// read as they arrive, such as from a log being followed through
+    /// /dev/stdin, that lack times of their own
+    #[structopt(long)]
+    timestamps: bool,
+
+    /// Write no more than this many matching lines a second, minute or hour,
+    /// as `100/s`, `600/m` or `1000/h`, dropping the rest and saying how many
+    /// were dropped on stderr
+    #[structopt(long, value_name = "rate")]
+    rate_limit: Option<throttle::Rate>,
+
+    /// End each line written with \r\n instead of \n
+    #[structopt(long)]
+    crlf: bool,
+
+    /// Open a match in $EDITOR afterwards, asking which if there are several
+    #[structopt(long)]
+    edit: bool,
+
+    /// Write each event as this plugin from ~/.config/grrs/plugins formats it
+    #[cfg(feature = "plugins")]
+    #[structopt(long, value_name = "plugin", conflicts_with_all = &["json", "fzf", "column"])]
+    formatter: Option<String>,
+
+    /// Highlight the syntax of each line written, going by the file's
+    /// extension
+    #[cfg(feature = "pretty")]
+    #[structopt(
+        long,
+        conflicts_with_all = &["json", "fzf", "column", "compat", "json-regions", "only-matching"]
+    )]
+    pretty: bool,
+
+    /// Save the options and results to this file, for `grrs session`
+    #[structopt(long, parse(from_os_str))]
+    save_session: Option<PathBuf>,
+
+    /// Display only the matches that differ from those saved in this session
+    /// file, failing if any are new
+    #[structopt(
+        long,
+        parse(from_os_str),
+        conflicts_with_all = &["outfile", "json", "fzf", "column", "edit"]
+    )]
+    baseline: Option<PathBuf>,
+
+    /// Display only the matches that differ from those in the file as git
+    /// has it at this revision, such as `main`, failing if any are new
+    #[structopt(
+        long,
+        value_name = "revision",
+        conflicts_with_all = &["baseline", "outfile", "json", "fzf", "column", "edit"]
+    )]
+    baseline_rev: Option<String>,
+
+    /// Send a desktop notification once the search is done, if it took at
+    /// least this many seconds
+    #[cfg(feature = "notify")]
+    #[structopt(long, value_name = "seconds")]
+    notify: Option<u64>,
+
+    /// Write and exit like another tool, for scripts that parse its output:
+    /// `grep` writes `path:line:text` as `grep -Hn` does
+    #[structopt(
+        long,
+        value_name = "tool",
+        conflicts_with_all = &[
+            "json",
+            "fzf",
+            "column",
+            "window",
+            "baseline",
+            "baseline-rev",
+            "json-regions",
+            "only-matching"
+        ]
+    )]
+    compat: Option<Compat>,
+
+    /// Copy the results to the clipboard instead of displaying them
+    #[cfg(feature = "clipboard")]
+    #[structopt(long, conflicts_with_all = &["outfile", "baseline", "baseline-rev"])]
+    clipboard: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Count {
+    /// The pattern to look for
+    pattern: String,
+    /// The paths to the files to read. With several, each file's count is
+    /// written as `path:count`, followed by `total:count`
+    #[structopt(parse(from_os_str), required = true)]
+    paths: Vec<PathBuf>,
+    #[structopt(flatten)]
+    common: Common,
+    #[structopt(flatten)]
+    filters: Filters,
+
+    /// Count every match instead of the lines that match, so a line with
+    /// three matches counts three
+    #[structopt(long)]
+    count_matches: bool,
+
+    /// Count the lines, words and bytes of the lines that match, as `wc`
+    /// would, writing them as `lines words bytes` ahead of any path
+    #[structopt(long, conflicts_with = "count-matches")]
+    wc: bool,
+
+    /// Send a desktop notification once the search is done, if it took at
+    /// least this many seconds
+    #[cfg(feature = "notify")]
+    #[structopt(long, value_name = "seconds")]
+    notify: Option<u64>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Replace {
+    /// The pattern to look for
+    pattern: String,
+    /// The text to replace each match with
+    replacement: String,
+    /// The path to the file to read
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    #[structopt(flatten)]
+    common: Common,
+
+    /// End each line written with \r\n instead of \n
+    #[structopt(long, conflicts_with = "plan-json")]
+    crlf: bool,
+
+    /// Write each replacement that would be made as a JSON object a line,
+    /// with its path, line number, byte range in the line and old and new
+    /// text, instead of the replaced lines
+    #[structopt(long)]
+    plan_json: bool,
+
+    /// Match the pattern regardless of letter case, and give each
+    /// replacement the case of the text it replaces, so replacing `color`
+    /// with `colour` turns `Color` into `Colour` and `COLOR` into `COLOUR`
+    #[structopt(long)]
+    preserve_case: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Files {
+    /// The pattern to look for
+    pattern: String,
+    /// The paths to the files to read, or with --git to limit its files to
+    #[structopt(parse(from_os_str), required_unless = "git")]
+    paths: Vec<PathBuf>,
+    #[structopt(flatten)]
+    common: Common,
+    #[structopt(flatten)]
+    filters: Filters,
+
+    /// Search the files git lists as `tracked`, `staged` or `modified`,
+    /// reading what's in the working tree
+    #[structopt(long, value_name = "selection")]
+    git: Option<git::Selection>,
+
+    /// Only list the files that also contain this pattern, anywhere in the
+    /// file rather than on the same line. Can be given more than once
+    #[structopt(long, value_name = "pattern", number_of_values = 1)]
+    files_matching_all: Vec<String>,
+
+    /// Only search the files whose names match this glob, such as `*.toml`,
+    /// looking through the directories among the paths for them. Can be given
+    /// more than once
+    #[structopt(
+        short = "g",
+        long = "glob",
+        value_name = "glob",
+        number_of_values = 1,
+        parse(try_from_str = Glob::new)
+    )]
+    globs: Vec<Glob>,
+
+    /// Only read the files an index written by `grrs index` shows may
+    /// contain the patterns, and those changed since it was written. Regular
+    /// expressions, --invert-match and parsed fields read every file
+    #[structopt(long, value_name = "path", parse(from_os_str))]
+    index: Option<PathBuf>,
+
+    /// Save the matches in each file to this file, for `grrs session` and
+    /// --baseline
+    #[structopt(long, parse(from_os_str))]
+    save_session: Option<PathBuf>,
+
+    /// Display only the matches that differ from those saved in this session
+    /// file, under the path of each file, instead of the files that match,
+    /// failing if any are new
+    #[structopt(long, parse(from_os_str))]
+    baseline: Option<PathBuf>,
+
+    /// Display only the matches that differ from those in the files as git
+    /// has them at this revision, such as `main`, as --baseline does
+    #[structopt(long, value_name = "revision", conflicts_with = "baseline")]
+    baseline_rev: Option<String>,
+
+    /// Don't report the files that couldn't be read
+    #[structopt(short = "s", long)]
+    no_messages: bool,
+
+    /// Display how many files were searched, matched and skipped afterwards
+    #[structopt(long)]
+    stats: bool,
+
+    /// Write a JSON object to stderr every so often while searching, and once
+    /// done, with how many files were searched, the one last started on and
+    /// how fast they're being read
+    #[structopt(long)]
+    progress_json: bool,
+
+    /// Skip files that can't be opened for this reason without failing:
+    /// `missing`, `denied`, `directory` or `binary`
+    #[structopt(long, value_name = "reason", number_of_values = 1)]
+    skip: Vec<OpenFailure>,
+
+    /// Stop at the first file that can't be opened for this reason
+    #[structopt(long, value_name = "reason", number_of_values = 1)]
+    abort_on: Vec<OpenFailure>,
+
+    /// Stop once more than this many files, or directories looked through
+    /// for --glob, couldn't be read
+    #[structopt(long, value_name = "count")]
+    max_errors: Option<usize>,
+
+    /// How many threads look through directories and search files at once
+    /// for --glob, by default one for each CPU
+    #[structopt(short = "j", long, value_name = "count")]
+    threads: Option<usize>,
+
+    /// Send a desktop notification once the search is done, if it took at
+    /// least this many seconds
+    #[cfg(feature = "notify")]
+    #[structopt(long, value_name = "seconds")]
+    notify: Option<u64>,
+
+    /// Copy the paths of the matching files to the clipboard instead of
+    /// displaying them
+    #[cfg(feature = "clipboard")]
+    #[structopt(long, conflicts_with_all = &["baseline", "baseline-rev"])]
+    clipboard: bool,
+}
+
+#[derive(Debug, StructOpt)]
+struct Find {
+    /// The glob to match file names against, such as `*.toml`, or the end of
+    /// the path if it has a `/`. Can be given more than once
+    #[structopt(
+        short = "g",
+        long = "glob",
+        value_name = "glob",
+        number_of_values = 1,
+        required = true,
+        parse(try_from_str = Glob::new)
+    )]
+    globs: Vec<Glob>,
+    /// The files and directories to look through
+    #[structopt(parse(from_os_str), default_value = ".")]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Index {
+    /// The files and directories to index
+    #[structopt(parse(from_os_str), default_value = ".")]
+    paths: Vec<PathBuf>,
+    /// The path to write the index to
+    #[structopt(short, long, parse(from_os_str), default_value = ".grrs-index")]
+    output: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+struct Diff {
+    /// The pattern to look for
+    pattern: String,
+    /// The path to the first file to read
+    #[structopt(parse(from_os_str))]
+    a: PathBuf,
+    /// The path to the second file to read
+    #[structopt(parse(from_os_str))]
+    b: PathBuf,
+    #[structopt(flatten)]
+    common: Common,
+    #[structopt(flatten)]
+    filters: Filters,
+}
+
+#[derive(Debug, StructOpt)]
+struct Log {
+    /// The pattern to look for
+    pattern: String,
+    #[structopt(flatten)]
+    common: Common,
+
+    /// Only look through commits made after this date, such as `2024-01-01`
+    /// or `2.weeks`
+    #[structopt(long, value_name = "date")]
+    since: Option<String>,
+
+    /// Only look through commits by authors whose name or email matches this
+    #[structopt(long, value_name = "name")]
+    author: Option<String>,
+}
+
+#[derive(Debug, StructOpt)]
+struct Interactive {
+    /// The path to the file to read
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    #[structopt(flatten)]
+    common: Common,
+    #[structopt(flatten)]
+    filters: Filters,
+}
+
+#[derive(Debug, StructOpt)]
+struct History {}
+
+#[cfg(feature = "tui")]
+#[derive(Debug, StructOpt)]
+struct Tui {
+    /// The path to the file to read
+    #[structopt(parse(from_os_str))]
+    path: PathBuf,
+    #[structopt(flatten)]
+    common: Common,
+}
+
+/// Flags taken by every subcommand.
+#[derive(Debug, StructOpt)]
+struct Common {
+    #[structopt(flatten)]
+    verbose: Verbosity,
+    #[structopt(flatten)]
+    options: SearchOptions,
+
+    /// Replace bytes that aren't valid UTF-8 instead of stopping at them
+    #[structopt(long)]
+    lossy: bool,
+
+    /// How big a buffer to read files through, such as `64K` or `1M`, or
+    /// `auto` to size it for each file
+    #[structopt(long, value_name = "size", default_value = "auto")]
+    buffer_size: BufferSize,
+
+    /// Ignore the configuration files at ~/.config/grrs/config.toml and in .grrsrc
+    #[structopt(long)]
+    no_config: bool,
+
+    /// Search for the pattern saved under this name by `grrs pattern save`,
+    /// with the options saved with it, instead of a pattern argument
+    #[structopt(long = "use", value_name = "name")]
+    saved: Option<String>,
+
+    /// Write how long each stage of the search took to this file, as a Chrome trace
+    #[cfg(feature = "trace")]
+    #[structopt(long, parse(from_os_str))]
+    trace: Option<PathBuf>,
+}
+
+/// Flags narrowing which lines, or which part of them, are searched.
+#[derive(Debug, StructOpt)]
+struct Filters {
+    /// Only consider log lines timestamped at or after this time
+    #[structopt(long, parse(try_from_str = parse_bound))]
+    since: Option<chrono::NaiveDateTime>,
+
+    /// Only consider log lines timestamped before this time
+    #[structopt(long, parse(try_from_str = parse_bound))]
+    until: Option<chrono::NaiveDateTime>,
+
+    /// The strftime-style format of the timestamps in the file
+    #[structopt(long, default_value = DEFAULT_FORMAT)]
+    timestamp_format: String,
+
+    /// The regex locating the timestamp within each line
+    #[structopt(long, default_value = DEFAULT_REGEX)]
+    timestamp_regex: String,
+
+    /// Parse each line as `logfmt`, `syslog` or `json` fields
+    #[structopt(long)]
+    parse: Option<LineFormat>,
+
+    /// Only match parsed lines whose field equals a value, as `field=value`
+    #[structopt(long = "where", number_of_values = 1)]
+    conditions: Vec<Condition>,
+
+    /// Match the pattern against this parsed field instead of the whole line
+    #[structopt(long)]
+    field: Option<String>,
+
+    /// Parse each line as JSON and match the pattern against the value at this path
+    #[structopt(long, conflicts_with_all = &["parse", "field"])]
+    json_path: Option<String>,
+
+    /// Match the pattern with the matcher of this plugin from ~/.config/grrs/plugins
+    #[cfg(feature = "plugins")]
+    #[structopt(long, value_name = "plugin")]
+    matcher: Option<String>,
+
+    /// Run this Rhai script on each line, which is dropped if it returns
+    /// false, or searched in place of a string it returns
+    #[cfg(feature = "script")]
+    #[structopt(long, value_name = "script", parse(try_from_str = script::Script::compile))]
+    filter_script: Option<script::Script>,
+}
+
+impl Cli {
+    /// Returns the flags shared by the subcommands that search.
+    fn common(&self) -> Option<&Common> {
+        match self {
+            Cli::Search(args) => Some(&args.common),
+            Cli::Count(args) => Some(&args.common),
+            Cli::Replace(args) => Some(&args.common),
+            Cli::Files(args) => Some(&args.common),
+            Cli::Diff(args) => Some(&args.common),
+            Cli::Log(args) => Some(&args.common),
+            Cli::Interactive(args) => Some(&args.common),
+            Cli::Find(_)
+            | Cli::Index(_)
+            | Cli::Session(_)
+            | Cli::History(_)
+            | Cli::Pattern(_)
+            | Cli::Bench(_)
+            | Cli::Serve(_) => None,
+            #[cfg(feature = "tui")]
+            Cli::Tui(args) => Some(&args.common),
+        }
+    }
+
+    /// Returns the pattern given on the command line, if any.
+    fn pattern(&self) -> Option<&str> {
+        match self {
+            Cli::Search(args) => Some(&args.pattern),
+            Cli::Count(args) => Some(&args.pattern),
+            Cli::Replace(args) => Some(&args.pattern),
+            Cli::Files(args) => Some(&args.pattern),
+            Cli::Diff(args) => Some(&args.pattern),
+            Cli::Log(args) => Some(&args.pattern),
+            _ => None,
+        }
+    }
+}
+
+impl Filters {
+    /// Reports whether patterns are matched against whole lines as they're
+    /// read, rather than a parsed field or by a plugin.
+    fn matches_lines(&self) -> bool {
+        #[cfg(feature = "plugins")]
+        if self.matcher.is_some() {
+            return false;
+        }
+        self.parse.is_none() && self.field.is_none() && self.json_path.is_none()
+    }
+
+    /// Builds the matcher for a pattern, which matches against a parsed field
+    /// when asked to.
+    fn matcher(&self, searcher: &Searcher, pattern: &str) -> Result<Box<dyn Matcher>> {
+        let format = self
+            .json_path
+            .as_ref()
+            .map(|_| LineFormat::Json)
+            .or(self.parse);
+        let field = self.json_path.clone().or_else(|| self.field.clone());
+        let filter = match format {
+            None if !self.conditions.is_empty() || field.is_some() => {
+                return Err(anyhow!(
+                    "--where and --field require --parse or --json-path"
+                ));
+            }
+            None => None,
+            Some(LineFormat::Json) => {
+                let conditions = self
+                    .conditions
+                    .iter()
+                    .map(|condition| Condition {
+                        field: json_path(&condition.field),
+                        value: condition.value.clone(),
+                    })
+                    .collect();
+                let field = field.as_deref().map(json_path);
+                Some(FieldFilter::new(LineFormat::Json, conditions, field))
+            }
+            Some(format) => Some(FieldFilter::new(format, self.conditions.clone(), field)),
+        };
+
+        Ok(match filter {
+            None => self.pattern_matcher(searcher, pattern)?,
+            Some(filter) => Box::new(FieldMatcher::new(
+                filter,
+                self.pattern_matcher(searcher, pattern)?,
+            )),
+        })
+    }
+
+    /// Builds the matcher for the pattern itself, using a plugin if one's named.
+    fn pattern_matcher(&self, searcher: &Searcher, pattern: &str) -> Result<Box<dyn Matcher>> {
+        #[cfg(feature = "plugins")]
+        if let Some(name) = &self.matcher {
+            return plugin::find(&plugin::installed()?, name)?.matcher(pattern);
+        }
+
+        Ok(searcher.matcher(pattern)?)
+    }
+
+    /// Searches the numbered lines that fall within the time window.
+    fn search<S: AsRef<str>>(
+        &self,
+        searcher: &Searcher,
+        matcher: &dyn Matcher,
+        lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        self.search_under(searcher, matcher, None, lines, sink)
+    }
+
+    /// Searches as Filters::search() does, grouping the lines found if asked
+    /// to.
+    fn search_under<S: AsRef<str>>(
+        &self,
+        searcher: &Searcher,
+        matcher: &dyn Matcher,
+        grouping: Option<Grouping>,
+        lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        let mut window = match self.since.is_some() || self.until.is_some() {
+            false => None,
+            true => Some(TimeWindow::new(
+                self.since,
+                self.until,
+                &self.timestamp_format,
+                &self.timestamp_regex,
+            )?),
+        };
+        let mut in_window = |line: &str| window.as_mut().is_none_or(|w| w.contains(line));
+        let lines = lines
+            .take_while(|_| !interrupt::interrupted())
+            .filter(|line| {
+                line.as_ref()
+                    .map_or(true, |(_, line)| in_window(line.as_ref()))
+            });
+
+        let _span = tracing::info_span!("search").entered();
+        #[cfg(feature = "script")]
+        if let Some(script) = &self.filter_script {
+            let lines = lines.filter_map(|line| match line {
+                Ok((num, line)) => (script.apply(num, line.as_ref()))
+                    .map(|kept| kept.map(|line| (num, line)))
+                    .transpose(),
+                Err(err) => Some(Err(err)),
+            });
+            return search_lines(searcher, matcher, grouping, lines, sink);
+        }
+        search_lines(searcher, matcher, grouping, lines, sink)
+    }
+
+    /// Searches the lines read from a file as Filters::search_under() does,
+    /// but a buffer at a time, without copying out lines that don't match,
+    /// unless lines are dropped first, decoded lossily or kept for grouping.
+    fn search_reader(
+        &self,
+        searcher: &Searcher,
+        matcher: &dyn Matcher,
+        grouping: Option<Grouping>,
+        reader: Box<dyn BufRead>,
+        common: &Common,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        if self.drops_lines() || common.lossy || grouping.is_some() {
+            let lines = read_lines(reader, common);
+            return self.search_under(searcher, matcher, grouping, lines, sink);
+        }
+        let _span = tracing::info_span!("search").entered();
+        let reader = interrupt::Reader(reader);
+        searcher.search_buffered(matcher, reader, &mut TracedSink(sink))
+    }
+
+    /// Reports whether some lines are dropped before they're searched, for
+    /// falling outside the time window or by the filter script.
+    fn drops_lines(&self) -> bool {
+        let drops = self.since.is_some() || self.until.is_some();
+        #[cfg(feature = "script")]
+        let drops = drops || self.filter_script.is_some();
+        drops
+    }
+}
+
+/// How the lines found are grouped when they're written.
+#[derive(Clone, Copy)]
+enum Grouping<'m> {
+    /// Under the nearest line above them this matches, for --show-function
+    Headings(&'m dyn Matcher),
+    /// Into whole records between the lines this matches, for --paragraph
+    Records(&'m dyn Matcher),
+}
+
+fn search_lines<S: AsRef<str>>(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    grouping: Option<Grouping>,
+    lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary, GrrsError> {
+    let sink = &mut TracedSink(sink);
+    match grouping {
+        None => searcher.search_numbered(matcher, lines, sink),
+        Some(Grouping::Headings(headings)) => {
+            heading::search_numbered(searcher, matcher, headings, lines, sink)
+        }
+        Some(Grouping::Records(separators)) => {
+            record::search_numbered(searcher, matcher, separators, lines, sink)
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    // The reader of a pipe like `grrs pattern big.log | head` going away isn't
+    // worth reporting, so exit as if killed by SIGPIPE.
+    match run() {
+        Err(err) if is_broken_pipe(&err) => std::process::exit(141),
+        result => result,
+    }
+}
+
+fn run() -> Result<()> {
+    let args = saved::with_saved_pattern(with_default_subcommand(std::env::args_os()))?;
+    let matches = app().get_matches_from(args);
+    let args = Cli::from_clap(&matches);
+    let common = match (&args, args.common()) {
+        (_, Some(common)) => common,
+        (Cli::Find(args), None) => return find(args),
+        (Cli::Index(args), None) => return index(args),
+        (Cli::Pattern(command), None) => {
+            let (_, flags) = matches.subcommand();
+            return saved::run(command, flags.expect("a subcommand is required"));
+        }
+        (Cli::Session(command), None) => return session::run(command),
+        (Cli::Bench(command), None) => return bench::run(command),
+        (Cli::Serve(command), None) => return serve::run(command),
+        (_, None) => return list_history(),
+    };
+
+    env_logger::Builder::new()
+        .filter_level(
+            common
+                .verbose
+                .log_level()
+                .map_or(log::LevelFilter::Off, |level| level.to_level_filter()),
+        )
+        .init();
+    #[cfg(feature = "trace")]
+    let _trace = match &common.trace {
+        Some(path) => Some(trace::start(path)?),
+        None => None,
+    };
+
+    let (_, flags) = matches.subcommand();
+    let config = load_config(common)?;
+    let recording = match config.history {
+        true => history::default_path(),
+        false => None,
+    };
+    if let (Some(path), Some(pattern)) = (&recording, args.pattern()) {
+        if let Err(err) = history::record(path, pattern) {
+            log::warn!("{:#}", err);
+        }
+    }
+
+    // Interactive sessions and the browser keep Ctrl-C for quitting.
+    if let Cli::Search(_) | Cli::Count(_) | Cli::Replace(_) | Cli::Files(_) | Cli::Diff(_) = &args {
+        interrupt::catch()?;
+    }
+    // Options saved with a pattern take precedence over those configured,
+    // but not over flags.
+    let configured = match &common.saved {
+        Some(name) => saved::find(name)?.options(&config.search)?,
+        None => config.search,
+    };
+    let options = resolve_options(configured, common, flags.expect("a subcommand is required"));
+    match &args {
+        Cli::Search(args) => match search(args, options) {
+            // grep reports errors like any other, but exits with 2.
+            Err(err) if args.compat == Some(Compat::Grep) && !is_broken_pipe(&err) => {
+                eprintln!("grrs: {:#}", err);
+                std::process::exit(2);
+            }
+            result => result,
+        },
+        Cli::Count(args) => count(args, options),
+        Cli::Replace(args) => replace(args, options),
+        Cli::Files(args) => files(args, options),
+        Cli::Find(args) => find(args),
+        Cli::Index(args) => index(args),
+        Cli::Diff(args) => diff(args, options),
+        Cli::Log(args) => log(args, options),
+        Cli::Interactive(args) => interactive(args, options, recording.as_deref()),
+        Cli::Session(command) => session::run(command),
+        Cli::History(_) => list_history(),
+        Cli::Pattern(command) => saved::run(command, flags.expect("a subcommand is required")),
+        Cli::Bench(command) => bench::run(command),
+        Cli::Serve(command) => serve::run(command),
+        #[cfg(feature = "tui")]
+        Cli::Tui(args) => {
+            let lines = read_lines(open(&args.path, &args.common)?, &args.common);
+            let lines = lines.collect::<Result<_, _>>()?;
+            tui::Browser::new(args.path.clone(), lines, Searcher::new(options)).run()
+        }
+    }
+}
+
+/// Reports whether an error came from writing to a closed pipe.
+fn is_broken_pipe(err: &Error) -> bool {
+    err.chain().any(|cause| {
+        let err = match cause.downcast_ref::<GrrsError>() {
+            Some(GrrsError::Io(err)) => Some(err),
+            _ => cause.downcast_ref::<std::io::Error>(),
+        };
+        err.is_some_and(|err| err.kind() == ErrorKind::BrokenPipe)
+    })
+}
+
+/// Builds the parser, applying each subcommand's about text last.
+fn app() -> App<'static, 'static> {
+    SUBCOMMANDS.iter().fold(
+        App::new("grrs")
+            .version(env!("CARGO_PKG_VERSION"))
+            .about(ABOUT)
+            .setting(AppSettings::SubcommandRequiredElseHelp),
+        |app, &(name, about, subcommand)| {
+            app.subcommand(subcommand().name(name).about(about).long_about(about))
+        },
+    )
+}
+
+/// Inserts the default subcommand into arguments that don't name one, so
+/// `grrs <pattern> <path>` keeps working, and takes a leading flag like
+/// `--interactive` to mean the subcommand of that name.
+fn with_default_subcommand(args: impl Iterator<Item = OsString>) -> Vec<OsString> {
+    let mut args: Vec<_> = args.collect();
+    let named = match args.get(1).map(|arg| arg.to_str()) {
+        None => true,
+        Some(arg) => arg.is_some_and(|arg| {
+            TOP_LEVEL.contains(&arg) || SUBCOMMANDS.iter().any(|(name, ..)| *name == arg)
+        }),
+    };
+    let flag = (args.get(1).and_then(|arg| arg.to_str()))
+        .and_then(|arg| arg.strip_prefix("--"))
+        .filter(|name| AS_FLAGS.contains(name))
+        .map(OsString::from);
+    if let Some(name) = flag {
+        args[1] = name;
+    } else if !named {
+        args.insert(1, SUBCOMMANDS[0].0.into());
+    }
+
+    args
+}
+
+/// Reads the configuration files, unless asked not to.
+fn load_config(common: &Common) -> Result<Config> {
+    if common.no_config {
+        return Ok(Config::default());
+    }
+
+    // The nearest .grrsrc holds project defaults, which take precedence over
+    // the user's own.
+    let project = Config::find_project_file(std::env::current_dir()?);
+    let paths: Vec<_> = Config::default_path().into_iter().chain(project).collect();
+    for path in &paths {
+        log::debug!("reading configuration from {}", path.display());
+    }
+
+    Ok(Config::load_all(&paths)?)
+}
+
+/// Layers the flags given on the command line over the configured options.
+fn resolve_options(
+    configured: SearchOptions,
+    common: &Common,
+    flags: &ArgMatches,
+) -> SearchOptions {
+    // Flags only take precedence over the configuration when given, and are
+    // named like their fields in kebab case.
+    configured.with_overrides(&common.options, |name| {
+        flags.occurrences_of(name.replace('_', "-")) > 0
+    })
+}
+
+fn check_pattern(pattern: &str) -> Result<()> {
+    match pattern.trim().is_empty() {
+        false => Ok(()),
+        true => Err(anyhow!("pattern appears to be empty")),
+    }
+}
+
+/// Passes events on to another sink, writing each within a `write` span.
+struct TracedSink<'s>(&'s mut dyn Sink);
+
+impl Sink for TracedSink<'_> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        let _span = tracing::trace_span!("write").entered();
+        self.0.event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        let _span = tracing::trace_span!("write").entered();
+        self.0.finish()
+    }
+}
+
+fn open(path: &Path, common: &Common) -> Result<Box<dyn BufRead>> {
+    let _span = tracing::info_span!("open", path = %path.display()).entered();
+    // The error says which file, and why.
+    Ok(open_input_with(path, common.buffer_size)?)
+}
+
+/// Numbers the lines read from a file, replacing invalid UTF-8 if asked to.
+fn read_lines(reader: Box<dyn BufRead>, common: &Common) -> Lines {
+    match common.lossy {
+        false => Box::new(numbered_lines(reader)),
+        true => Box::new(numbered_lines_lossy(reader)),
+    }
+}
+
+fn search(args: &Search, options: SearchOptions) -> Result<()> {
+    let patterns: Vec<_> = std::iter::once(&args.pattern)
+        .chain(&args.patterns)
+        .collect();
+    for pattern in &patterns {
+        check_pattern(pattern)?;
+    }
+    #[cfg(feature = "notify")]
+    let timer = notify::Timer::start(args.notify);
+    let searcher = Searcher::new(options);
+    let patterns = (patterns.iter())
+        .map(|pattern| -> Result<Box<dyn Matcher>> {
+            let matcher = args.filters.matcher(&searcher, pattern)?;
+            Ok(match args.overlapping {
+                false => matcher,
+                true => Box::new(Overlapping(matcher)),
+            })
+        })
+        .collect::<Result<_>>()?;
+    let patterns = PatternSet::new(patterns);
+    let matcher: &dyn Matcher = &patterns;
+    // grep reports whether binary files match rather than refusing them.
+    let reader = match args.compat {
+        Some(Compat::Grep) => match open_sniffed(&args.path, args.common.buffer_size)? {
+            (content, reader) if content.is_binary() => {
+                return search_binary(args, &searcher, matcher, reader);
+            }
+            (_, reader) => reader,
+        },
+        None => open(&args.path, &args.common)?,
+    };
+    let headings = match args.show_function {
+        false => None,
+        true => Some(RegexMatcher::new(&args.heading_regex, false)?),
+    };
+    let separators = match (args.paragraph, &args.record_separator) {
+        (true, _) => Some(RegexMatcher::new(record::PARAGRAPH_SEPARATOR, false)?),
+        (false, Some(separator)) => Some(RegexMatcher::new(separator, false)?),
+        (false, None) => None,
+    };
+    let grouping = match (&headings, &separators) {
+        (Some(headings), _) => Some(Grouping::Headings(headings)),
+        (None, Some(separators)) => Some(Grouping::Records(separators)),
+        (None, None) => None,
+    };
+    let search = |reader, sink: &mut dyn Sink| match args.window {
+        None => {
+            let filters = &args.filters;
+            Ok(filters.search_reader(&searcher, matcher, grouping, reader, &args.common, sink)?)
+        }
+        Some(excerpt) => search_windows(args, excerpt, &searcher, matcher, reader, sink),
+    };
+    let baseline = match (&args.baseline, &args.baseline_rev) {
+        (Some(path), _) => Some(session::Saved::load(path)?),
+        (None, Some(revision)) => {
+            let mut saved =
+                session::Session::new(&args.pattern, &args.path, searcher.options().clone());
+            for blob in git::files_at(revision, std::slice::from_ref(&args.path))? {
+                let mut sink = CollectSink::default();
+                search(open_bytes(&blob.path, blob.read()?)?, &mut sink)?;
+                saved.events.extend(sink.events);
+            }
+            Some(session::Saved::File(saved))
+        }
+        (None, None) => None,
+    };
+    let run = |sink: &mut dyn Sink| search(reader, sink);
+    if let Some(baseline) = baseline {
+        return compare_with_baseline(args, &baseline, &searcher, run);
+    }
+
+    let unit = match args.byte_columns {
+        false => ColumnUnit::Graphemes,
+        true => ColumnUnit::Bytes,
+    };
+    // Only stdout is known to be a terminal.
+    let colored = match args.outfile {
+        None => args.color.enabled(),
+        Some(_) => args.color == Color::Always,
+    };
+    let formatter: Box<dyn Formatter> = match (args.json, args.fzf, args.column) {
+        _ if args.compat == Some(Compat::Grep) => {
+            Box::new(GrepFormatter::new(args.path.display().to_string()))
+        }
+        _ if args.json_regions => Box::new(RegionJsonFormatter::default()),
+        (true, ..) if !args.patterns.is_empty() => Box::new(PatternJsonFormatter::new(&patterns)),
+        (true, ..) => Box::new(JsonFormatter),
+        (_, true, _) => {
+            Box::new(FzfFormatter::new(args.path.display().to_string(), matcher).unit(unit))
+        }
+        (.., true) => Box::new(ColumnFormatter::new(matcher).unit(unit)),
+        _ if args.only_matching => Box::new(OnlyMatchingFormatter::new(matcher)),
+        _ if colored => Box::new(ColorFormatter::new(&patterns)),
+        _ => Box::new(LineFormatter),
+    };
+    #[cfg(feature = "plugins")]
+    let formatter = match &args.formatter {
+        Some(name) => plugin::find(&plugin::installed()?, name)?.formatter()?,
+        None => formatter,
+    };
+    #[cfg(feature = "pretty")]
+    let formatter: Box<dyn Formatter> = match args.pretty {
+        true => Box::new(pretty::PrettyFormatter::for_file(&args.path)),
+        false => formatter,
+    };
+    let formatter = match args.timestamps {
+        false => formatter,
+        true => Box::new(TimestampFormatter(formatter)),
+    };
+    let formatter = match args.crlf {
+        false => formatter,
+        true => Box::new(CrlfFormatter(formatter)),
+    };
+    #[cfg(feature = "clipboard")]
+    let copied = clipboard::Buffer::default();
+    let mut sink: Box<dyn Sink> = match &args.outfile {
+        #[cfg(feature = "clipboard")]
+        None if args.clipboard => Box::new(WriterSink::with_formatter(copied.clone(), formatter)),
+        None => Box::new(WriterSink::with_formatter(std::io::stdout(), formatter)),
+        Some(outfile) => {
+            purge_file(outfile).with_context(|| {
+                format!(
+                    "could not create file '{}'",
+                    display_path(outfile).display()
+                )
+            })?;
+            let sink = FileSink::create(outfile).with_context(|| {
+                format!(
+                    "could not create file '{}'",
+                    display_path(outfile).display()
+                )
+            })?;
+            let encoding = args.output_encoding.unwrap_or_default();
+            Box::new(sink.formatter(formatter).encoding(encoding))
+        }
+    };
+    if let Some(rate) = args.rate_limit {
+        let before = searcher.options().before();
+        sink = Box::new(throttle::ThrottleSink::new(sink, rate, before));
+    }
+    if let Some(path) = &args.save_session {
+        let saved = session::Session::new(&args.pattern, &args.path, searcher.options().clone());
+        sink = Box::new(session::SaveSink::new(sink, path.clone(), saved));
+    }
+    let done = |summary: &SearchSummary| -> Result<()> {
+        interrupt::exit_if_interrupted(read(summary));
+        #[cfg(feature = "clipboard")]
+        if args.clipboard {
+            copied.copy()?;
+            eprintln!(
+                "grrs: copied {} matching lines to the clipboard",
+                summary.matched_lines
+            );
+        }
+        #[cfg(feature = "notify")]
+        timer.finish(&found(summary, &args.path.display()));
+        if args.compat == Some(Compat::Grep) && summary.matched_lines == 0 {
+            std::process::exit(1);
+        }
+        Ok(())
+    };
+    if !args.edit {
+        return done(&run(&mut *sink)?);
+    }
+
+    let mut recorder = editor::Recorder::new(&mut *sink);
+    done(&run(&mut recorder)?)?;
+    let stdin = std::io::stdin();
+    if let Some(line_number) = editor::choose(&recorder.matches, stdin.lock())? {
+        editor::open(&args.path, line_number)?;
+    }
+
+    Ok(())
+}
+
+/// Reports whether a binary file matches, as grep does, rather than writing
+/// what's in it.
+fn search_binary(
+    args: &Search,
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    reader: Box<dyn BufRead>,
+) -> Result<()> {
+    // One match is enough to tell.
+    let searcher = Searcher::new(SearchOptions {
+        max_count: Some(1),
+        ..without_context(searcher.options().clone())
+    });
+    let lines = Box::new(numbered_lines_lossy(reader));
+    let mut sink = CollectSink::default();
+    let summary = args.filters.search(&searcher, matcher, lines, &mut sink)?;
+    interrupt::exit_if_interrupted(read(&summary));
+    if summary.matched_lines == 0 {
+        std::process::exit(1);
+    }
+    writeln!(
+        std::io::stdout(),
+        "Binary file {} matches",
+        args.path.display()
+    )?;
+
+    Ok(())
+}
+
+/// Searches as usual, but displays only the matches that differ from a
+/// baseline, exiting with 1 if any are new.
+fn compare_with_baseline(
+    args: &Search,
+    baseline: &session::Saved,
+    searcher: &Searcher,
+    run: impl FnOnce(&mut dyn Sink) -> Result<SearchSummary>,
+) -> Result<()> {
+    let mut sink = CollectSink::default();
+    let summary = run(&mut sink)?;
+    interrupt::exit_if_interrupted(read(&summary));
+    let mut current = session::Session::new(&args.pattern, &args.path, searcher.options().clone());
+    current.events = sink.events;
+    if let Some(path) = &args.save_session {
+        current.save(path)?;
+    }
+    let current = session::Saved::File(current);
+    if session::write_diff(baseline, &current)? {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Searches in a bounded buffer instead of reading whole lines, sending an
+/// excerpt of each matching line to the sink in place of the line.
+fn search_windows(
+    args: &Search,
+    excerpt: usize,
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    reader: impl Read,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary> {
+    let options = searcher.options();
+    let filters = &args.filters;
+    let filtered = filters.since.is_some()
+        || filters.until.is_some()
+        || filters.parse.is_some()
+        || filters.json_path.is_some();
+    let context =
+        options.before() > 0 || options.after() > 0 || options.after_context_until.is_some();
+    if context || options.invert_match || filtered {
+        return Err(anyhow!(
+            "--window can't be combined with context, --invert-match or the line filters"
+        ));
+    }
+    if args.common.lossy {
+        return Err(anyhow!("--window can't be combined with --lossy"));
+    }
+
+    let mut sink = TracedSink(sink);
+    let (mut written, mut matched) = (Ok(()), 0);
+    let _span = tracing::info_span!("search").entered();
+    let summary = WindowSearcher::new(excerpt).search(matcher, reader, |found| {
+        written = sink.event(SinkEvent::Match {
+            line_number: found.line_number,
+            line: found.excerpt.into(),
+        });
+        matched += 1;
+        let limit_reached = options.max_count.is_some_and(|max| matched >= max);
+        match written.is_err() || limit_reached || interrupt::interrupted() {
+            false => ControlFlow::Continue(()),
+            true => ControlFlow::Break(()),
+        }
+    })?;
+    written?;
+    sink.finish()?;
+
+    Ok(summary)
+}
+
+fn count(args: &Count, options: SearchOptions) -> Result<()> {
+    check_pattern(&args.pattern)?;
+    #[cfg(feature = "notify")]
+    let timer = notify::Timer::start(args.notify);
+    let searcher = Searcher::new(without_context(options));
+    let matcher = args.filters.matcher(&searcher, &args.pattern)?;
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    let several = args.paths.len() > 1;
+    // Counts are written as `path:count`, and --wc as `wc` writes them.
+    let write = |out: &mut dyn Write, stats: &LineStats, place: Option<&str>| {
+        let count = match (args.wc, args.count_matches) {
+            (true, _) => format!("{} {} {}", stats.lines, stats.words, stats.bytes),
+            (false, true) => stats.matches.to_string(),
+            (false, false) => stats.lines.to_string(),
+        };
+        match place {
+            None => writeln!(out, "{}", count),
+            Some(place) if args.wc => writeln!(out, "{} {}", count, place),
+            Some(place) => writeln!(out, "{}:{}", place, count),
+        }
+    };
+    let mut total = SearchSummary::default();
+    let mut counted = LineStats::default();
+    for path in &args.paths {
+        let reader = open(path, &args.common)?;
+        let mut sink = StatsSink::new(&*matcher);
+        let lines = read_lines(reader, &args.common);
+        let summary = args
+            .filters
+            .search(&searcher, &*matcher, lines, &mut sink)?;
+        if several {
+            write(&mut out, &sink.stats, Some(&path.display().to_string()))?;
+        }
+        total.matched_lines += summary.matched_lines;
+        total.total_lines += summary.total_lines;
+        counted += sink.stats;
+        if interrupt::interrupted() {
+            break;
+        }
+    }
+    write(&mut out, &counted, several.then_some("total"))?;
+    interrupt::exit_if_interrupted(read(&total));
+    #[cfg(feature = "notify")]
+    timer.finish(&match args.paths.as_slice() {
+        [path] => found(&total, &path.display()),
+        paths => found(&total, &format!("{} files", paths.len())),
+    });
+
+    Ok(())
+}
+
+fn replace(args: &Replace, options: SearchOptions) -> Result<()> {
+    check_pattern(&args.pattern)?;
+    let reader = open(&args.path, &args.common)?;
+    let options = SearchOptions {
+        ignore_case: options.ignore_case || args.preserve_case,
+        ..options
+    };
+    let matcher = Searcher::new(options).matcher(&args.pattern)?;
+    let replacement = |matched: &str| -> Cow<str> {
+        match args.preserve_case {
+            false => Cow::Borrowed(&args.replacement),
+            true => Cow::Owned(preserve_case(matched, &args.replacement)),
+        }
+    };
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let _span = tracing::info_span!("search").entered();
+    let terminator = if args.crlf { "\r\n" } else { "\n" };
+    let mut written = 0;
+    for line in read_lines(reader, &args.common).take_while(|_| !interrupt::interrupted()) {
+        let (line_number, line) = line?;
+        if args.plan_json {
+            for span in matcher.find_iter(&line) {
+                let edit = serde_json::json!({
+                    "path": args.path,
+                    "line_number": line_number,
+                    "start": span.start,
+                    "end": span.end,
+                    "old": &line[span.start..span.end],
+                    "new": replacement(&line[span.start..span.end]),
+                });
+                writeln!(out, "{}", edit)?;
+            }
+            written += 1;
+            continue;
+        }
+        write!(
+            out,
+            "{}{}",
+            replace_matches_with(&line, &*matcher, replacement),
+            terminator
+        )?;
+        written += 1;
+    }
+    out.flush()?;
+    interrupt::exit_if_interrupted(format!("writing {} lines", written));
+
+    Ok(())
+}
+
+fn files(args: &Files, options: SearchOptions) -> Result<()> {
+    let patterns: Vec<_> = std::iter::once(&args.pattern)
+        .chain(&args.files_matching_all)
+        .collect();
+    for pattern in &patterns {
+        check_pattern(pattern)?;
+    }
+    // An index can only tell which files don't contain a string, as it's
+    // found in the file.
+    let index = match &args.index {
+        Some(path) if !options.regex && !options.invert_match && args.filters.matches_lines() => {
+            Some(index::Index::load(path)?)
+        }
+        _ => None,
+    };
+    let literals: Vec<_> = (patterns.iter())
+        .map(|pattern| (pattern.as_str(), options.case_insensitive_for(pattern)))
+        .collect();
+    let may_match = |path: &Path| {
+        index.as_ref().is_none_or(|index| {
+            (literals.iter())
+                .all(|(literal, ignore_case)| index.may_contain(path, literal, *ignore_case))
+        })
+    };
+    #[cfg(feature = "notify")]
+    let timer = notify::Timer::start(args.notify);
+    // Reading a file can stop at its first match, unless there are other
+    // patterns to find too, or every match is compared or saved.
+    let recording =
+        args.save_session.is_some() || args.baseline.is_some() || args.baseline_rev.is_some();
+    let options = SearchOptions {
+        max_count: (args.files_matching_all.is_empty() && !recording).then_some(1),
+        ..without_context(options)
+    };
+    // Files are read, and their lines searched, through the same buffers
+    // over and over.
+    let pool = BufferPool::new();
+    let searcher = (Searcher::builder().options(options))
+        .pool(pool.clone())
+        .build();
+    let patterns = (patterns.iter())
+        .map(|pattern| args.filters.matcher(&searcher, pattern))
+        .collect::<Result<_>>()?;
+    let patterns = PatternSet::new(patterns);
+    let listed = match args.git {
+        Some(selection) => git::files(selection, &args.paths)?,
+        None => args.paths.clone(),
+    };
+    let policy = args
+        .skip
+        .iter()
+        .fold(FilePolicy::default(), |p, &f| p.skip(f));
+    let policy = args.abort_on.iter().fold(policy, |p, &f| p.abort_on(f));
+    let policy = match args.max_errors {
+        Some(max) => policy.max_errors(max),
+        None => policy,
+    };
+    let policy = policy.buffer_size(args.common.buffer_size).pool(pool);
+    // Searches what's read for every pattern, returning the matches too if
+    // they're to be saved or compared.
+    let search_all = |reader| -> Result<_, GrrsError> {
+        let mut hits = PatternHits::new(&patterns);
+        let mut found = CollectSink::default();
+        let sink: &mut dyn Sink = match recording {
+            true => &mut found,
+            false => &mut hits,
+        };
+        let summary =
+            (args.filters).search_reader(&searcher, &patterns, None, reader, &args.common, sink)?;
+        for event in &found.events {
+            hits.event(event.clone())?;
+        }
+        // A file missing any of the patterns counts as not matching.
+        Ok(match hits.all() {
+            true => (summary, found.events),
+            false => {
+                let summary = SearchSummary {
+                    matched_lines: 0,
+                    ..summary
+                };
+                (summary, Vec::new())
+            }
+        })
+    };
+    let recorded = Mutex::new(HashMap::new());
+    let progress = args.progress_json.then(progress::Progress::start);
+    let search = |path: &Path, reader| {
+        if !may_match(path) {
+            if let Some(progress) = &progress {
+                progress.searched();
+            }
+            return Ok(SearchSummary::default());
+        }
+        let reader = match &progress {
+            Some(progress) => progress.reader(path, reader),
+            None => reader,
+        };
+        let searched = search_all(reader);
+        if let Some(progress) = &progress {
+            progress.searched();
+        }
+        let (summary, events) = searched?;
+        if !events.is_empty() {
+            recorded.lock().unwrap().insert(path.to_path_buf(), events);
+        }
+        Ok(summary)
+    };
+    // With globs, directories are looked through and their files searched at
+    // once, so how many files there are isn't known until the end.
+    let wanted = |path: &Path| {
+        !interrupt::interrupted() && args.globs.iter().any(|glob| glob.is_match(path))
+    };
+    let results = match args.globs.is_empty() {
+        true => {
+            let paths = listed.iter().take_while(|_| !interrupt::interrupted());
+            search_files(paths, &policy, search)
+        }
+        false => {
+            let threads = (args.threads)
+                .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+            search_tree(&listed, threads, &policy, wanted, search)
+        }
+    };
+    let mut recorded = recorded.into_inner().unwrap();
+    let session = |path: &Path, events| session::Session {
+        events,
+        ..session::Session::new(&args.pattern, path, searcher.options().clone())
+    };
+    let tree = |files| {
+        session::Saved::Tree(session::TreeSession {
+            pattern: args.pattern.clone(),
+            paths: listed.clone(),
+            files,
+        })
+    };
+    let current = recording.then(|| {
+        let files = (results.matched())
+            .filter_map(|path| Some(session(path, recorded.remove(path)?)))
+            .collect();
+        tree(files)
+    });
+    if let (Some(path), Some(current)) = (&args.save_session, &current) {
+        current.save(path)?;
+    }
+    let baseline = match (&args.baseline, &args.baseline_rev) {
+        (Some(path), _) => Some(session::Saved::load(path)?),
+        // Each file as git has it, under the path it has in the working tree.
+        (None, Some(revision)) => {
+            let mut files = Vec::new();
+            for blob in git::files_at(revision, &listed)? {
+                if !args.globs.is_empty() && !wanted(&blob.path) {
+                    continue;
+                }
+                let found = open_bytes(&blob.path, blob.read()?).and_then(search_all);
+                match found {
+                    Ok((_, events)) if !events.is_empty() => {
+                        files.push(session(&blob.path, events))
+                    }
+                    Ok(_) => {}
+                    // Files that weren't text then had nothing to match.
+                    Err(err) if err.open_failure() == Some(OpenFailure::Binary) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Some(tree(files))
+        }
+        (None, None) => None,
+    };
+    if let Some(progress) = progress {
+        progress.finish();
+    }
+    let tried = results.searched.len() + results.errors.len() + results.skipped.len();
+    let (total, progress) = match args.globs.is_empty() {
+        true => (listed.len(), format!("{} of {}", tried, listed.len())),
+        false => (tried, tried.to_string()),
+    };
+    let stdout = std::io::stdout();
+    let mut out: Box<dyn Write> = Box::new(stdout.lock());
+    #[cfg(feature = "clipboard")]
+    let copied = clipboard::Buffer::default();
+    #[cfg(feature = "clipboard")]
+    if args.clipboard {
+        out = Box::new(copied.clone());
+    }
+    let added = match (&baseline, &current) {
+        (Some(baseline), Some(current)) => session::write_diff(baseline, current)?,
+        _ => {
+            for path in results.matched() {
+                writeln!(out, "{}", path.display())?;
+            }
+            false
+        }
+    };
+    if !args.no_messages && !results.is_complete() {
+        eprintln!(
+            "grrs: could not read {} of {} files:",
+            results.errors.len(),
+            total
+        );
+        for (path, err) in &results.errors {
+            eprintln!("  {}: {}", display_path(path).display(), err);
+        }
+    }
+    if results.aborted {
+        let reason = match args.max_errors {
+            Some(max) if results.errors.len() > max => {
+                format!(", as more than {} could not be read", max)
+            }
+            _ => String::new(),
+        };
+        eprintln!("grrs: stopped after {} files{}", progress, reason);
+    }
+    if args.stats {
+        eprintln!(
+            "{} files searched, {} matched, {} could not be read",
+            results.searched.len(),
+            results.matched().count(),
+            results.errors.len() + results.skipped.len()
+        );
+    }
+    out.flush()?;
+    #[cfg(feature = "clipboard")]
+    if args.clipboard {
+        copied.copy()?;
+        let paths = results.matched().count();
+        eprintln!("grrs: copied {} paths to the clipboard", paths);
+    }
+    interrupt::exit_if_interrupted(format!("searching {} files", progress));
+    #[cfg(feature = "notify")]
+    timer.finish(&format!(
+        "{} of {} files matched `{}`",
+        results.matched().count(),
+        total,
+        args.pattern
+    ));
+    // Like grep, a partial failure exits with 2 once every file is done.
+    if !results.is_complete() {
+        std::process::exit(2);
+    }
+    if added {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Lists the files under the paths whose names match any of the globs,
+/// reporting the directories that couldn't be looked through.
+fn find(args: &Find) -> Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut unreadable = 0;
+    for path in walk(&args.paths) {
+        match path {
+            Ok(path) if args.globs.iter().any(|glob| glob.is_match(&path)) => {
+                writeln!(out, "{}", display_path(&path).display())?;
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("grrs: {}", err);
+                unreadable += 1;
+            }
+        }
+    }
+    out.flush()?;
+    if unreadable > 0 {
+        std::process::exit(2);
+    }
+
+    Ok(())
+}
+
+/// Writes an index of the files under the paths, leaving out the index itself
+/// and files that aren't text.
+fn index(args: &Index) -> Result<()> {
+    let output = args.output.canonicalize().ok();
+    let (built, errors) = index::Index::build(&args.paths, |path| {
+        (output.as_ref()).is_none_or(|output| path.canonicalize().ok().as_ref() != Some(output))
+    });
+    let mut unreadable = 0;
+    for err in errors {
+        if let GrrsError::Open {
+            failure: OpenFailure::Binary,
+            ..
+        } = err
+        {
+            continue;
+        }
+        eprintln!("grrs: {}", err);
+        unreadable += 1;
+    }
+    built
+        .save(&args.output)
+        .with_context(|| format!("could not write index `{}`", args.output.display()))?;
+    eprintln!(
+        "grrs: indexed {} files into {}",
+        built.len(),
+        args.output.display()
+    );
+    if unreadable > 0 {
+        std::process::exit(2);
+    }
+
+    Ok(())
+}
+
+/// Searches both files, printing the matching lines only one of them has.
+fn diff(args: &Diff, options: SearchOptions) -> Result<()> {
+    check_pattern(&args.pattern)?;
+    let searcher = Searcher::new(without_context(options));
+    let matcher = args.filters.matcher(&searcher, &args.pattern)?;
+    let matches = |path: &Path| -> Result<(Vec<_>, SearchSummary)> {
+        let mut sink = CollectSink::default();
+        let lines = read_lines(open(path, &args.common)?, &args.common);
+        let summary = args
+            .filters
+            .search(&searcher, &*matcher, lines, &mut sink)?;
+        let lines = (sink.events.into_iter())
+            .filter_map(|event| match event {
+                SinkEvent::Match { line_number, line } => Some((line_number, line.into_owned())),
+                _ => None,
+            })
+            .collect();
+        Ok((lines, summary))
+    };
+    let (a, read_a) = matches(&args.a)?;
+    interrupt::exit_if_interrupted(format!("{} of {}", read(&read_a), args.a.display()));
+    let (b, read_b) = matches(&args.b)?;
+    interrupt::exit_if_interrupted(format!("{} of {}", read(&read_b), args.b.display()));
+
+    // Each side is marked like a unified diff, from the first file to the
+    // second.
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    for (sign, lines) in [
+        ('-', compare::only_in(&a, &b)),
+        ('+', compare::only_in(&b, &a)),
+    ] {
+        for (line_number, line) in lines {
+            writeln!(out, "{} LINE# {}: {}", sign, line_number, line)?;
+        }
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+fn log(args: &Log, options: SearchOptions) -> Result<()> {
+    check_pattern(&args.pattern)?;
+    let matcher = Searcher::new(options).matcher(&args.pattern)?;
+    let filter = git::LogFilter {
+        since: args.since.clone(),
+        author: args.author.clone(),
+    };
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    git::log(
+        &filter,
+        |line| matcher.is_match(line),
+        |commit| {
+            let id = commit.id.get(..12).unwrap_or(&commit.id);
+            writeln!(
+                out,
+                "{} {} {}: {}",
+                id, commit.date, commit.author, commit.subject
+            )?;
+            for change in &commit.changes {
+                let sign = if change.added { '+' } else { '-' };
+                writeln!(out, "  {} {}: {}", sign, change.path.display(), change.text)?;
+            }
+            Ok(())
+        },
+    )?;
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Reads a file into memory, then searches it for each pattern read from
+/// stdin until it closes, recalling `!N` from the history. The prompt goes to
+/// stderr, so results can still be piped elsewhere.
+fn interactive(args: &Interactive, options: SearchOptions, recording: Option<&Path>) -> Result<()> {
+    let lines =
+        read_lines(open(&args.path, &args.common)?, &args.common).collect::<Result<Vec<_>, _>>()?;
+    let mut recorded = match history::default_path() {
+        Some(path) => history::load(&path)?,
+        None => Vec::new(),
+    };
+    let searcher = Searcher::new(options);
+    let mut sink = WriterSink::stdout();
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    loop {
+        eprint!("> ");
+        let mut pattern = String::new();
+        if input.read_line(&mut pattern)? == 0 {
+            eprintln!();
+            return Ok(());
+        }
+        let pattern = pattern.trim_end_matches(&['\r', '\n'][..]);
+        if pattern.trim().is_empty() {
+            continue;
+        }
+        let pattern = match history::recall(&recorded, pattern) {
+            Ok(Some(recalled)) => {
+                eprintln!("{}", recalled);
+                recalled.to_string()
+            }
+            Ok(None) => pattern.to_string(),
+            Err(err) => {
+                eprintln!("error: {:#}", err);
+                continue;
+            }
+        };
+        if let Some(path) = recording {
+            if let Err(err) = history::record(path, &pattern) {
+                log::warn!("{:#}", err);
+            }
+            recorded.push(pattern.clone());
+        }
+
+        // A bad pattern shouldn't end the session.
+        let searched = args
+            .filters
+            .matcher(&searcher, &pattern)
+            .and_then(|matcher| {
+                let lines = lines.iter().map(|(num, line)| Ok((*num, line)));
+                Ok(args
+                    .filters
+                    .search(&searcher, &*matcher, lines, &mut sink)?)
+            });
+        match searched {
+            Ok(summary) => eprintln!(
+                "{} of {} lines matched",
+                summary.matched_lines, summary.total_lines
+            ),
+            Err(err) => eprintln!("error: {:#}", err),
+        }
+    }
+}
+
+/// Writes the recorded patterns, numbered from 1.
+fn list_history() -> Result<()> {
+    let path =
+        history::default_path().ok_or_else(|| anyhow!("could not find the home directory"))?;
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for (number, pattern) in (1..).zip(history::load(&path)?) {
+        writeln!(out, "{:>5}  {}", number, pattern)?;
+    }
+
+    Ok(())
+}
+
+/// Describes how far a search read, for when it's interrupted.
+fn read(summary: &SearchSummary) -> String {
+    format!(
+        "reading {} lines, of which {} matched",
+        summary.total_lines, summary.matched_lines
+    )
+}
+
+/// Describes what a search found, for a notification.
+#[cfg(feature = "notify")]
+fn found(summary: &SearchSummary, place: &dyn std::fmt::Display) -> String {
+    format!(
+        "{} of {} lines matched in {}",
+        summary.matched_lines, summary.total_lines, place
+    )
+}
+
+/// Drops the context options, for subcommands that never print lines.
+fn without_context(options: SearchOptions) -> SearchOptions {
+    SearchOptions {
+        before_context: 0,
+        after_context: 0,
+        context: None,
+        after_context_until: None,
+        passthru: false,
+        ..options
+    }
+}
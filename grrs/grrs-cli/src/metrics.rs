@@ -0,0 +1,193 @@
+//! What `grrs serve` counts while it runs, written in the Prometheus text
+//! format for `GET /metrics`.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The upper bounds, in seconds, of the search latency histogram's buckets.
+const BUCKETS: [f64; 10] = [0.0005, 0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.5, 1.0, 5.0];
+
+/// How a search was asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Socket,
+    Http,
+    Grpc,
+}
+
+impl Transport {
+    const ALL: [Transport; 3] = [Transport::Socket, Transport::Http, Transport::Grpc];
+
+    fn name(self) -> &'static str {
+        match self {
+            Transport::Socket => "socket",
+            Transport::Http => "http",
+            Transport::Grpc => "grpc",
+        }
+    }
+}
+
+/// Counters shared by every connection.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    searches: [AtomicU64; 3],
+    bytes_scanned: AtomicU64,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    /// The searches that took no longer than each of BUCKETS
+    latency_buckets: [AtomicU64; BUCKETS.len()],
+    latency_micros: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts a search that's been answered, and how long it took.
+    pub fn searched(&self, transport: Transport, elapsed: Duration) {
+        self.searches[transport as usize].fetch_add(1, Ordering::Relaxed);
+        let seconds = elapsed.as_secs_f64();
+        for (bucket, bound) in self.latency_buckets.iter().zip(BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        (self.latency_micros).fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Counts the bytes of the lines read by a search.
+    pub fn scanned(&self, bytes: u64) {
+        self.bytes_scanned.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts a file being searched from memory or read again.
+    pub fn cached(&self, hit: bool) {
+        match hit {
+            true => self.cache_hits.fetch_add(1, Ordering::Relaxed),
+            false => self.cache_misses.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Writes every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let get = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut out = String::new();
+        header(
+            &mut out,
+            "grrs_searches_total",
+            "counter",
+            "Searches answered.",
+        );
+        for transport in Transport::ALL {
+            let searches = get(&self.searches[transport as usize]);
+            let _ = writeln!(
+                out,
+                "grrs_searches_total{{transport=\"{}\"}} {}",
+                transport.name(),
+                searches
+            );
+        }
+
+        let (hits, misses) = (get(&self.cache_hits), get(&self.cache_misses));
+        let ratio = match hits + misses {
+            0 => 0.0,
+            total => hits as f64 / total as f64,
+        };
+        let values = [
+            (
+                "grrs_bytes_scanned_total",
+                "counter",
+                "Bytes of lines searched.",
+                get(&self.bytes_scanned) as f64,
+            ),
+            (
+                "grrs_cache_hits_total",
+                "counter",
+                "Files searched from memory.",
+                hits as f64,
+            ),
+            (
+                "grrs_cache_misses_total",
+                "counter",
+                "Files read because they weren't in memory or had changed.",
+                misses as f64,
+            ),
+            (
+                "grrs_cache_hit_ratio",
+                "gauge",
+                "The share of files searched from memory.",
+                ratio,
+            ),
+        ];
+        for (name, kind, help, value) in values {
+            header(&mut out, name, kind, help);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+
+        let name = "grrs_search_duration_seconds";
+        header(
+            &mut out,
+            name,
+            "histogram",
+            "How long searches took to answer.",
+        );
+        for (bucket, bound) in self.latency_buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, get(bucket));
+        }
+        let count: u64 = self.searches.iter().map(get).sum();
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(
+            out,
+            "{}_sum {}",
+            name,
+            get(&self.latency_micros) as f64 / 1e6
+        );
+        let _ = writeln!(out, "{}_count {}", name, count);
+
+        out
+    }
+}
+
+/// Writes the lines that describe a metric.
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_metrics() {
+        let metrics = Metrics::default();
+        metrics.searched(Transport::Http, Duration::from_millis(20));
+        metrics.searched(Transport::Socket, Duration::from_secs(2));
+        metrics.scanned(1024);
+        metrics.cached(false);
+        for _ in 0..3 {
+            metrics.cached(true);
+        }
+
+        let rendered = metrics.render();
+        for line in [
+            "# TYPE grrs_searches_total counter",
+            "grrs_searches_total{transport=\"socket\"} 1",
+            "grrs_searches_total{transport=\"http\"} 1",
+            "grrs_searches_total{transport=\"grpc\"} 0",
+            "grrs_bytes_scanned_total 1024",
+            "grrs_cache_hits_total 3",
+            "grrs_cache_misses_total 1",
+            "grrs_cache_hit_ratio 0.75",
+            "grrs_search_duration_seconds_bucket{le=\"0.01\"} 0",
+            "grrs_search_duration_seconds_bucket{le=\"0.025\"} 1",
+            "grrs_search_duration_seconds_bucket{le=\"5\"} 2",
+            "grrs_search_duration_seconds_bucket{le=\"+Inf\"} 2",
+            "grrs_search_duration_seconds_sum 2.02",
+            "grrs_search_duration_seconds_count 2",
+        ] {
+            assert!(
+                rendered.lines().any(|rendered| rendered == line),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Desktop notifications for `--notify`, so a long search can be left to
+//! finish while working on something else.
+
+use notify_rust::Notification;
+use std::time::{Duration, Instant};
+
+/// Times a search, to say when it's done if it took long enough.
+#[derive(Debug)]
+pub struct Timer {
+    started: Instant,
+    threshold: Option<Duration>,
+}
+
+impl Timer {
+    /// Starts timing a search, which is notified if it takes at least this
+    /// many seconds, or never without any.
+    pub fn start(seconds: Option<u64>) -> Timer {
+        Timer {
+            started: Instant::now(),
+            threshold: seconds.map(Duration::from_secs),
+        }
+    }
+
+    fn due(&self) -> bool {
+        (self.threshold).is_some_and(|threshold| self.started.elapsed() >= threshold)
+    }
+
+    /// Notifies that the search is done, if it took long enough. Failing to is
+    /// only logged, as the search itself succeeded.
+    pub fn finish(&self, body: &str) {
+        if !self.due() {
+            return;
+        }
+        let shown = Notification::new()
+            .appname("grrs")
+            .summary("grrs finished searching")
+            .body(body)
+            .show();
+        if let Err(err) = shown {
+            log::warn!("could not send a notification: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_long_searches() {
+        assert!(!Timer::start(None).due());
+        assert!(Timer::start(Some(0)).due());
+        assert!(!Timer::start(Some(3600)).due());
+    }
+}
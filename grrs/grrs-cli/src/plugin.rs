@@ -0,0 +1,383 @@
+//! Matchers and formatters from third parties, loaded from the shared
+//! libraries in `~/.config/grrs/plugins` when `--matcher` or `--formatter`
+//! names one.
+//!
+//! A plugin is a cdylib exporting, with the C ABI:
+//!
+//! ```c
+//! /* Every plugin: the name it's chosen by, which must stay valid. */
+//! const char *grrs_plugin_name(void);
+//!
+//! /* A matcher: compile a NUL-terminated pattern, returning null if it's
+//!  * invalid; find the first match in `len` bytes of UTF-8, storing its byte
+//!  * range; and release a compiled pattern. Finding may happen on several
+//!  * threads at once. */
+//! void *grrs_matcher_new(const char *pattern);
+//! bool grrs_matcher_find(const void *matcher, const char *line, size_t len,
+//!                        size_t *start, size_t *end);
+//! void grrs_matcher_free(void *matcher);
+//!
+//! /* A formatter: write an event, as grrs-ffi's GrrsEventKind numbers them,
+//!  * to at most `capacity` bytes of `out`, returning how many bytes it needs.
+//!  * grrs calls again with more room if that's more than it gave. */
+//! size_t grrs_format(uint32_t kind, uint64_t line_number, const char *line,
+//!                    size_t len, char *out, size_t capacity);
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use grrs_core::config::Config;
+use grrs_core::format::Formatter;
+use grrs_core::matcher::Matcher;
+use grrs_core::sink::SinkEvent;
+use grrs_core::GrrsError;
+use libloading::Library;
+use std::ffi::{c_void, CStr, CString};
+use std::io::{ErrorKind, Write};
+use std::ops::Range;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+type NameFn = unsafe extern "C" fn() -> *const c_char;
+type NewFn = unsafe extern "C" fn(*const c_char) -> *mut c_void;
+type FindFn =
+    unsafe extern "C" fn(*const c_void, *const c_char, usize, *mut usize, *mut usize) -> bool;
+type FreeFn = unsafe extern "C" fn(*mut c_void);
+type FormatFn = unsafe extern "C" fn(u32, u64, *const c_char, usize, *mut c_char, usize) -> usize;
+
+/// The matcher functions a plugin exports.
+#[derive(Debug, Clone, Copy)]
+struct MatcherApi {
+    new: NewFn,
+    find: FindFn,
+    free: FreeFn,
+}
+
+/// A loaded plugin, offering a matcher, a formatter or both.
+#[derive(Debug)]
+pub struct Plugin {
+    pub name: String,
+    matcher: Option<MatcherApi>,
+    format: Option<FormatFn>,
+    /// Kept open for as long as anything made by the plugin is in use
+    library: Option<Arc<Library>>,
+}
+
+/// Returns where plugins are looked for: `grrs/plugins` beside the
+/// configuration file.
+pub fn default_dir() -> Option<PathBuf> {
+    Some(Config::default_path()?.with_file_name("plugins"))
+}
+
+/// Loads every shared library in a directory, in order of their file names,
+/// or none if there's no such directory.
+pub fn discover(dir: &Path) -> Result<Vec<Plugin>> {
+    let context = || format!("could not read plugins `{}`", dir.display());
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(context),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry.with_context(context)?.path();
+        if path
+            .extension()
+            .is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION)
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    paths.iter().map(|path| Plugin::load(path)).collect()
+}
+
+/// Returns the plugin of this name.
+pub fn find<'p>(plugins: &'p [Plugin], name: &str) -> Result<&'p Plugin> {
+    plugins
+        .iter()
+        .find(|plugin| plugin.name == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = plugins.iter().map(|plugin| plugin.name.as_str()).collect();
+            match names.is_empty() {
+                true => anyhow!("no plugin named `{}`, as no plugins are installed", name),
+                false => anyhow!(
+                    "no plugin named `{}`, expected one of {}",
+                    name,
+                    names.join(", ")
+                ),
+            }
+        })
+}
+
+/// Loads the plugins installed in the default directory.
+pub fn installed() -> Result<Vec<Plugin>> {
+    match default_dir() {
+        Some(dir) => discover(&dir),
+        None => Ok(Vec::new()),
+    }
+}
+
+impl Plugin {
+    /// Loads a plugin from a shared library.
+    pub fn load(path: &Path) -> Result<Plugin> {
+        let context = || format!("could not load plugin `{}`", path.display());
+        // Loading runs the library's initialisers, so a plugin is trusted as
+        // much as grrs itself.
+        let library = unsafe { Library::new(path) }.with_context(context)?;
+        // The symbols are functions of the types documented above.
+        let plugin = unsafe {
+            let name = (library.get::<NameFn>(b"grrs_plugin_name"))
+                .map(|name| *name)
+                .with_context(context)?;
+            let matcher = match (
+                library.get::<NewFn>(b"grrs_matcher_new"),
+                library.get::<FindFn>(b"grrs_matcher_find"),
+                library.get::<FreeFn>(b"grrs_matcher_free"),
+            ) {
+                (Ok(new), Ok(find), Ok(free)) => Some(MatcherApi {
+                    new: *new,
+                    find: *find,
+                    free: *free,
+                }),
+                _ => None,
+            };
+            let format = library.get::<FormatFn>(b"grrs_format").ok().map(|f| *f);
+            Plugin::new(name, matcher, format)
+        };
+        let mut plugin = plugin.with_context(context)?;
+        plugin.library = Some(Arc::new(library));
+
+        Ok(plugin)
+    }
+
+    /// Makes a plugin from the functions it exports.
+    ///
+    /// # Safety
+    ///
+    /// The functions must behave as documented above.
+    unsafe fn new(
+        name: NameFn,
+        matcher: Option<MatcherApi>,
+        format: Option<FormatFn>,
+    ) -> Result<Plugin> {
+        let name = name();
+        if name.is_null() {
+            return Err(anyhow!("`grrs_plugin_name` returned null"));
+        }
+        let name = (CStr::from_ptr(name).to_str())
+            .context("the plugin's name isn't valid UTF-8")?
+            .to_string();
+
+        Ok(Plugin {
+            name,
+            matcher,
+            format,
+            library: None,
+        })
+    }
+
+    /// Compiles a pattern with the plugin's matcher.
+    pub fn matcher(&self, pattern: &str) -> Result<Box<dyn Matcher>> {
+        let api = (self.matcher)
+            .ok_or_else(|| anyhow!("plugin `{}` doesn't provide a matcher", self.name))?;
+        let pattern = CString::new(pattern).context("the pattern contains a NUL byte")?;
+        let handle = unsafe { (api.new)(pattern.as_ptr()) };
+        if handle.is_null() {
+            return Err(anyhow!("plugin `{}` rejected the pattern", self.name));
+        }
+
+        Ok(Box::new(PluginMatcher {
+            handle,
+            api,
+            _library: self.library.clone(),
+        }))
+    }
+
+    /// Returns the plugin's formatter.
+    pub fn formatter(&self) -> Result<Box<dyn Formatter>> {
+        let format = (self.format)
+            .ok_or_else(|| anyhow!("plugin `{}` doesn't provide a formatter", self.name))?;
+
+        Ok(Box::new(PluginFormatter {
+            format,
+            buffer: vec![0; 256],
+            _library: self.library.clone(),
+        }))
+    }
+}
+
+/// A pattern compiled by a plugin.
+struct PluginMatcher {
+    handle: *mut c_void,
+    api: MatcherApi,
+    _library: Option<Arc<Library>>,
+}
+
+// The ABI requires that a compiled pattern can be used from any thread, and
+// from several at once.
+unsafe impl Send for PluginMatcher {}
+unsafe impl Sync for PluginMatcher {}
+
+impl Matcher for PluginMatcher {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        let (mut start, mut end) = (0, 0);
+        let found = unsafe {
+            (self.api.find)(
+                self.handle,
+                line.as_ptr().cast(),
+                line.len(),
+                &mut start,
+                &mut end,
+            )
+        };
+        // A range that isn't whole characters of the line counts as no match.
+        (found && line.get(start..end).is_some()).then_some(start..end)
+    }
+}
+
+impl Drop for PluginMatcher {
+    fn drop(&mut self) {
+        unsafe { (self.api.free)(self.handle) }
+    }
+}
+
+/// Writes events as a plugin formats them.
+struct PluginFormatter {
+    format: FormatFn,
+    buffer: Vec<u8>,
+    _library: Option<Arc<Library>>,
+}
+
+impl Formatter for PluginFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        let (kind, line_number, line) = match event {
+            SinkEvent::Match { line_number, line } => (0, *line_number, line.as_ref()),
+            SinkEvent::Context { line_number, line } => (1, *line_number, line.as_ref()),
+            SinkEvent::Break => (2, 0, ""),
+            SinkEvent::Heading { line_number, line } => (3, *line_number, line.as_ref()),
+        };
+        let function = self.format;
+        let format = |buffer: &mut [u8]| unsafe {
+            let out = buffer.as_mut_ptr().cast();
+            function(
+                kind,
+                line_number,
+                line.as_ptr().cast(),
+                line.len(),
+                out,
+                buffer.len(),
+            )
+        };
+        let mut needed = format(&mut self.buffer);
+        if needed > self.buffer.len() {
+            self.buffer.resize(needed, 0);
+            needed = format(&mut self.buffer);
+        }
+        match self.buffer.get(..needed) {
+            Some(formatted) => Ok(writer.write_all(formatted)?),
+            None => {
+                Err(std::io::Error::other("a formatter plugin kept asking for more room").into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" fn name() -> *const c_char {
+        b"upper\0".as_ptr().cast()
+    }
+
+    /// Matches the pattern in upper case only.
+    unsafe extern "C" fn new(pattern: *const c_char) -> *mut c_void {
+        let pattern = CStr::from_ptr(pattern).to_string_lossy().to_uppercase();
+        Box::into_raw(Box::new(pattern)).cast()
+    }
+
+    unsafe extern "C" fn find(
+        matcher: *const c_void,
+        line: *const c_char,
+        len: usize,
+        start: *mut usize,
+        end: *mut usize,
+    ) -> bool {
+        let pattern = &*(matcher as *const String);
+        let line = std::str::from_utf8_unchecked(std::slice::from_raw_parts(line.cast(), len));
+        match line.find(pattern.as_str()) {
+            Some(at) => {
+                (*start, *end) = (at, at + pattern.len());
+                true
+            }
+            None => false,
+        }
+    }
+
+    unsafe extern "C" fn free(matcher: *mut c_void) {
+        drop(Box::from_raw(matcher as *mut String));
+    }
+
+    unsafe extern "C" fn format(
+        kind: u32,
+        line_number: u64,
+        line: *const c_char,
+        len: usize,
+        out: *mut c_char,
+        capacity: usize,
+    ) -> usize {
+        let line = std::slice::from_raw_parts(line as *const u8, len);
+        let formatted = format!(
+            "{}@{} {}\n",
+            kind,
+            line_number,
+            String::from_utf8_lossy(line)
+        );
+        if formatted.len() <= capacity {
+            std::ptr::copy_nonoverlapping(formatted.as_ptr(), out.cast(), formatted.len());
+        }
+        formatted.len()
+    }
+
+    #[test]
+    fn use_a_plugin() -> Result<()> {
+        let api = MatcherApi { new, find, free };
+        let plugin = unsafe { Plugin::new(name, Some(api), Some(format)) }?;
+        assert_eq!(plugin.name, "upper");
+
+        let matcher = plugin.matcher("todo")?;
+        assert_eq!(matcher.find("todo: TODO"), Some(6..10));
+        assert!(!matcher.is_match("todo"));
+
+        let mut formatter = plugin.formatter()?;
+        let mut output = Vec::new();
+        let line = "x".repeat(300);
+        let event = SinkEvent::Match {
+            line_number: 2,
+            line: line.as_str().into(),
+        };
+        formatter.format(&event, &mut output)?;
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        assert_eq!(String::from_utf8(output)?, format!("0@2 {}\n2@0 \n", line));
+
+        Ok(())
+    }
+
+    #[test]
+    fn discover_plugins() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        assert!(discover(&dir.path().join("missing"))?.is_empty());
+        std::fs::write(dir.path().join("README.txt"), "not a plugin")?;
+        assert!(discover(dir.path())?.is_empty());
+
+        let library = format!("broken.{}", std::env::consts::DLL_EXTENSION);
+        std::fs::write(dir.path().join(&library), "not a library either")?;
+        let err = discover(dir.path()).unwrap_err();
+        assert!(err.to_string().starts_with("could not load plugin"));
+        assert!(super::find(&[], "upper").is_err());
+
+        Ok(())
+    }
+}
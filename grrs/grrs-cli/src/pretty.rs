@@ -0,0 +1,166 @@
+//! Syntax highlighting for `--pretty`, so matches in source code read as
+//! they would in an editor.
+
+use grrs_core::format::Formatter;
+use grrs_core::sink::SinkEvent;
+use grrs_core::GrrsError;
+use std::io::Write;
+use std::path::Path;
+use syntect::highlighting::{HighlightIterator, HighlightState, Highlighter, Theme, ThemeSet};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+/// The theme lines are highlighted with.
+const THEME: &str = "base16-ocean.dark";
+
+/// Writes events like grrs_core::format::LineFormatter, highlighting each
+/// line in the syntax its file's extension suggests, with the line numbers of
+/// matches in bold.
+///
+/// Each run of adjacent lines is highlighted from where it starts, so a
+/// string or comment opened on a line that isn't displayed isn't recognised.
+pub struct PrettyFormatter {
+    syntaxes: SyntaxSet,
+    theme: Theme,
+    syntax: SyntaxReference,
+    /// The number of the line after the one last highlighted, and the state
+    /// it carries on from
+    state: Option<(u64, ParseState, HighlightState)>,
+}
+
+impl std::fmt::Debug for PrettyFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("PrettyFormatter")
+            .field(&self.syntax.name)
+            .finish()
+    }
+}
+
+impl PrettyFormatter {
+    /// Highlights the lines of the file at the path, falling back to its
+    /// first line, then to plain text, for a syntax.
+    pub fn for_file(path: &Path) -> PrettyFormatter {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let syntax = (syntaxes.find_syntax_for_file(path).ok().flatten())
+            .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+            .clone();
+        let theme = ThemeSet::load_defaults().themes.remove(THEME);
+
+        PrettyFormatter {
+            theme: theme.expect("the default themes include THEME"),
+            syntax,
+            syntaxes,
+            state: None,
+        }
+    }
+
+    /// Returns the line with escapes colouring its syntax, carrying on from
+    /// the line before if that was the last one highlighted.
+    fn highlight(&mut self, line_number: u64, line: &str) -> Result<String, GrrsError> {
+        let highlighter = Highlighter::new(&self.theme);
+        if self
+            .state
+            .as_ref()
+            .is_none_or(|(next, ..)| *next != line_number)
+        {
+            let highlight = HighlightState::new(&highlighter, ScopeStack::new());
+            self.state = Some((line_number, ParseState::new(&self.syntax), highlight));
+        }
+        let (next, parse, highlight) = self.state.as_mut().expect("the state was just set");
+        *next = line_number + 1;
+        // The default syntaxes expect each line to end in a newline.
+        let line = format!("{}\n", line);
+        let changes = (parse.parse_line(&line, &self.syntaxes))
+            .map_err(|err| GrrsError::InvalidArgument(format!("could not highlight: {}", err)))?;
+        let ranges: Vec<_> =
+            HighlightIterator::new(highlight, &changes, &line, &highlighter).collect();
+        let escaped = as_24_bit_terminal_escaped(&ranges, false);
+
+        Ok(format!("{}\x1b[0m", escaped.trim_end_matches('\n')))
+    }
+}
+
+impl Formatter for PrettyFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                let line = self.highlight(*line_number, line)?;
+                writeln!(writer, "\x1b[1mLINE# {}:\x1b[0m {}", line_number, line)?
+            }
+            SinkEvent::Context { line_number, line } => {
+                let line = self.highlight(*line_number, line)?;
+                writeln!(writer, "LINE# {}- {}", line_number, line)?
+            }
+            SinkEvent::Break => writeln!(writer, "--")?,
+            SinkEvent::Heading { line_number, line } => {
+                let line = self.highlight(*line_number, line)?;
+                writeln!(writer, "LINE# {}= {}", line_number, line)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Drops the escapes from highlighted text.
+    fn plain(text: &str) -> String {
+        let mut plain = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('\x1b') {
+            plain.push_str(&rest[..start]);
+            rest = rest[start..].split_once('m').map_or("", |(_, after)| after);
+        }
+        plain + rest
+    }
+
+    #[test]
+    fn highlight_lines() -> Result<(), GrrsError> {
+        let mut formatter = PrettyFormatter::for_file(Path::new("main.rs"));
+        assert_eq!(formatter.syntax.name, "Rust");
+        let mut output = Vec::new();
+        for event in [
+            SinkEvent::Context {
+                line_number: 1,
+                line: "/* a comment".into(),
+            },
+            SinkEvent::Match {
+                line_number: 2,
+                line: "fn main() {} */".into(),
+            },
+            SinkEvent::Match {
+                line_number: 9,
+                line: "fn main() {}".into(),
+            },
+        ] {
+            formatter.format(&event, &mut output)?;
+        }
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(
+            lines.iter().map(|line| plain(line)).collect::<Vec<_>>(),
+            [
+                "LINE# 1- /* a comment",
+                "LINE# 2: fn main() {} */",
+                "LINE# 9: fn main() {}"
+            ]
+        );
+        // The comment carries on to the next line, but not past a gap.
+        let colour = |line: &str| {
+            line[..line.find("fn").unwrap()]
+                .rsplit('\x1b')
+                .next()
+                .map(String::from)
+        };
+        assert_ne!(colour(lines[1]), colour(lines[2]));
+        assert!(lines[2].contains("\x1b[38;2;"));
+
+        let formatter = PrettyFormatter::for_file(Path::new("notes.unknown"));
+        assert_eq!(formatter.syntax.name, "Plain Text");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! Status events written to stderr as a JSON object a line while files are
+//! searched, for wrappers to show live progress with while results still go to
+//! stdout.
+
+use serde_json::json;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How often an event is written while searching.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Writes a `progress` event every so often from its own thread, until
+/// finished with a `done` event.
+pub struct Progress {
+    state: Arc<State>,
+    ticker: JoinHandle<()>,
+}
+
+struct State {
+    started: Instant,
+    /// How many files have been searched
+    files: AtomicU64,
+    /// How many bytes have been read from them
+    bytes: AtomicU64,
+    /// The file last started on
+    path: Mutex<Option<PathBuf>>,
+    finished: AtomicBool,
+}
+
+impl Progress {
+    /// Starts writing events.
+    pub fn start() -> Progress {
+        let state = Arc::new(State {
+            started: Instant::now(),
+            files: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            path: Mutex::new(None),
+            finished: AtomicBool::new(false),
+        });
+        let ticking = state.clone();
+        let ticker = std::thread::spawn(move || loop {
+            std::thread::park_timeout(INTERVAL);
+            if ticking.finished.load(Ordering::SeqCst) {
+                break;
+            }
+            ticking.write("progress");
+        });
+
+        Progress { state, ticker }
+    }
+
+    /// Reads a file through, counting its bytes, and noting it as the one
+    /// being searched until another starts.
+    pub fn reader(&self, path: &Path, inner: Box<dyn BufRead>) -> Box<dyn BufRead> {
+        *self.state.path.lock().unwrap() = Some(path.to_path_buf());
+        Box::new(Counted {
+            inner,
+            state: self.state.clone(),
+        })
+    }
+
+    /// Counts a file as searched.
+    pub fn searched(&self) {
+        self.state.files.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Stops writing `progress` events, and writes the `done` one.
+    pub fn finish(self) {
+        self.state.finished.store(true, Ordering::SeqCst);
+        self.ticker.thread().unpark();
+        let _ = self.ticker.join();
+        self.state.write("done");
+    }
+}
+
+impl State {
+    fn event(&self, event: &str) -> serde_json::Value {
+        let bytes = self.bytes.load(Ordering::SeqCst);
+        let elapsed = self.started.elapsed();
+        let per_second = match elapsed.as_secs_f64() {
+            secs if secs > 0.0 => (bytes as f64 / secs) as u64,
+            _ => 0,
+        };
+        json!({
+            "event": event,
+            "files": self.files.load(Ordering::SeqCst),
+            "path": self.path.lock().unwrap().as_ref().map(|path| path.display().to_string()),
+            "bytes": bytes,
+            "bytes_per_second": per_second,
+            "elapsed_ms": elapsed.as_millis() as u64,
+        })
+    }
+
+    /// Writes an event, ignoring a closed stderr as there's nowhere to report
+    /// it.
+    fn write(&self, event: &str) {
+        let line = self.event(event).to_string();
+        let _ = writeln!(io::stderr().lock(), "{}", line);
+    }
+}
+
+/// Reads as the inner reader does, adding up the bytes read.
+struct Counted {
+    inner: Box<dyn BufRead>,
+    state: Arc<State>,
+}
+
+impl Read for Counted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.state.bytes.fetch_add(read as u64, Ordering::SeqCst);
+        Ok(read)
+    }
+}
+
+impl BufRead for Counted {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.state.bytes.fetch_add(amt as u64, Ordering::SeqCst);
+        self.inner.consume(amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_the_bytes_read() -> io::Result<()> {
+        let progress = Progress::start();
+        let mut reader = progress.reader(Path::new("a.txt"), Box::new(&b"lorem\nipsum\n"[..]));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest)?;
+        progress.searched();
+        let event = progress.state.event("done");
+        assert_eq!(event["files"], 1);
+        assert_eq!(event["path"], "a.txt");
+        assert_eq!(event["bytes"], 12);
+        progress.finish();
+
+        Ok(())
+    }
+}
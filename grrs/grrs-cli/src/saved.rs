@@ -0,0 +1,133 @@
+//! Patterns saved under names with `grrs pattern save`, kept in
+//! `~/.config/grrs/patterns.toml` and searched for with `--use <name>` in place
+//! of a pattern.
+
+use anyhow::{anyhow, Context, Result};
+use grrs_core::config::{SavedPattern, SavedPatterns};
+use grrs_core::SearchOptions;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::clap::ArgMatches;
+use structopt::StructOpt;
+
+/// The subcommands whose first argument is a pattern, which `--use` stands in
+/// for.
+const TAKING_PATTERNS: &[&str] = &["search", "count", "replace", "files", "diff", "log"];
+
+/// What `grrs pattern` does with the saved patterns.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Save a pattern under a name, along with any search options given, such
+    /// as `-i`, replacing any saved under it before
+    Save {
+        /// The name to use the pattern by, such as `deprecated-api`
+        name: String,
+        /// The pattern to save
+        pattern: String,
+        #[structopt(flatten)]
+        options: SearchOptions,
+    },
+    /// List the names and patterns saved
+    List {},
+}
+
+pub fn run(command: &Command, flags: &ArgMatches) -> Result<()> {
+    let path = default_path()?;
+    match command {
+        Command::Save {
+            name,
+            pattern,
+            options,
+        } => {
+            if pattern.starts_with('-') {
+                return Err(anyhow!(
+                    "patterns starting with `-` can't be saved, as they'd be taken for a flag"
+                ));
+            }
+            // Only the options given are saved, named like their flags.
+            let given = flags
+                .subcommand_matches("save")
+                .expect("saving was asked for");
+            let saved = SavedPattern::new(pattern, options, |name| {
+                given.occurrences_of(name.replace('_', "-")) > 0
+            });
+            let mut patterns = SavedPatterns::load(&path)?;
+            patterns.insert(name, saved);
+            patterns
+                .save(&path)
+                .with_context(|| format!("could not save patterns to `{}`", path.display()))?;
+        }
+        Command::List {} => {
+            let stdout = std::io::stdout();
+            let mut out = stdout.lock();
+            for (name, saved) in SavedPatterns::load(&path)?.iter() {
+                writeln!(out, "{}  {}", name, saved.pattern)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Looks up the pattern saved under a name.
+pub fn find(name: &str) -> Result<SavedPattern> {
+    let saved = SavedPatterns::load(default_path()?)?;
+    match saved.get(name) {
+        Some(saved) => Ok(saved.clone()),
+        None => Err(anyhow!(
+            "there's no pattern saved as `{}`, see `grrs pattern list`",
+            name
+        )),
+    }
+}
+
+/// Puts the pattern saved under the name given with `--use` in place of the
+/// pattern argument, just after the subcommand, so the arguments then parse
+/// as if it had been typed.
+pub fn with_saved_pattern(mut args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let given = (args.iter().skip(2))
+        .map(|arg| arg.to_str())
+        .take_while(|&arg| arg != Some("--"))
+        .collect::<Vec<_>>();
+    let name = (given.iter().enumerate()).find_map(|(at, arg)| match arg {
+        Some("--use") => given.get(at + 1).copied().flatten(),
+        Some(arg) => arg.strip_prefix("--use="),
+        None => None,
+    });
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => return Ok(args),
+    };
+    let subcommand = args.get(1).and_then(|arg| arg.to_str());
+    if !subcommand.is_some_and(|subcommand| TAKING_PATTERNS.contains(&subcommand)) {
+        return Err(anyhow!(
+            "--use only goes with subcommands that take a pattern"
+        ));
+    }
+    args.insert(2, find(&name)?.pattern.into());
+
+    Ok(args)
+}
+
+fn default_path() -> Result<PathBuf> {
+    SavedPatterns::default_path().ok_or_else(|| anyhow!("could not find the home directory"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leave_arguments_without_use_alone() -> Result<()> {
+        let args: Vec<OsString> = ["grrs", "search", "--", "--use", "a.txt"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(with_saved_pattern(args.clone())?, args);
+        let args = vec!["grrs".into(), "find".into(), "--use=todo".into()];
+        assert!(with_saved_pattern(args).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+//! Rhai scripts run on each line with `--filter-script`, for filters and
+//! rewrites the flags can't express.
+
+use grrs_core::GrrsError;
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::fmt;
+
+/// A compiled script, which sees each line as `line` and its number as
+/// `line_number`. Returning `true` or `false` keeps or drops the line, and
+/// returning a string searches that in its place.
+pub struct Script {
+    source: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl fmt::Debug for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Script").field(&self.source).finish()
+    }
+}
+
+impl Script {
+    /// Compiles a script, failing if it isn't valid Rhai.
+    pub fn compile(source: &str) -> Result<Script, GrrsError> {
+        let engine = Engine::new();
+        let ast = (engine.compile(source))
+            .map_err(|err| GrrsError::InvalidArgument(format!("invalid script: {}", err)))?;
+
+        Ok(Script {
+            source: source.to_string(),
+            engine,
+            ast,
+        })
+    }
+
+    /// Runs the script on a line, returning what to search in its place, or
+    /// `None` if the line is dropped.
+    pub fn apply(&self, line_number: u64, line: &str) -> Result<Option<String>, GrrsError> {
+        let mut scope = Scope::new();
+        scope.push("line", line.to_string());
+        scope.push("line_number", line_number as i64);
+        let failed = |reason: String| {
+            GrrsError::InvalidArgument(format!(
+                "--filter-script failed on line {}: {}",
+                line_number, reason
+            ))
+        };
+        let result: Dynamic = (self.engine)
+            .eval_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|err| failed(err.to_string()))?;
+
+        match result.type_name() {
+            "bool" => Ok(result
+                .as_bool()
+                .unwrap_or_default()
+                .then(|| line.to_string())),
+            "string" => Ok(Some(result.into_string().unwrap_or_default())),
+            other => Err(failed(format!(
+                "expected true, false or a string, but got {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_and_rewrite_lines() -> Result<(), GrrsError> {
+        let script = Script::compile(r#"line.len() < 20 && line.contains("ERROR")"#)?;
+        assert_eq!(
+            script.apply(1, "ERROR: disk full")?,
+            Some("ERROR: disk full".into())
+        );
+        assert_eq!(script.apply(2, "INFO: disk fine")?, None);
+        assert_eq!(script.apply(3, &"ERROR ".repeat(10))?, None);
+
+        let script = Script::compile(r#"if line_number > 1 { line.to_upper() } else { false }"#)?;
+        assert_eq!(script.apply(1, "first")?, None);
+        assert_eq!(script.apply(2, "second")?, Some("SECOND".into()));
+
+        let err = Script::compile("line.len()")?.apply(4, "four").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--filter-script failed on line 4: expected true, false or a string, but got i64"
+        );
+        assert!(Script::compile("line.len( <").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,581 @@
+//! `grrs serve`, answering searches over a Unix socket, HTTP or gRPC so
+//! editors and other tools can search again without starting grrs and reading
+//! the files each time.
+//!
+//! Paths are found under the `--root` directory, the current one unless
+//! given, and those leading out of it, through `..` or a link, are refused.
+//!
+//! Over the socket, requests and responses are JSON-RPC 2.0, one message per
+//! line. The only method is `search`, taking `pattern`, `path` and optionally
+//! `options` as they're saved in a session, and returning the `events` found
+//! with the `matched_lines` and `total_lines` counts. Notifications, sent
+//! without an `id`, are carried out but not answered:
+//!
+//! ```text
+//! {"jsonrpc":"2.0","id":1,"method":"search","params":{"pattern":"TODO","path":"src/main.rs"}}
+//! {"jsonrpc":"2.0","id":1,"result":{"events":[...],"matched_lines":2,"total_lines":90}}
+//! ```
+//!
+//! Over HTTP, `POST /search` takes `pattern`, `paths` and `options`, and
+//! answers with the same result for each file, along with its `path`, or an
+//! `error` if it couldn't be searched. They're sent as `{"files":[...]}`, or
+//! with `?stream=true` as a line of JSON each as soon as it's searched.
+//!
+//! `GET /metrics` reports the searches answered, the bytes searched, how often
+//! files were searched from memory and how long searches took, for Prometheus.
+//!
+//! With the `grpc` feature, the service in proto/grrs.proto streams each line
+//! as it's found instead.
+
+use crate::http::{self, Chunked, Rejection};
+use crate::metrics::{Metrics, Transport};
+use anyhow::{anyhow, Context, Result};
+use grrs_core::matcher::Matcher;
+use grrs_core::sink::{CollectSink, Sink, SinkEvent};
+use grrs_core::{numbered_lines, open_input, GrrsError, SearchOptions, SearchSummary, Searcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::io::BufRead;
+use std::io::{BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+use structopt::StructOpt;
+
+/// Where `grrs serve` listens.
+#[derive(Debug, StructOpt)]
+pub struct Command {
+    /// The path to a Unix socket to create, answering JSON-RPC on it
+    #[cfg(unix)]
+    #[structopt(long, parse(from_os_str))]
+    socket: Option<PathBuf>,
+
+    /// The address to answer HTTP requests on, such as `127.0.0.1:7700`
+    #[structopt(long, value_name = "address")]
+    http: Option<String>,
+
+    /// The address to answer gRPC requests on
+    #[cfg(feature = "grpc")]
+    #[structopt(long, value_name = "address")]
+    grpc: Option<String>,
+
+    /// The directory whose files may be searched, which the paths sent are
+    /// relative to
+    #[structopt(long, parse(from_os_str), default_value = ".")]
+    root: PathBuf,
+}
+
+/// Accepts connections on one of the places `grrs serve` listens.
+type Server = Box<dyn FnOnce() -> Result<()> + Send>;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Absent for notifications, which are never answered, though an `id` of
+    /// `null` is
+    #[serde(default, deserialize_with = "present")]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Reads a field that's there, even as `null`, as Some.
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    pattern: String,
+    path: PathBuf,
+    #[serde(default)]
+    options: SearchOptions,
+}
+
+#[derive(Debug, Deserialize)]
+struct FilesParams {
+    pattern: String,
+    paths: Vec<PathBuf>,
+    #[serde(default)]
+    options: SearchOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchResult {
+    events: Vec<SinkEvent<'static>>,
+    matched_lines: u64,
+    total_lines: u64,
+}
+
+/// The numbered lines of a file.
+type Lines = Arc<Vec<(u64, String)>>;
+
+/// A file's modification time and length, which change when it's written.
+type Version = (SystemTime, u64);
+
+/// What `grrs serve` keeps between searches.
+#[derive(Debug)]
+pub struct State {
+    /// The directory searched files must be in, canonicalized
+    root: PathBuf,
+    cache: Cache,
+    pub metrics: Metrics,
+}
+
+impl State {
+    /// Serves the files under a directory, which must already be
+    /// canonicalized.
+    pub fn new(root: PathBuf) -> State {
+        State {
+            root,
+            cache: Cache::new(CACHED_FILES),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Finds a path sent by a client under the root, refusing any that leads
+    /// out of it. A path that doesn't exist is refused the same way, so
+    /// clients can't tell which paths outside the root do.
+    fn resolve(&self, path: &Path) -> Result<PathBuf, GrrsError> {
+        match self.root.join(path).canonicalize() {
+            Ok(resolved) if resolved.starts_with(&self.root) => Ok(resolved),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "`{}` was not found in the directory being served",
+                path.display()
+            ))),
+        }
+    }
+}
+
+/// How many files `grrs serve` keeps the lines of.
+const CACHED_FILES: usize = 256;
+
+/// The lines of the files searched most recently, read again once a file
+/// changes.
+#[derive(Debug)]
+struct Cache {
+    capacity: usize,
+    files: Mutex<CachedFiles>,
+}
+
+#[derive(Debug, Default)]
+struct CachedFiles {
+    /// Each file's version and lines, and when it was last searched
+    by_path: HashMap<PathBuf, (Version, Lines, u64)>,
+    searches: u64,
+}
+
+impl Cache {
+    /// Keeps up to `capacity` files, dropping the one searched longest ago
+    /// to make room for another.
+    fn new(capacity: usize) -> Cache {
+        Cache {
+            capacity,
+            files: Mutex::default(),
+        }
+    }
+
+    fn lines(&self, path: &Path, metrics: &Metrics) -> Result<Lines, GrrsError> {
+        // A file whose version can't be told isn't kept, and opening it says
+        // why it can't be read.
+        let version = std::fs::metadata(path)
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .ok();
+        {
+            let mut files = self.files.lock().unwrap();
+            files.searches += 1;
+            let searches = files.searches;
+            if let Some((cached, lines, searched)) = files.by_path.get_mut(path) {
+                if Some(*cached) == version {
+                    log::debug!("searching `{}` from memory", path.display());
+                    metrics.cached(true);
+                    *searched = searches;
+                    return Ok(lines.clone());
+                }
+            }
+        }
+        metrics.cached(false);
+
+        let lines = Arc::new(numbered_lines(open_input(path)?).collect::<Result<Vec<_>, _>>()?);
+        if let Some(version) = version {
+            let mut files = self.files.lock().unwrap();
+            let searches = files.searches;
+            if files.by_path.len() >= self.capacity && !files.by_path.contains_key(path) {
+                let oldest = (files.by_path.iter())
+                    .min_by_key(|(_, (_, _, searched))| *searched)
+                    .map(|(path, _)| path.clone());
+                if let Some(oldest) = oldest {
+                    files.by_path.remove(&oldest);
+                }
+            }
+            (files.by_path).insert(path.to_path_buf(), (version, lines.clone(), searches));
+        }
+
+        Ok(lines)
+    }
+}
+
+/// Runs `grrs serve` until it's killed.
+pub fn run(command: &Command) -> Result<()> {
+    let root = (command.root.canonicalize())
+        .with_context(|| format!("could not serve `{}`", command.root.display()))?;
+    let state = Arc::new(State::new(root));
+    let mut servers: Vec<Server> = Vec::new();
+    #[cfg(unix)]
+    if let Some(socket) = &command.socket {
+        let listener = bind(socket)?;
+        eprintln!("grrs: listening on {}", socket.display());
+        let state = state.clone();
+        servers.push(Box::new(move || {
+            accept(listener.incoming(), &state, answer)
+        }));
+    }
+    if let Some(address) = &command.http {
+        let listener = listen(address)?;
+        eprintln!("grrs: listening on http://{}", listener.local_addr()?);
+        let state = state.clone();
+        servers.push(Box::new(move || {
+            accept(listener.incoming(), &state, answer_http)
+        }));
+    }
+    #[cfg(feature = "grpc")]
+    if let Some(address) = &command.grpc {
+        let listener = listen(address)?;
+        eprintln!("grrs: listening for gRPC on {}", listener.local_addr()?);
+        let state = state.clone();
+        servers.push(Box::new(move || crate::grpc::serve(listener, state)));
+    }
+
+    let last = (servers.pop()).ok_or_else(|| anyhow!("give an address or socket to listen on"))?;
+    // Failing to accept connections on any of them ends them all.
+    for server in servers {
+        std::thread::spawn(move || {
+            if let Err(err) = server() {
+                eprintln!("Error: {:#}", err);
+                std::process::exit(1);
+            }
+        });
+    }
+    last()
+}
+
+fn listen(address: &str) -> Result<TcpListener> {
+    TcpListener::bind(address).with_context(|| format!("could not listen on `{}`", address))
+}
+
+/// Creates a socket. One left behind by a daemon that's gone would stop
+/// this one starting, but one that's still answering is left alone.
+#[cfg(unix)]
+fn bind(socket: &Path) -> Result<UnixListener> {
+    if socket.exists() && UnixStream::connect(socket).is_err() {
+        std::fs::remove_file(socket)
+            .with_context(|| format!("could not remove stale socket `{}`", socket.display()))?;
+    }
+
+    UnixListener::bind(socket)
+        .with_context(|| format!("could not listen on `{}`", socket.display()))
+}
+
+/// Answers each connection on its own thread.
+fn accept<S: Send + 'static>(
+    connections: impl Iterator<Item = std::io::Result<S>>,
+    state: &Arc<State>,
+    answer: fn(&State, S) -> Result<()>,
+) -> Result<()> {
+    for stream in connections {
+        let stream = stream.context("could not accept a connection")?;
+        let state = state.clone();
+        std::thread::spawn(move || {
+            if let Err(err) = answer(&state, stream) {
+                log::warn!("{:#}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Answers each JSON-RPC request sent over a connection until it's closed.
+#[cfg(unix)]
+fn answer(state: &State, stream: UnixStream) -> Result<()> {
+    let requests = BufReader::new(stream.try_clone()?);
+    let mut out = stream;
+    for message in requests.lines() {
+        let message = message?;
+        if message.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = respond(state, &message) {
+            serde_json::to_writer(&mut out, &response)?;
+            out.write_all(b"\n")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Answers the HTTP request sent over a connection.
+fn answer_http(state: &State, stream: TcpStream) -> Result<()> {
+    let mut request = BufReader::new(stream.try_clone()?);
+    let mut out = stream;
+    let result = http::read_request(&mut request).and_then(|request| {
+        match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/search") => search_files(state, &request, &mut out),
+            (_, "/search") => Err(Rejection::new(405, "/search only takes POST")),
+            ("GET", "/metrics") => {
+                let body = state.metrics.render();
+                let content_type = "text/plain; version=0.0.4";
+                if let Err(err) = http::respond(&mut out, 200, content_type, body.as_bytes()) {
+                    log::warn!("could not answer for metrics: {}", err);
+                }
+                Ok(())
+            }
+            (_, "/metrics") => Err(Rejection::new(405, "/metrics only takes GET")),
+            (_, path) => Err(Rejection::new(404, format!("no such endpoint `{}`", path))),
+        }
+    });
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(rejection) => {
+            let body = json!({ "error": rejection.message }).to_string();
+            Ok(http::respond(
+                &mut out,
+                rejection.status,
+                "application/json",
+                body.as_bytes(),
+            )?)
+        }
+    }
+}
+
+/// Searches the files a `POST /search` names, writing the response unless
+/// the request is rejected.
+fn search_files(
+    state: &State,
+    request: &http::Request,
+    out: &mut TcpStream,
+) -> Result<(), Rejection> {
+    let start = Instant::now();
+    let params: FilesParams = serde_json::from_slice(&request.body)
+        .map_err(|err| Rejection::new(400, format!("invalid request: {}", err)))?;
+    let searcher = Searcher::new(params.options);
+    let matcher =
+        (searcher.matcher(&params.pattern)).map_err(|err| Rejection::new(400, err.to_string()))?;
+    let results =
+        params.paths.iter().map(
+            |path| match search_file(state, &searcher, &*matcher, path) {
+                Ok(result) => json!({
+                    "path": path,
+                    "events": result.events,
+                    "matched_lines": result.matched_lines,
+                    "total_lines": result.total_lines,
+                }),
+                Err(err) => json!({ "path": path, "error": err.to_string() }),
+            },
+        );
+
+    // Once the response has started, failing to write it is only logged.
+    let written = match request.flag("stream") {
+        false => {
+            let body = json!({ "files": results.collect::<Vec<_>>() }).to_string();
+            http::respond(out, 200, "application/json", body.as_bytes())
+        }
+        true => Chunked::start(&mut *out, "application/x-ndjson").and_then(|mut chunked| {
+            for result in results {
+                chunked.write_all(format!("{}\n", result).as_bytes())?;
+            }
+            chunked.finish()
+        }),
+    };
+    if let Err(err) = written {
+        log::warn!("could not answer a search: {}", err);
+    }
+    state.metrics.searched(Transport::Http, start.elapsed());
+
+    Ok(())
+}
+
+/// Returns the response to a single JSON-RPC message, unless it's a
+/// notification.
+fn respond(state: &State, message: &str) -> Option<Value> {
+    let request: Request = match serde_json::from_str(message) {
+        Ok(request) => request,
+        Err(err) => {
+            return Some(failure(
+                Value::Null,
+                -32700,
+                format!("parse error: {}", err),
+            ))
+        }
+    };
+    let result = match request.method.as_str() {
+        "search" => match serde_json::from_value(request.params) {
+            Ok(params) => {
+                let start = Instant::now();
+                let result = search(state, params).map_err(|err| (-32000, err.to_string()));
+                state.metrics.searched(Transport::Socket, start.elapsed());
+                result
+            }
+            Err(err) => Err((-32602, format!("invalid params: {}", err))),
+        },
+        method => Err((-32601, format!("unknown method `{}`", method))),
+    };
+
+    let id = request.id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => failure(id, code, message),
+    })
+}
+
+fn failure(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn search(state: &State, params: SearchParams) -> Result<SearchResult, GrrsError> {
+    let searcher = Searcher::new(params.options);
+    let matcher = searcher.matcher(&params.pattern)?;
+    search_file(state, &searcher, &*matcher, &params.path)
+}
+
+fn search_file(
+    state: &State,
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    path: &Path,
+) -> Result<SearchResult, GrrsError> {
+    let mut sink = CollectSink::default();
+    let summary = search_cached(state, searcher, matcher, path, &mut sink)?;
+
+    Ok(SearchResult {
+        events: sink.events,
+        matched_lines: summary.matched_lines,
+        total_lines: summary.total_lines,
+    })
+}
+
+/// Searches a file under the root, its lines as they're cached, counting the
+/// bytes read.
+pub fn search_cached(
+    state: &State,
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    path: &Path,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary, GrrsError> {
+    let path = state.resolve(path)?;
+    let lines = state.cache.lines(&path, &state.metrics)?;
+    let mut scanned = 0;
+    let numbered = lines.iter().map(|(num, line)| {
+        scanned += line.len() as u64 + 1;
+        Ok((*num, line))
+    });
+    let summary = searcher.search_numbered(matcher, numbered, sink);
+    state.metrics.scanned(scanned);
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn respond_to_requests() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("served");
+        std::fs::create_dir(&root)?;
+        let path = root.join("notes.txt");
+        std::fs::write(&path, "A test\nActual content\n")?;
+        std::fs::write(dir.path().join("secret.txt"), "A test\n")?;
+        let state = State::new(root.canonicalize()?);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 7,
+            "method": "search",
+            "params": { "pattern": "TEST", "path": path, "options": { "ignore_case": true } },
+        })
+        .to_string();
+
+        let response = respond(&state, &request).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["matched_lines"], 1);
+        assert_eq!(response["result"]["events"][0]["line"], "A test");
+
+        // A file that's changed is read again.
+        std::fs::write(&path, "A test\nAnother test\nActual content\n")?;
+        assert_eq!(
+            respond(&state, &request).unwrap()["result"]["matched_lines"],
+            2
+        );
+
+        let response = respond(&state, r#"{"jsonrpc":"2.0","id":1,"method":"index"}"#).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+        let response = respond(&state, r#"{"jsonrpc":"2.0","id":2,"method":"search"}"#).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+        assert_eq!(respond(&state, "{").unwrap()["error"]["code"], -32700);
+
+        // Notifications aren't answered, even when they fail, but a null id is.
+        let notification =
+            r#"{"jsonrpc":"2.0","method":"search","params":{"pattern":"a","path":"notes.txt"}}"#;
+        assert!(respond(&state, notification).is_none());
+        assert!(respond(&state, r#"{"jsonrpc":"2.0","method":"index"}"#).is_none());
+        let response = respond(&state, r#"{"jsonrpc":"2.0","id":null,"method":"index"}"#).unwrap();
+        assert_eq!(
+            (&response["id"], &response["error"]["code"]),
+            (&Value::Null, &json!(-32601))
+        );
+
+        // Only files under the root are searched, wherever a path leads.
+        let outside = |path: &str| {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": 3,
+                "method": "search",
+                "params": { "pattern": "test", "path": path },
+            });
+            respond(&state, &request.to_string()).unwrap()["error"]["message"].clone()
+        };
+        let refused = "`../secret.txt` was not found in the directory being served";
+        assert_eq!(outside("../secret.txt"), refused);
+        assert_eq!(outside("notes.txt"), Value::Null);
+        // Whether a path outside the root exists isn't given away.
+        let refused = "`../missing.txt` was not found in the directory being served";
+        assert_eq!(outside("../missing.txt"), refused);
+        let refused = "`missing.txt` was not found in the directory being served";
+        assert_eq!(outside("missing.txt"), refused);
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), root.join("link.txt"))?;
+            let refused = "`link.txt` was not found in the directory being served";
+            assert_eq!(outside("link.txt"), refused);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_the_files_searched_most_recently() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let [a, b, c] = ["a.txt", "b.txt", "c.txt"].map(|name| dir.path().join(name));
+        for path in [&a, &b, &c] {
+            std::fs::write(path, "A test\n")?;
+        }
+        let cache = Cache::new(2);
+        let metrics = Metrics::default();
+        for path in [&a, &b, &a, &c] {
+            cache.lines(path, &metrics)?;
+        }
+        let cached = cache.files.lock().unwrap();
+        let mut kept: Vec<_> = cached.by_path.keys().collect();
+        kept.sort();
+        assert_eq!(kept, [&a, &c]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+//! Saved searches, written by `--save-session` so their results can be shown,
+//! narrowed or compared later without searching the files again.
+
+use anyhow::{Context, Result};
+use grrs_core::format::{Formatter, JsonFormatter, LineFormatter};
+use grrs_core::sink::{Sink, SinkEvent, WriterSink};
+use grrs_core::{GrrsError, SearchOptions, Searcher};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use structopt::StructOpt;
+
+/// What `grrs session` does with a saved session.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Display the results of a saved search again
+    Show {
+        /// The path to the session file to read
+        #[structopt(parse(from_os_str))]
+        session: PathBuf,
+        /// Write each result as a JSON object on its own line
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Display the saved matches that also contain another pattern
+    Filter {
+        /// The pattern to look for among the saved matches
+        pattern: String,
+        /// The path to the session file to read
+        #[structopt(parse(from_os_str))]
+        session: PathBuf,
+        /// Match the pattern case-insensitively
+        #[structopt(short, long)]
+        ignore_case: bool,
+        /// Treat the pattern as a regular expression
+        #[structopt(short = "E", long)]
+        regex: bool,
+    },
+    /// Display the matches found by only one of two saved searches
+    Diff {
+        /// The path to the earlier session file
+        #[structopt(parse(from_os_str))]
+        old: PathBuf,
+        /// The path to the later session file
+        #[structopt(parse(from_os_str))]
+        new: PathBuf,
+    },
+}
+
+/// A search and everything it reported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub pattern: String,
+    pub path: PathBuf,
+    pub options: SearchOptions,
+    pub events: Vec<SinkEvent<'static>>,
+}
+
+impl Session {
+    /// Starts a session for a search that hasn't reported anything yet.
+    pub fn new(pattern: &str, path: &Path, options: SearchOptions) -> Session {
+        Session {
+            pattern: pattern.to_string(),
+            path: path.to_path_buf(),
+            options,
+            events: Vec::new(),
+        }
+    }
+
+    /// Writes the session as JSON.
+    pub fn save(&self, path: &Path) -> Result<(), GrrsError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut file, self).map_err(std::io::Error::from)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the lines that matched, with their line numbers.
+    fn matches(&self) -> impl Iterator<Item = (u64, &str)> {
+        self.events.iter().filter_map(|event| match event {
+            SinkEvent::Match { line_number, line } => Some((*line_number, line.as_ref())),
+            _ => None,
+        })
+    }
+}
+
+/// A search of several files by `grrs files`, with a session for each file
+/// that matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeSession {
+    pub pattern: String,
+    pub paths: Vec<PathBuf>,
+    pub files: Vec<Session>,
+}
+
+/// What a session file holds: a search of one file, or of several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Saved {
+    File(Session),
+    Tree(TreeSession),
+}
+
+impl Saved {
+    /// Reads a session file written by Session::save() or Saved::save().
+    pub fn load(path: &Path) -> Result<Saved> {
+        let context = || format!("could not read session `{}`", path.display());
+        let file = File::open(path).with_context(context)?;
+        serde_json::from_reader(BufReader::new(file)).with_context(context)
+    }
+
+    /// Writes the session file as JSON.
+    pub fn save(&self, path: &Path) -> Result<(), GrrsError> {
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut file, self).map_err(std::io::Error::from)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    fn pattern(&self) -> &str {
+        match self {
+            Saved::File(session) => &session.pattern,
+            Saved::Tree(tree) => &tree.pattern,
+        }
+    }
+
+    /// Returns the session of each file searched.
+    fn files(&self) -> &[Session] {
+        match self {
+            Saved::File(session) => std::slice::from_ref(session),
+            Saved::Tree(tree) => &tree.files,
+        }
+    }
+}
+
+/// Passes events on to another sink, and saves them with the search they came
+/// from once it finishes.
+pub struct SaveSink<S: Sink> {
+    sink: S,
+    path: PathBuf,
+    session: Session,
+}
+
+impl<S: Sink> SaveSink<S> {
+    pub fn new(sink: S, path: PathBuf, session: Session) -> SaveSink<S> {
+        SaveSink {
+            sink,
+            path,
+            session,
+        }
+    }
+}
+
+impl<S: Sink> Sink for SaveSink<S> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        self.session.events.push(event.clone().into_owned());
+        self.sink.event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.sink.finish()?;
+        self.session.save(&self.path)
+    }
+}
+
+/// Runs a `grrs session` command. The results of a session of several files
+/// are written under the path of each.
+pub fn run(command: &Command) -> Result<()> {
+    match command {
+        Command::Show { session, json } => {
+            let saved = Saved::load(session)?;
+            let stdout = std::io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            for file in saved.files() {
+                match (json, &saved) {
+                    // Each event says which file it's from instead.
+                    (true, Saved::Tree(_)) => {
+                        for event in &file.events {
+                            let mut value = serde_json::to_value(event)?;
+                            value["path"] = json!(file.path);
+                            writeln!(out, "{}", value)?;
+                        }
+                        continue;
+                    }
+                    (false, Saved::Tree(_)) => writeln!(out, "{}", file.path.display())?,
+                    (_, Saved::File(_)) => {}
+                }
+                let formatter: Box<dyn Formatter> = match json {
+                    false => Box::new(LineFormatter),
+                    true => Box::new(JsonFormatter),
+                };
+                let mut sink = WriterSink::with_formatter(&mut out, formatter);
+                for event in file.events.iter().cloned() {
+                    sink.event(event)?;
+                }
+                sink.finish()?;
+            }
+            out.flush()?;
+        }
+        Command::Filter {
+            pattern,
+            session,
+            ignore_case,
+            regex,
+        } => {
+            let searcher = Searcher::builder()
+                .case_insensitive(*ignore_case)
+                .regex(*regex)
+                .build();
+            let matcher = searcher.matcher(pattern)?;
+            let saved = Saved::load(session)?;
+            let stdout = std::io::stdout();
+            let mut out = BufWriter::new(stdout.lock());
+            for file in saved.files() {
+                let mut matches = file.matches().filter(|(_, line)| matcher.is_match(line));
+                let first = match matches.next() {
+                    Some(first) => first,
+                    None => continue,
+                };
+                if let Saved::Tree(_) = saved {
+                    writeln!(out, "{}", file.path.display())?;
+                }
+                let mut sink = WriterSink::new(&mut out);
+                for (line_number, line) in std::iter::once(first).chain(matches) {
+                    let line = line.into();
+                    sink.event(SinkEvent::Match { line_number, line })?;
+                }
+                sink.finish()?;
+            }
+            out.flush()?;
+        }
+        Command::Diff { old, new } => {
+            write_diff(&Saved::load(old)?, &Saved::load(new)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the matches found by only one of two sessions to stdout, returning
+/// whether the later one found any the earlier didn't. Searches of one file
+/// are compared whatever the files are called, and otherwise each file is
+/// compared with the one at the same path, with its matches written under
+/// the path.
+pub fn write_diff(old: &Saved, new: &Saved) -> Result<bool> {
+    if old.pattern() != new.pattern() {
+        log::warn!(
+            "comparing searches for different patterns, `{}` and `{}`",
+            old.pattern(),
+            new.pattern()
+        );
+    }
+    let stdout = std::io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let lines = diff_files(old, new);
+    for (sign, path, line_number, line) in &lines {
+        match path {
+            Some(path) => writeln!(
+                out,
+                "{} {}: LINE# {}: {}",
+                sign,
+                path.display(),
+                line_number,
+                line
+            )?,
+            None => writeln!(out, "{} LINE# {}: {}", sign, line_number, line)?,
+        }
+    }
+    out.flush()?;
+
+    Ok(lines.iter().any(|(sign, ..)| *sign == '+'))
+}
+
+/// Compares the matches of two saved searches as grrs::session::write_diff()
+/// does, returning the path of each line if it's written.
+fn diff_files(old: &Saved, new: &Saved) -> Vec<(char, Option<PathBuf>, u64, String)> {
+    if let (Saved::File(old), Saved::File(new)) = (old, new) {
+        return (diff(old, new).into_iter())
+            .map(|(sign, line_number, line)| (sign, None, line_number, line.to_string()))
+            .collect();
+    }
+
+    // The files matched in either, in the order of the earlier session.
+    let mut paths: Vec<_> = (old.files().iter().chain(new.files()))
+        .map(|session| normalized(&session.path))
+        .collect();
+    let mut seen = HashSet::new();
+    paths.retain(|path| seen.insert(path.clone()));
+    let find = |saved: &Saved, path: &Path| {
+        (saved.files().iter())
+            .find(|session| normalized(&session.path) == path)
+            .cloned()
+            .unwrap_or_else(|| Session::new(saved.pattern(), path, SearchOptions::default()))
+    };
+    let mut lines = Vec::new();
+    for path in paths {
+        let (old, new) = (find(old, &path), find(new, &path));
+        lines.extend(
+            (diff(&old, &new).into_iter()).map(|(sign, line_number, line)| {
+                (sign, Some(path.clone()), line_number, line.to_string())
+            }),
+        );
+    }
+
+    lines
+}
+
+/// Returns a path without any `.` components, so `./src/main.rs` is paired
+/// with `src/main.rs`.
+fn normalized(path: &Path) -> PathBuf {
+    (path.components())
+        .filter(|component| *component != Component::CurDir)
+        .collect()
+}
+
+/// Compares the matches of two sessions by their text, as lines may have moved
+/// between them: first those only in `old`, marked `-`, then those only in
+/// `new`, marked `+`.
+fn diff<'s>(old: &'s Session, new: &'s Session) -> Vec<(char, u64, &'s str)> {
+    let mut lines = only_in(old, new, '-');
+    lines.extend(only_in(new, old, '+'));
+
+    lines
+}
+
+/// Returns the matches of a session that another doesn't have, counting a
+/// line repeated more often in the first as missing from the second.
+fn only_in<'s>(session: &'s Session, other: &Session, sign: char) -> Vec<(char, u64, &'s str)> {
+    let mut unpaired: HashMap<&str, usize> = HashMap::new();
+    for (_, line) in other.matches() {
+        *unpaired.entry(line).or_default() += 1;
+    }
+
+    (session.matches())
+        .filter(|(_, line)| match unpaired.get_mut(line) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .map(|(line_number, line)| (sign, line_number, line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(lines: &[(u64, &str)]) -> Session {
+        let mut session = Session::new("test", Path::new("notes.txt"), SearchOptions::default());
+        for (line_number, line) in lines {
+            session.events.push(SinkEvent::Match {
+                line_number: *line_number,
+                line: line.to_string().into(),
+            });
+        }
+
+        session
+    }
+
+    #[test]
+    fn save_and_load() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("results.grrs");
+        let saved = session(&[(1, "A test")]);
+        saved.save(&path)?;
+        assert_eq!(Saved::load(&path)?, Saved::File(saved.clone()));
+
+        let tree = Saved::Tree(TreeSession {
+            pattern: "test".into(),
+            paths: vec![".".into()],
+            files: vec![saved],
+        });
+        tree.save(&path)?;
+        assert_eq!(Saved::load(&path)?, tree);
+
+        Ok(())
+    }
+
+    #[test]
+    fn diff_sessions() {
+        let old = session(&[(1, "a test"), (2, "a test"), (5, "old test")]);
+        let new = session(&[(3, "a test"), (4, "new test")]);
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                ('-', 2, "a test"),
+                ('-', 5, "old test"),
+                ('+', 4, "new test")
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_trees_by_path() {
+        let tree = |files: &[(&str, &[(u64, &str)])]| {
+            let files = (files.iter())
+                .map(|(path, lines)| Session {
+                    path: path.into(),
+                    ..session(lines)
+                })
+                .collect();
+            Saved::Tree(TreeSession {
+                pattern: "test".into(),
+                paths: vec![".".into()],
+                files,
+            })
+        };
+        let old = tree(&[("./a.txt", &[(1, "a test")]), ("b.txt", &[(1, "b test")])]);
+        let new = tree(&[("a.txt", &[(2, "a test")]), ("c.txt", &[(1, "c test")])]);
+        assert_eq!(
+            diff_files(&old, &new),
+            vec![
+                ('-', Some("b.txt".into()), 1, "b test".to_string()),
+                ('+', Some("c.txt".into()), 1, "c test".to_string())
+            ]
+        );
+        // A search of one file is compared with the same file in a tree.
+        let one = Saved::File(Session {
+            path: "./c.txt".into(),
+            ..session(&[(1, "c test")])
+        });
+        assert!(diff_files(&new, &one).iter().all(|(sign, ..)| *sign == '-'));
+    }
+}
@@ -0,0 +1,237 @@
+//! `--rate-limit`, for keeping a storm of matches from a chatty source from
+//! flooding the terminal.
+
+use anyhow::{anyhow, Result};
+use grrs_core::sink::{Sink, SinkEvent};
+use grrs_core::GrrsError;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How many matching lines may be written in each second, minute or hour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    pub lines: u64,
+    pub per: Duration,
+}
+
+impl FromStr for Rate {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Rate> {
+        let (lines, per) = input.split_once('/').unwrap_or((input, "s"));
+        let per = match per {
+            "s" => Duration::from_secs(1),
+            "m" => Duration::from_secs(60),
+            "h" => Duration::from_secs(60 * 60),
+            _ => {
+                return Err(anyhow!(
+                    "unknown period `{}`, expected `s`, `m` or `h`",
+                    per
+                ))
+            }
+        };
+        match lines.parse() {
+            Ok(lines) if lines > 0 => Ok(Rate { lines, per }),
+            _ => Err(anyhow!(
+                "invalid rate `{}`, expected a number of lines such as `100/s`",
+                input
+            )),
+        }
+    }
+}
+
+/// Passes events on to another sink until the rate is reached, then drops
+/// matches, and the lines of context after them, until the period is up. The
+/// lines of context before the first match written again are kept for it,
+/// after a break. How many were dropped is noted on stderr once matches are
+/// written again, and when the search finishes.
+pub struct ThrottleSink<S: Sink> {
+    sink: S,
+    rate: Rate,
+    /// The most lines of context before a match
+    before: usize,
+    /// When the current period began
+    started: Instant,
+    /// How many matches have been written since
+    written: u64,
+    /// How many matches have been dropped since they were last noted
+    dropped: u64,
+    /// Whether the last match was dropped, and its context with it
+    dropping: bool,
+    /// The lines of context read since then that could come before the next
+    /// match
+    held: VecDeque<SinkEvent<'static>>,
+}
+
+impl<S: Sink> ThrottleSink<S> {
+    /// Throttles matches, keeping up to `before` lines of context for the
+    /// first written after some are dropped.
+    pub fn new(sink: S, rate: Rate, before: usize) -> ThrottleSink<S> {
+        ThrottleSink {
+            sink,
+            rate,
+            before,
+            started: Instant::now(),
+            written: 0,
+            dropped: 0,
+            dropping: false,
+            held: VecDeque::new(),
+        }
+    }
+
+    fn note_dropped(&mut self) {
+        if self.dropped > 0 {
+            eprintln!("grrs: {}", dropped_note(self.dropped));
+            self.dropped = 0;
+        }
+    }
+}
+
+/// Says how many matching lines were dropped.
+fn dropped_note(dropped: u64) -> String {
+    format!("dropped {} matching lines over the rate limit", dropped)
+}
+
+impl<S: Sink> Sink for ThrottleSink<S> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { .. } = event {
+            if self.started.elapsed() >= self.rate.per {
+                self.started = Instant::now();
+                self.written = 0;
+            }
+            let was_dropping = self.dropping;
+            self.dropping = self.written >= self.rate.lines;
+            match self.dropping {
+                true => {
+                    self.dropped += 1;
+                    self.held.clear();
+                }
+                false => {
+                    self.note_dropped();
+                    self.written += 1;
+                    if was_dropping {
+                        self.sink.event(SinkEvent::Break)?;
+                        for held in std::mem::take(&mut self.held) {
+                            self.sink.event(held)?;
+                        }
+                    }
+                }
+            }
+        }
+        match (self.dropping, event) {
+            (true, SinkEvent::Context { line_number, line }) if self.before > 0 => {
+                if self.held.len() == self.before {
+                    self.held.pop_front();
+                }
+                let line = line.into_owned().into();
+                self.held
+                    .push_back(SinkEvent::Context { line_number, line });
+                Ok(())
+            }
+            // Context before a break is only ever after a dropped match.
+            (true, SinkEvent::Break) => {
+                self.held.clear();
+                Ok(())
+            }
+            (true, _) => Ok(()),
+            (false, event) => self.sink.event(event),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.sink.finish()?;
+        self.note_dropped();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grrs_core::sink::CollectSink;
+
+    #[test]
+    fn drop_matches_over_the_rate() -> Result<(), GrrsError> {
+        let rate = Rate::from_str("2/h").unwrap();
+        assert_eq!(rate.per, Duration::from_secs(3600));
+        assert!(Rate::from_str("0/s").is_err());
+        assert!(Rate::from_str("5/d").is_err());
+
+        let mut collected = CollectSink::default();
+        let mut sink = ThrottleSink::new(&mut collected, rate, 1);
+        for line_number in 1..=4 {
+            let line = "match".into();
+            sink.event(SinkEvent::Match { line_number, line })?;
+            let line = "after".into();
+            sink.event(SinkEvent::Context { line_number, line })?;
+        }
+        assert_eq!(sink.dropped, 2);
+        assert_eq!(
+            dropped_note(sink.dropped),
+            "dropped 2 matching lines over the rate limit"
+        );
+        sink.finish()?;
+        assert_eq!(sink.dropped, 0);
+        let written: Vec<_> = (collected.events.iter())
+            .map(|event| match event {
+                SinkEvent::Match { line_number, .. } | SinkEvent::Context { line_number, .. } => {
+                    *line_number
+                }
+                _ => 0,
+            })
+            .collect();
+        assert_eq!(written, [1, 1, 2, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_the_context_before_a_match_written_again() -> Result<(), GrrsError> {
+        let rate = Rate {
+            lines: 1,
+            per: Duration::from_millis(50),
+        };
+        let mut collected = CollectSink::default();
+        let mut sink = ThrottleSink::new(&mut collected, rate, 1);
+        let events = [
+            SinkEvent::Match {
+                line_number: 1,
+                line: "match".into(),
+            },
+            SinkEvent::Match {
+                line_number: 2,
+                line: "dropped".into(),
+            },
+            SinkEvent::Context {
+                line_number: 3,
+                line: "after".into(),
+            },
+            SinkEvent::Break,
+            SinkEvent::Context {
+                line_number: 8,
+                line: "before".into(),
+            },
+        ];
+        for event in events {
+            sink.event(event)?;
+        }
+        std::thread::sleep(rate.per);
+        sink.event(SinkEvent::Match {
+            line_number: 9,
+            line: "again".into(),
+        })?;
+        sink.finish()?;
+        let written: Vec<_> = (collected.events.iter())
+            .map(|event| match event {
+                SinkEvent::Match { line_number, .. } => format!(":{}", line_number),
+                SinkEvent::Context { line_number, .. } => format!("-{}", line_number),
+                _ => "--".to_string(),
+            })
+            .collect();
+        assert_eq!(written, [":1", "--", "-8", ":9"]);
+
+        Ok(())
+    }
+}
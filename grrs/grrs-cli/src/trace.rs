@@ -0,0 +1,56 @@
+//! Timings of each stage of a search, written by `--trace` as a Chrome trace
+//! that chrome://tracing, Perfetto or speedscope can show as a flame graph.
+//! Requires the `trace` feature.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::File;
+use std::path::Path;
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::Registry;
+
+/// Starts recording spans to a trace file, which is complete once the returned
+/// guard is dropped.
+pub fn start(path: &Path) -> Result<FlushGuard> {
+    let file = File::create(path)
+        .with_context(|| format!("could not create file '{}'", path.display()))?;
+    let (layer, guard) = ChromeLayerBuilder::new()
+        .writer(file)
+        .include_args(true)
+        .build();
+    tracing::subscriber::set_global_default(Registry::default().with(layer))
+        .map_err(|err| anyhow!("could not start tracing: {}", err))?;
+
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn write_spans() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("trace.json");
+        let guard = start(&path)?;
+        tracing::info_span!("open", path = %"notes.txt").in_scope(|| {});
+        // Each directory listed while walking a tree, on any thread.
+        let tree = dir.path().join("tree");
+        fs::create_dir(&tree)?;
+        let search = |_: &Path, _| Ok(grrs_core::SearchSummary::default());
+        grrs_core::search_tree([&tree], 2, &Default::default(), |_| true, search);
+        drop(guard);
+        let written = fs::read_to_string(&path)?;
+        assert!(written.contains(r#""name":"open""#));
+        assert!(written.contains(r#""path":"notes.txt""#));
+        assert!(written.contains(r#""name":"walk""#));
+        let listed = format!(
+            r#""path":{}"#,
+            serde_json::to_string(&tree.display().to_string())?
+        );
+        assert!(written.contains(&listed));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,282 @@
+//! A full-screen browser for the matches in a file, enabled by the `tui`
+//! feature. The file is searched again as the pattern is typed, and Enter
+//! opens the selected match in `$EDITOR`.
+
+use crate::editor;
+use anyhow::Result;
+use grrs_core::Searcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::ops::Range;
+use std::path::PathBuf;
+
+/// How matched text is drawn.
+const MATCH: Style = Style::new().fg(Color::Red).add_modifier(Modifier::BOLD);
+
+/// A matching line, by its index among the lines of the file.
+#[derive(Debug, PartialEq)]
+struct Found {
+    index: usize,
+    spans: Vec<Range<usize>>,
+}
+
+/// What a key press asks the browser to do next.
+#[derive(Debug, PartialEq)]
+enum Action {
+    Continue,
+    Edit(u64),
+    Quit,
+}
+
+/// The state of the browser, updated as keys are pressed.
+pub struct Browser {
+    path: PathBuf,
+    lines: Vec<(u64, String)>,
+    searcher: Searcher,
+    pattern: String,
+    found: Vec<Found>,
+    error: Option<String>,
+    list: ListState,
+}
+
+impl Browser {
+    /// Creates a browser over the numbered lines read from a file, which starts
+    /// out with an empty pattern.
+    pub fn new(path: PathBuf, lines: Vec<(u64, String)>, searcher: Searcher) -> Browser {
+        Browser {
+            path,
+            lines,
+            searcher,
+            pattern: String::new(),
+            found: Vec::new(),
+            error: None,
+            list: ListState::default(),
+        }
+    }
+
+    /// Runs the browser until it's quit, restoring the terminal afterwards.
+    pub fn run(mut self) -> Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let result = self.event_loop(&mut terminal);
+        ratatui::restore();
+
+        result
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.render(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match self.key(key) {
+                Action::Continue => {}
+                Action::Quit => return Ok(()),
+                Action::Edit(line_number) => {
+                    // The editor gets the terminal to itself until it exits.
+                    ratatui::restore();
+                    let edited = editor::open(&self.path, line_number);
+                    *terminal = ratatui::try_init()?;
+                    edited?;
+                }
+            }
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Action::Quit
+            }
+            KeyCode::Enter => {
+                if let Some(found) = self.selected() {
+                    return Action::Edit(self.lines[found.index].0);
+                }
+            }
+            KeyCode::Char(c) => {
+                self.pattern.push(c);
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.pattern.pop();
+                self.refresh();
+            }
+            KeyCode::Down => {
+                let last = self.found.len().saturating_sub(1);
+                let next = self
+                    .list
+                    .selected()
+                    .map_or(0, |index| (index + 1).min(last));
+                self.list
+                    .select(Some(next).filter(|_| !self.found.is_empty()));
+            }
+            KeyCode::Up => {
+                let previous = self.list.selected().map(|index| index.saturating_sub(1));
+                self.list.select(previous);
+            }
+            _ => {}
+        }
+
+        Action::Continue
+    }
+
+    /// Searches the lines again for the current pattern, selecting the first
+    /// match. A pattern that doesn't compile finds nothing.
+    fn refresh(&mut self) {
+        self.found.clear();
+        self.error = None;
+        if !self.pattern.is_empty() {
+            match self.searcher.matcher(&self.pattern) {
+                Ok(matcher) => {
+                    self.found = (self.lines.iter().enumerate())
+                        .filter_map(|(index, (_, line))| {
+                            let spans: Vec<_> = matcher.find_iter(line).collect();
+                            (!spans.is_empty()).then_some(Found { index, spans })
+                        })
+                        .collect();
+                }
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+        self.list.select(Some(0).filter(|_| !self.found.is_empty()));
+    }
+
+    fn selected(&self) -> Option<&Found> {
+        self.list.selected().and_then(|index| self.found.get(index))
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [input, body, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [results, preview] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(body);
+
+        let pattern =
+            Paragraph::new(self.pattern.as_str()).block(Block::bordered().title("Pattern"));
+        frame.render_widget(pattern, input);
+
+        let lines = &self.lines;
+        let items: Vec<_> = (self.found.iter())
+            .map(|found| {
+                let (line_number, line) = &lines[found.index];
+                highlighted(format!("{}: ", line_number), line, &found.spans)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title("Matches"))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, results, &mut self.list);
+
+        // The selected line is centred among its neighbours, where it can be.
+        let mut context = Vec::new();
+        if let Some(found) = self.selected() {
+            let height = usize::from(preview.height.saturating_sub(2));
+            let start = found.index.saturating_sub(height / 2);
+            for (index, (line_number, line)) in
+                self.lines.iter().enumerate().skip(start).take(height)
+            {
+                context.push(match index == found.index {
+                    true => highlighted(format!("{}: ", line_number), line, &found.spans),
+                    false => Line::raw(format!("{}- {}", line_number, line)),
+                });
+            }
+        }
+        let title = format!("{}", self.path.display());
+        frame.render_widget(
+            Paragraph::new(context).block(Block::bordered().title(title)),
+            preview,
+        );
+
+        let status_line = match &self.error {
+            Some(err) => Line::styled(err.lines().next().unwrap_or_default().to_string(), MATCH),
+            None => Line::raw(format!(
+                "Matching lines: {} | Up/Down: select | Enter: open in $EDITOR | Esc: quit",
+                self.found.len()
+            )),
+        };
+        frame.render_widget(status_line, status);
+    }
+}
+
+/// Builds a line of text after a prefix, with the spans of it that matched
+/// highlighted.
+fn highlighted<'a>(prefix: String, line: &'a str, spans: &[Range<usize>]) -> Line<'a> {
+    let mut parts = vec![Span::raw(prefix)];
+    let mut last = 0;
+    for span in spans {
+        let start = span.start.max(last);
+        parts.push(Span::raw(&line[last..start]));
+        parts.push(Span::styled(&line[start..span.end], MATCH));
+        last = span.end;
+    }
+    parts.push(Span::raw(&line[last..]));
+
+    Line::from(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn browser() -> Browser {
+        let lines = ["A test", "Actual content", "Another test"];
+        Browser::new(
+            PathBuf::from("notes.txt"),
+            (1..)
+                .zip(lines.iter().map(|line| line.to_string()))
+                .collect(),
+            Searcher::builder().case_insensitive(true).build(),
+        )
+    }
+
+    fn press(browser: &mut Browser, code: KeyCode) -> Action {
+        browser.key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn filter_as_the_pattern_is_typed() {
+        let mut browser = browser();
+        for c in "TEST".chars() {
+            press(&mut browser, KeyCode::Char(c));
+        }
+        assert_eq!(browser.found.len(), 2);
+        assert_eq!(press(&mut browser, KeyCode::Down), Action::Continue);
+        assert_eq!(press(&mut browser, KeyCode::Down), Action::Continue);
+        assert_eq!(press(&mut browser, KeyCode::Enter), Action::Edit(3));
+        press(&mut browser, KeyCode::Backspace);
+        press(&mut browser, KeyCode::Backspace);
+        assert_eq!(browser.found.len(), 3);
+        assert_eq!(browser.list.selected(), Some(0));
+        assert_eq!(press(&mut browser, KeyCode::Esc), Action::Quit);
+    }
+
+    #[test]
+    fn render_matches_and_preview() -> Result<()> {
+        let mut browser = browser();
+        press(&mut browser, KeyCode::Char('c'));
+        press(&mut browser, KeyCode::Char('o'));
+        let mut terminal = Terminal::new(TestBackend::new(80, 8))?;
+        terminal.draw(|frame| browser.render(frame))?;
+        let screen: String = (terminal.backend().buffer().content.iter())
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("2: Actual content"));
+        assert!(screen.contains("1- A test"));
+        assert!(screen.contains("Matching lines: 1"));
+
+        Ok(())
+    }
+}
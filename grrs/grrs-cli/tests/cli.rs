@@ -0,0 +1,1755 @@
+use assert_cmd::prelude::*; // Add methods on commands
+use predicates::prelude::*; // Used for writing assertions
+use std::fs::{remove_file, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Command, Stdio}; // Run programs
+use tempfile::NamedTempFile;
+
+#[test]
+fn file_doesnt_exist() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("grrs")?;
+
+    cmd.arg("foobar").arg("test/file/doesnt/exist");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No such file or directory"));
+
+    Ok(())
+}
+
+#[test]
+fn find_content_in_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("test").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("test\nLINE# 4: Another test"));
+
+    Ok(())
+}
+
+#[test]
+fn find_content_in_file_and_write_to_file() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--outfile")
+        .arg("test_outfile.txt")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success();
+    let mut file = File::open("test_outfile.txt")?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    assert_eq!(contents, "LINE# 1: A test\nLINE# 4: Another test\n");
+    remove_file("test_outfile.txt")?;
+
+    Ok(())
+}
+
+#[test]
+fn empty_pattern_string() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("").arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("pattern appears to be empty"));
+
+    Ok(())
+}
+
+#[test]
+fn filter_by_date_range() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "2023-12-31 23:59:00 ERROR before\n2024-01-01 08:00:00 ERROR inside\n  ERROR trace\n2024-01-02 00:00:00 ERROR after"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--since")
+        .arg("2024-01-01 00:00")
+        .arg("--until")
+        .arg("2024-01-02")
+        .arg("ERROR")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 2: 2024-01-01 08:00:00 ERROR inside\nLINE# 3:   ERROR trace\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn match_a_logfmt_field() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "level=info msg=\"disk ok\"\nlevel=error msg=\"disk full\"\nlevel=error msg=timeout disk=sda"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--parse")
+        .arg("logfmt")
+        .arg("--where")
+        .arg("level=error")
+        .arg("--field")
+        .arg("msg")
+        .arg("disk")
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: level=error msg=\"disk full\"\n"));
+
+    Ok(())
+}
+
+#[test]
+fn match_a_json_path() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "{{\"level\":\"info\",\"msg\":\"no error here\"}}\n{{\"level\":\"error\",\"msg\":\"boom\"}}\nnot json error"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--json-path")
+        .arg(".level")
+        .arg("error")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 2: {\"level\":\"error\",\"msg\":\"boom\"}\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn search_options_flags() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "A test\nActual content\nMore content\nAnother TEST\nTestament"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("-i")
+        .arg("-w")
+        .arg("-B")
+        .arg("1")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1: A test\n--\nLINE# 3- More content\nLINE# 4: Another TEST\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn write_json_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--json").arg("test").arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "{\"line\":\"A test\",\"line_number\":1,\"type\":\"match\"}\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn write_fzf_output() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test")?;
+    let expected = format!(
+        "{0}:1:3:A test\n{0}:3:9:Another test\n",
+        file.path().display()
+    );
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--fzf")
+        .arg("-C")
+        .arg("1")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn write_and_exit_like_grep() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test")?;
+    let expected = format!(
+        "{0}:1:A test\n{0}-2-Actual content\n{0}:3:Another test\n",
+        file.path().display()
+    );
+    let mut binary = NamedTempFile::new()?;
+    binary.write_all(b"\x7fELF\0\0a test\0")?;
+
+    let grep =
+        |pattern: &str, path: &std::path::Path| -> Result<Command, Box<dyn std::error::Error>> {
+            let mut cmd = Command::cargo_bin("grrs")?;
+            cmd.args(["--compat", "grep", "-A", "1", pattern]).arg(path);
+            Ok(cmd)
+        };
+    grep("test", file.path())?
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+    grep("absent", file.path())?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty());
+    grep("test", binary.path())?
+        .assert()
+        .success()
+        .stdout(format!("Binary file {} matches\n", binary.path().display()));
+    grep("test", "test/file/doesnt/exist".as_ref())?
+        .assert()
+        .code(2)
+        .stderr(predicate::str::starts_with("grrs: "));
+
+    // Only grep's own format is written.
+    for other in ["--json-regions", "--only-matching"] {
+        let mut cmd = grep("test", file.path())?;
+        cmd.arg(other);
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn color_each_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "warn: low disk\nerror: no disk\ninfo: fine")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--color", "always", "warn", "-e", "error"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1: \x1b[1;31mwarn\x1b[0m: low disk\nLINE# 2: \x1b[1;32merror\x1b[0m: no disk\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--json", "disk", "-e", "error"]).arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "{\"line\":\"warn: low disk\",\"line_number\":1,\"patterns\":[0],\"type\":\"match\"}\n\
+         {\"line\":\"error: no disk\",\"line_number\":2,\"patterns\":[0,1],\"type\":\"match\"}\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn display_only_the_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "banana\nnothing here")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--only-matching", "ana"]).arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: ana\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--only-matching", "--overlapping", "ana"])
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: ana\nLINE# 1: ana\n"));
+
+    Ok(())
+}
+
+#[test]
+fn show_the_function_of_each_match() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "## Usage\nrun it\n\n## Tests\nrun them\nthen run it")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["-p", "run"]).arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1= ## Usage\nLINE# 2: run it\nLINE# 4= ## Tests\nLINE# 5: run them\nLINE# 6: then run it\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["-p", "--heading-regex", "^r", "then"])
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 5= run them\nLINE# 6: then run it\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn search_a_gzipped_file() -> Result<(), Box<dyn std::error::Error>> {
+    let file = tempfile::Builder::new().suffix(".gz").tempfile()?;
+    let mut encoder = flate2::write::GzEncoder::new(file.reopen()?, flate2::Compression::fast());
+    writeln!(encoder, "A test\nActual content")?;
+    encoder.finish()?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("test").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "pretty")]
+fn highlight_syntax_in_no_other_format() -> Result<(), Box<dyn std::error::Error>> {
+    let file = tempfile::Builder::new().suffix(".rs").tempfile()?;
+    std::fs::write(file.path(), "fn main() {}\n")?;
+
+    for other in [
+        &["--compat", "grep"][..],
+        &["--json-regions"],
+        &["--only-matching"],
+    ] {
+        // cargo_bin() builds grrs with the default features only.
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_grrs"));
+        cmd.arg("--pretty").args(other).arg("main").arg(file.path());
+        cmd.assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sniff_what_files_hold() -> Result<(), Box<dyn std::error::Error>> {
+    let mut utf16 = NamedTempFile::new()?;
+    let text: Vec<u8> = "A test\nÀ test\n"
+        .encode_utf16()
+        .flat_map(u16::to_le_bytes)
+        .collect();
+    utf16.write_all(b"\xff\xfe")?;
+    utf16.write_all(&text)?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("À").arg(utf16.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: À test\n"));
+
+    let mut binary = NamedTempFile::new()?;
+    binary.write_all(b"\x7fELF\x02\x01a test")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("test").arg(binary.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("is an ELF executable, not text"));
+
+    Ok(())
+}
+
+#[test]
+fn read_through_a_buffer_of_any_size() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["search", "--buffer-size", "3", "content"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 2: Actual content\nLINE# 3: More content\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["search", "--buffer-size", "lots", "content"])
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid buffer size `lots`"));
+
+    Ok(())
+}
+
+#[test]
+fn search_without_simd() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--no-simd", "test"]).arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\nLINE# 3: Another test\n"));
+
+    Ok(())
+}
+
+#[test]
+fn read_a_config_file() -> Result<(), Box<dyn std::error::Error>> {
+    let config = tempfile::tempdir()?;
+    std::fs::create_dir(config.path().join("grrs"))?;
+    let mut file = File::create(config.path().join("grrs/config.toml"))?;
+    writeln!(file, "[search]\nignore_case = true\nbefore_context = 1")?;
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "Actual content\nA TEST")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .arg("test")
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1- Actual content\nLINE# 2: A TEST\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .arg("-B")
+        .arg("0")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: A TEST\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .arg("--no-config")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(""));
+
+    Ok(())
+}
+
+#[test]
+fn read_a_project_config_file() -> Result<(), Box<dyn std::error::Error>> {
+    let config = tempfile::tempdir()?;
+    std::fs::create_dir(config.path().join("grrs"))?;
+    let mut file = File::create(config.path().join("grrs/config.toml"))?;
+    writeln!(file, "[search]\nignore_case = true\nbefore_context = 1")?;
+    let project = tempfile::tempdir()?;
+    std::fs::create_dir(project.path().join("src"))?;
+    let mut file = File::create(project.path().join(".grrsrc"))?;
+    writeln!(file, "[search]\nbefore_context = 0")?;
+    let mut file = File::create(project.path().join("src/notes.txt"))?;
+    writeln!(file, "Actual content\nA TEST")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .current_dir(project.path().join("src"))
+        .arg("test")
+        .arg("notes.txt");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: A TEST\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .current_dir(project.path().join("src"))
+        .arg("--no-config")
+        .arg("test")
+        .arg("notes.txt");
+    cmd.assert().success().stdout(predicate::eq(""));
+
+    Ok(())
+}
+
+#[test]
+fn run_subcommands() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test test")?;
+    let other = NamedTempFile::new()?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("search").arg("content").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: Actual content\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("count")
+        .arg("-A")
+        .arg("1")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq("2\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("replace").arg("test").arg("pass").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("A pass\nActual content\nAnother pass pass\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    let expected = format!("{}\n", file.path().display());
+    cmd.arg("files")
+        .arg("test")
+        .arg(other.path())
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn count_every_match() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test test")?;
+    let mut other = NamedTempFile::new()?;
+    writeln!(other, "test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["count", "--count-matches", "test"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq("3\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["count", "--count-matches", "test"])
+        .arg(file.path())
+        .arg(other.path());
+    let expected = format!(
+        "{}:3\n{}:1\ntotal:4\n",
+        file.path().display(),
+        other.path().display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn count_words_of_matching_lines() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test test")?;
+    let mut other = NamedTempFile::new()?;
+    writeln!(other, "no match\ntest")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["count", "--wc", "test"]).arg(file.path());
+    cmd.assert().success().stdout(predicate::eq("2 5 25\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["count", "--wc", "test"])
+        .arg(file.path())
+        .arg(other.path());
+    let expected = format!(
+        "2 5 25 {}\n1 1 5 {}\n3 6 30 total\n",
+        file.path().display(),
+        other.path().display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["count", "--wc", "--count-matches", "test"])
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+fn list_files_matching_all_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let mut both = NamedTempFile::new()?;
+    writeln!(both, "foo\nand then\nbar")?;
+    let mut one = NamedTempFile::new()?;
+    writeln!(one, "foo foo")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "foo", "--files-matching-all", "bar"])
+        .arg(both.path())
+        .arg(one.path());
+    let expected = format!("{}\n", both.path().display());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn find_files_by_name() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("sub"))?;
+    std::fs::write(dir.path().join("sub/Cargo.toml"), "name = \"grrs\"\n")?;
+    std::fs::write(dir.path().join("other.toml"), "name = \"other\"\n")?;
+    std::fs::write(dir.path().join("notes.txt"), "name = \"grrs\"\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["find", "-g", "*.toml"]).arg(dir.path());
+    let expected = format!(
+        "{}\n{}\n",
+        dir.path().join("other.toml").display(),
+        dir.path().join("sub/Cargo.toml").display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    // Only content in the files with matching names counts.
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "grrs", "-g", "*.toml", "-j", "2"])
+        .arg(dir.path());
+    let expected = format!("{}\n", dir.path().join("sub/Cargo.toml").display());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn search_a_tree_with_a_link_to_a_directory() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let tree = dir.path().join("tree");
+    std::fs::create_dir_all(tree.join("sub"))?;
+    std::fs::write(tree.join("sub/a.txt"), "hello\n")?;
+    std::fs::write(tree.join("b.txt"), "hello\n")?;
+    std::os::unix::fs::symlink(tree.join("sub"), tree.join("link"))?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "-g", "*", "hello"]).arg(&tree);
+    let expected = format!(
+        "{}\n{}\n",
+        tree.join("b.txt").display(),
+        tree.join("sub/a.txt").display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()))
+        .stderr(predicate::str::is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn search_the_files_an_index_allows() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let index = dir.path().join(".grrs-index");
+    std::fs::write(dir.path().join("a.txt"), "TODO: tidy\n")?;
+    std::fs::write(dir.path().join("b.txt"), "done\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["index", "-o"]).arg(&index).arg(dir.path());
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("indexed 2 files"));
+
+    let files = || -> Result<Command, Box<dyn std::error::Error>> {
+        let mut cmd = Command::cargo_bin("grrs")?;
+        cmd.args(["files", "-i", "todo", "-g", "*.txt", "--index"])
+            .arg(&index)
+            .arg(dir.path());
+        Ok(cmd)
+    };
+    let expected = format!("{}\n", dir.path().join("a.txt").display());
+    files()?
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    // A file changed since it was indexed is read all the same.
+    std::fs::write(dir.path().join("b.txt"), "done\ntodo: more\n")?;
+    let expected = format!(
+        "{}\n{}\n",
+        dir.path().join("a.txt").display(),
+        dir.path().join("b.txt").display()
+    );
+    files()?
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn carry_on_past_unreadable_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test")?;
+    let dir = tempfile::tempdir()?;
+    let expected = format!("{}\n", file.path().display());
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("files")
+        .arg("test")
+        .arg(dir.path())
+        .arg(file.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::eq(expected.as_str()))
+        .stderr(predicate::str::contains("could not read 1 of 2 files:"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("files")
+        .arg("-s")
+        .arg("--stats")
+        .arg("test")
+        .arg(dir.path())
+        .arg("no/such/file")
+        .arg(file.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::eq(expected.as_str()))
+        .stderr(predicate::eq(
+            "1 files searched, 1 matched, 2 could not be read\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn choose_which_unreadable_files_to_skip() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test")?;
+    let dir = tempfile::tempdir()?;
+    let expected = format!("{}\n", file.path().display());
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("files")
+        .arg("--skip")
+        .arg("directory")
+        .arg("--stats")
+        .arg("test")
+        .arg(dir.path())
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()))
+        .stderr(predicate::eq(
+            "1 files searched, 1 matched, 1 could not be read\n",
+        ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("files")
+        .arg("--abort-on")
+        .arg("missing")
+        .arg("test")
+        .arg("no/such/file")
+        .arg(file.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains("`no/such/file` does not exist"))
+        .stderr(predicate::str::contains("stopped after 1 of 2 files"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("files")
+        .arg("--skip")
+        .arg("gone")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "unknown kind of file error `gone`",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn stop_after_too_many_errors() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "--max-errors", "1", "test", "gone/a", "gone/b"])
+        .arg(file.path());
+    cmd.assert()
+        .code(2)
+        .stdout(predicate::eq(""))
+        .stderr(predicate::str::contains(
+            "stopped after 2 of 3 files, as more than 1 could not be read",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn search_the_files_git_lists() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(dir.path())
+            .args(args)
+            .status()
+    };
+    git(&["init", "-q"])?;
+    std::fs::write(dir.path().join("committed.txt"), "TODO\n")?;
+    std::fs::write(dir.path().join("staged.txt"), "TODO\n")?;
+    std::fs::write(dir.path().join("untracked.txt"), "TODO\n")?;
+    git(&["add", "committed.txt"])?;
+    git(&[
+        "-c",
+        "user.name=grrs",
+        "-c",
+        "user.email=grrs@example.com",
+        "commit",
+        "-q",
+        "-m",
+        "first",
+    ])?;
+    git(&["add", "staged.txt"])?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path())
+        .arg("files")
+        .arg("--git")
+        .arg("staged")
+        .arg("TODO");
+    cmd.assert().success().stdout(predicate::eq("staged.txt\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path())
+        .arg("files")
+        .arg("--git")
+        .arg("tracked")
+        .arg("TODO")
+        .arg("committed.txt");
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("committed.txt\n"));
+
+    Ok(())
+}
+
+#[test]
+fn compare_the_matches_of_two_files() -> Result<(), Box<dyn std::error::Error>> {
+    let mut staging = NamedTempFile::new()?;
+    writeln!(staging, "port = 8080\nhost = staging\nlog = debug")?;
+    let mut production = NamedTempFile::new()?;
+    writeln!(production, "log = info\nhost = prod\nport  = 8080")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("diff")
+        .arg("-E")
+        .arg("^(host|port)")
+        .arg(staging.path())
+        .arg(production.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "- LINE# 2: host = staging\n+ LINE# 2: host = prod\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn search_the_git_history() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(dir.path())
+            .args(args)
+            .status()
+    };
+    let commit = |author: &str, message: &str| {
+        git(&["add", "-A"])?;
+        let name = format!("user.name={}", author);
+        let identity = ["-c", &name, "-c", "user.email=grrs@example.com"];
+        git(&[&identity[..], &["commit", "-q", "-m", message]].concat())
+    };
+    git(&["init", "-q"])?;
+    std::fs::write(dir.path().join("notes.txt"), "TODO: tidy\n")?;
+    commit("ana", "Add notes")?;
+    std::fs::write(dir.path().join("notes.txt"), "tidied\n")?;
+    commit("bo", "Tidy up")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path())
+        .arg("log")
+        .arg("-i")
+        .arg("--author")
+        .arg("bo")
+        .arg("todo");
+    cmd.assert().success().stdout(predicate::str::is_match(
+        "^[0-9a-f]{12} [0-9-]{10} bo: Tidy up\n  - notes.txt: TODO: tidy\n$",
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn time_the_engines() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("bench")
+        .arg("--size")
+        .arg("1")
+        .arg("--runs")
+        .arg("1");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "searching 1.0 MiB for `needle`, best of 1 runs\nliteral ",
+        ))
+        .stdout(predicate::str::contains("MiB/s").count(3));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn answer_searches_over_a_socket() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::net::UnixStream;
+
+    let dir = tempfile::tempdir()?;
+    let socket = dir.path().join("grrs.sock");
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "A test\nActual content\n")?;
+    let mut daemon = Command::cargo_bin("grrs")?
+        .arg("serve")
+        .arg("--socket")
+        .arg(&socket)
+        .arg("--root")
+        .arg(dir.path())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let mut tries = 0;
+    let stream = loop {
+        match UnixStream::connect(&socket) {
+            Ok(stream) => break stream,
+            Err(_) if tries < 100 => tries += 1,
+            Err(err) => return Err(err.into()),
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+
+    let mut requests = stream.try_clone()?;
+    let mut responses = BufReader::new(stream);
+    let request = format!(
+        "{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"search\",\"params\":{{\"pattern\":\"test\",\"path\":{:?}}}}}\n",
+        notes
+    );
+    requests.write_all(request.as_bytes())?;
+    let mut response = String::new();
+    responses.read_line(&mut response)?;
+    daemon.kill()?;
+    daemon.wait()?;
+    assert_eq!(
+        response,
+        "{\"id\":1,\"jsonrpc\":\"2.0\",\"result\":{\"events\":[{\"line\":\"A test\",\"line_number\":1,\"type\":\"match\"}],\"matched_lines\":1,\"total_lines\":2}}\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn answer_searches_over_http() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    let dir = tempfile::tempdir()?;
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "A test\nActual content\n")?;
+    let mut daemon = Command::cargo_bin("grrs")?
+        .arg("serve")
+        .arg("--http")
+        .arg("127.0.0.1:0")
+        .arg("--root")
+        .arg(dir.path())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut listening = String::new();
+    BufReader::new(daemon.stderr.take().unwrap()).read_line(&mut listening)?;
+    let address = listening
+        .trim()
+        .trim_start_matches("grrs: listening on http://");
+
+    let send = |method: &str, target: &str, body: &str| -> std::io::Result<String> {
+        let mut stream = TcpStream::connect(address)?;
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        Ok(response)
+    };
+    let body = format!(
+        "{{\"pattern\":\"test\",\"paths\":[{:?},\"missing.txt\"]}}",
+        notes
+    );
+    let whole = send("POST", "/search", &body)?;
+    let streamed = send("POST", "/search?stream=true", &body)?;
+    let unknown = send("POST", "/index", "{}")?;
+    let metrics = send("GET", "/metrics", "")?;
+    daemon.kill()?;
+    daemon.wait()?;
+
+    assert!(whole.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(whole.ends_with(&format!(
+        "{{\"files\":[{{\"events\":[{{\"line\":\"A test\",\"line_number\":1,\"type\":\"match\"}}],\"matched_lines\":1,\"path\":{:?},\"total_lines\":2}},{{\"error\":\"`missing.txt` was not found in the directory being served\",\"path\":\"missing.txt\"}}]}}",
+        notes
+    )));
+    assert!(streamed.contains("Transfer-Encoding: chunked\r\n"));
+    assert!(streamed
+        .contains("\"error\":\"`missing.txt` was not found in the directory being served\""));
+    assert!(streamed.ends_with("\r\n0\r\n\r\n"));
+    assert!(unknown.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(metrics.contains("\ngrrs_searches_total{transport=\"http\"} 2\n"));
+    assert!(metrics.contains("\ngrrs_cache_hits_total 1\n"));
+
+    Ok(())
+}
+
+#[test]
+fn exit_quietly_when_the_pipe_closes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    for _ in 0..100_000 {
+        writeln!(file, "A test line")?;
+    }
+
+    // Closing stdout straight away, like `| head` would after its first lines.
+    let mut child = Command::cargo_bin("grrs")?
+        .arg("test")
+        .arg(file.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    drop(child.stdout.take());
+    let output = child.wait_with_output()?;
+    assert_eq!(output.status.code(), Some(141));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn stop_cleanly_on_ctrl_c() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let session = dir.path().join("session.json");
+    let mut child = Command::cargo_bin("grrs")?
+        .arg("test")
+        .arg("/dev/stdin")
+        .arg("--save-session")
+        .arg(&session)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    writeln!(stdin, "A test")?;
+    let mut first = String::new();
+    stdout.read_line(&mut first)?;
+    assert_eq!(first, "LINE# 1: A test\n");
+
+    // The search is waiting for its next line, which it stops before.
+    Command::new("kill")
+        .arg("-INT")
+        .arg(child.id().to_string())
+        .status()?;
+    // The handler runs on its own thread, so give it a moment.
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    writeln!(stdin, "Another test")?;
+    let output = child.wait_with_output()?;
+    assert_eq!(output.status.code(), Some(130));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        "grrs: interrupted after reading 1 lines, of which 1 matched\n"
+    );
+    assert!(std::fs::read_to_string(&session)?.contains("A test"));
+
+    Ok(())
+}
+
+#[test]
+fn search_invalid_utf8_lossily() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"A test\n\xff\xfe\nAnother test\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("test").arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("line 2 is not valid UTF-8"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--lossy")
+        .arg("-C")
+        .arg("1")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1: A test\nLINE# 2- \u{fffd}\u{fffd}\nLINE# 3: Another test\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn choose_line_terminators() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    file.write_all(b"A test\r\nActual content\r\nAnother test\r")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("-E").arg("test$").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\nLINE# 3: Another test\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--crlf").arg("content").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: Actual content\r\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("replace")
+        .arg("--crlf")
+        .arg("test")
+        .arg("pass")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "A pass\r\nActual content\r\nAnother pass\r\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn display_match_columns() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "Ünïcödé test\n日本語 test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--column").arg("test").arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1:9: Ünïcödé test\nLINE# 2:5: 日本語 test\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--column")
+        .arg("--byte-columns")
+        .arg("test")
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1:13: Ünïcödé test\nLINE# 2:11: 日本語 test\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn search_a_long_line_in_a_window() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test")?;
+    writeln!(
+        file,
+        "{}found it{}",
+        "x".repeat(500_000),
+        "y".repeat(500_000)
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--window").arg("3").arg("found").arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 2: xxxfound it\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--window")
+        .arg("3")
+        .arg("-C")
+        .arg("1")
+        .arg("found")
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--window can't be combined"));
+
+    Ok(())
+}
+
+#[test]
+fn search_interactively() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--interactive")
+        .arg("-E")
+        .arg(file.path())
+        .with_stdin()
+        .buffer("test\n(\ncontent$\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\nLINE# 2: Actual content\n"))
+        .stderr(predicate::str::contains("unclosed group"));
+
+    Ok(())
+}
+
+#[test]
+fn open_a_match_in_an_editor() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test")?;
+    let opened = format!("+3 {}\n", file.path().display());
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("EDITOR", "echo")
+        .arg("--edit")
+        .arg("test")
+        .arg(file.path())
+        .with_stdin()
+        .buffer("2\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::ends_with(opened.as_str()))
+        .stderr(predicate::str::contains("Open which match (1-2"));
+
+    Ok(())
+}
+
+#[test]
+fn record_and_recall_patterns() -> Result<(), Box<dyn std::error::Error>> {
+    let config = tempfile::tempdir()?;
+    std::fs::create_dir(config.path().join("grrs"))?;
+    std::fs::write(config.path().join("grrs/config.toml"), "history = true\n")?;
+    let data = tempfile::tempdir()?;
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .env("XDG_DATA_HOME", data.path())
+        .arg("content")
+        .arg(file.path());
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .env("XDG_DATA_HOME", data.path())
+        .arg("--interactive")
+        .arg(file.path())
+        .with_stdin()
+        .buffer("test\n!1\n!9\n")
+        .assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\nLINE# 2: Actual content\n"))
+        .stderr(predicate::str::contains("no pattern 9"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_DATA_HOME", data.path()).arg("--history");
+    cmd.assert().success().stdout(predicate::eq(
+        "    1  content\n    2  test\n    3  content\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn save_and_reuse_a_session() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let (old, new) = (dir.path().join("old.grrs"), dir.path().join("new.grrs"));
+    let mut file = File::create(dir.path().join("notes.txt"))?;
+    writeln!(file, "A test\nActual content\nAnother test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--save-session")
+        .arg(&old)
+        .arg("-A")
+        .arg("1")
+        .arg("test")
+        .arg(dir.path().join("notes.txt"));
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("session").arg("show").arg(&old);
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1: A test\nLINE# 2- Actual content\nLINE# 3: Another test\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("session")
+        .arg("filter")
+        .arg("-i")
+        .arg("a TEST")
+        .arg(&old);
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: A test\n"));
+
+    let mut file = File::create(dir.path().join("notes.txt"))?;
+    writeln!(file, "One more test\nAnother test")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--save-session")
+        .arg(&new)
+        .arg("test")
+        .arg(dir.path().join("notes.txt"));
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("session").arg("diff").arg(&old).arg(&new);
+    cmd.assert().success().stdout(predicate::eq(
+        "- LINE# 1: A test\n+ LINE# 1: One more test\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn save_a_session_for_one_pattern_only() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let session = dir.path().join("session.grrs");
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "A test\nb\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["test", "-e", "b", "--save-session"])
+        .arg(&session)
+        .arg(&notes);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    assert!(!session.exists());
+
+    Ok(())
+}
+
+#[test]
+fn compare_with_a_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let baseline = dir.path().join("baseline.grrs");
+    let notes = dir.path().join("notes.txt");
+    std::fs::write(&notes, "TODO: tidy\nTODO: test\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--save-session")
+        .arg(&baseline)
+        .arg("TODO")
+        .arg(&notes);
+    cmd.assert().success();
+
+    std::fs::write(&notes, "tidied\nTODO: test\n")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--baseline").arg(&baseline).arg("TODO").arg(&notes);
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("- LINE# 1: TODO: tidy\n"));
+
+    std::fs::write(&notes, "TODO: test\nTODO: document\n")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.arg("--baseline").arg(&baseline).arg("TODO").arg(&notes);
+    cmd.assert().code(1).stdout(predicate::eq(
+        "- LINE# 1: TODO: tidy\n+ LINE# 2: TODO: document\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn compare_a_tree_with_a_baseline() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let baseline = dir.path().join("baseline.grrs");
+    let src = dir.path().join("src");
+    std::fs::create_dir(&src)?;
+    std::fs::write(src.join("a.rs"), "// TODO: tidy\nfn a() {}\n")?;
+    std::fs::write(src.join("b.rs"), "// TODO: test\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "TODO", "-g", "*.rs", "--save-session"])
+        .arg(&baseline)
+        .arg(&src);
+    let expected = format!(
+        "{}\n{}\n",
+        src.join("a.rs").display(),
+        src.join("b.rs").display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["session", "show"]).arg(&baseline);
+    let expected = format!(
+        "{}\nLINE# 1: // TODO: tidy\n{}\nLINE# 1: // TODO: test\n",
+        src.join("a.rs").display(),
+        src.join("b.rs").display()
+    );
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    std::fs::write(src.join("a.rs"), "fn a() {}\n")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "TODO", "-g", "*.rs", "--baseline"])
+        .arg(&baseline)
+        .arg(&src);
+    let expected = format!("- {}: LINE# 1: // TODO: tidy\n", src.join("a.rs").display());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq(expected.as_str()));
+
+    std::fs::write(src.join("c.rs"), "fn c() {}\n// TODO: document\n")?;
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "TODO", "-g", "*.rs", "--baseline"])
+        .arg(&baseline)
+        .arg(&src);
+    let expected = format!(
+        "- {}: LINE# 1: // TODO: tidy\n+ {}: LINE# 2: // TODO: document\n",
+        src.join("a.rs").display(),
+        src.join("c.rs").display()
+    );
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::eq(expected.as_str()));
+
+    Ok(())
+}
+
+#[test]
+fn compare_with_a_revision() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(dir.path())
+            .args(args)
+            .status()
+    };
+    git(&["init", "-q"])?;
+    std::fs::create_dir(dir.path().join("src"))?;
+    std::fs::write(dir.path().join("src/a.rs"), "// TODO: tidy\n")?;
+    std::fs::write(dir.path().join("notes.txt"), "TODO: test\n")?;
+    git(&["add", "-A"])?;
+    git(&[
+        "-c",
+        "user.name=grrs",
+        "-c",
+        "user.email=grrs@example.com",
+        "commit",
+        "-q",
+        "-m",
+        "first",
+    ])?;
+    std::fs::write(dir.path().join("src/a.rs"), "fn a() {}\n")?;
+    std::fs::write(dir.path().join("src/b.rs"), "// TODO: document\n")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path()).args([
+        "files",
+        "TODO",
+        ".",
+        "-g",
+        "*.rs",
+        "--baseline-rev",
+        "HEAD",
+    ]);
+    cmd.assert().code(1).stdout(predicate::eq(
+        "- src/a.rs: LINE# 1: // TODO: tidy\n+ src/b.rs: LINE# 1: // TODO: document\n",
+    ));
+
+    // Nothing has changed in the notes since.
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path())
+        .args(["TODO", "notes.txt", "--baseline-rev", "HEAD"]);
+    cmd.assert().success().stdout(predicate::str::is_empty());
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.current_dir(dir.path())
+        .args(["TODO", "src/a.rs", "--baseline-rev", "HEAD"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("- LINE# 1: // TODO: tidy\n"));
+
+    Ok(())
+}
+
+#[test]
+fn use_a_saved_pattern() -> Result<(), Box<dyn std::error::Error>> {
+    let config = tempfile::tempdir()?;
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "TODO: tidy\ndone\ntodo: document")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .args(["pattern", "save", "todo", "-i", "todo:"]);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .args(["--use", "todo"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1: TODO: tidy\nLINE# 3: todo: document\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .args(["pattern", "list"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("todo  todo:\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.env("XDG_CONFIG_HOME", config.path())
+        .args(["--use", "fixme"])
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("no pattern saved as `fixme`"));
+
+    Ok(())
+}
+
+#[test]
+fn report_progress_as_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut a = NamedTempFile::new()?;
+    writeln!(a, "a test")?;
+    let mut b = NamedTempFile::new()?;
+    writeln!(b, "no match")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["files", "--progress-json", "test"])
+        .arg(a.path())
+        .arg(b.path());
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout)?,
+        format!("{}\n", a.path().display())
+    );
+    let stderr = String::from_utf8(output.stderr)?;
+    let done: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap())?;
+    assert_eq!(done["event"], "done");
+    assert_eq!(done["files"], 2);
+    assert_eq!(done["bytes"], 16);
+    assert_eq!(done["path"], b.path().display().to_string());
+
+    Ok(())
+}
+
+#[test]
+fn pass_every_line_through() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "starting\nerror: disk full\nstopping")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--passthru", "--color", "always", "error"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1- starting\nLINE# 2: \x1b[1;31merror\x1b[0m: disk full\nLINE# 3- stopping\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn plan_replacements_as_json() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "A test\nActual content\nAnother test test")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["replace", "--plan-json", "test", "pass"])
+        .arg(file.path());
+    let output = cmd.output()?;
+    assert!(output.status.success());
+    let edits = String::from_utf8(output.stdout)?
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+    let path = file.path().display().to_string();
+    assert_eq!(
+        edits,
+        [(1, 2, 6), (3, 8, 12), (3, 13, 17)]
+            .iter()
+            .map(|&(line_number, start, end)| serde_json::json!({
+                "path": path,
+                "line_number": line_number,
+                "start": start,
+                "end": end,
+                "old": "test",
+                "new": "pass",
+            }))
+            .collect::<Vec<_>>()
+    );
+    let mut content = String::new();
+    File::open(file.path())?.read_to_string(&mut content)?;
+    assert_eq!(content, "A test\nActual content\nAnother test test\n");
+
+    Ok(())
+}
+
+#[test]
+fn timestamp_lines_written() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "booting\nerror: no disk")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--timestamps", "error"]).arg(file.path());
+    cmd.assert().success().stdout(
+        predicate::str::is_match(
+            r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d{3} LINE# 2: error: no disk\n$",
+        )
+        .unwrap(),
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_the_outfile_in_another_encoding() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "café test")?;
+
+    for (encoding, expected) in [
+        ("latin1", b"LINE# 1: caf\xe9 test\n".to_vec()),
+        (
+            "utf-16le",
+            b"\xff\xfeL\0I\0N\0E\0#\0 \x001\0:\0 \0c\0a\0f\0\xe9\0 \0t\0e\0s\0t\0\n\0".to_vec(),
+        ),
+    ] {
+        let outfile = dir.path().join(encoding);
+        let mut cmd = Command::cargo_bin("grrs")?;
+        cmd.args(["--output-encoding", encoding, "--outfile"])
+            .arg(&outfile)
+            .arg("test")
+            .arg(file.path());
+        cmd.assert().success();
+        assert_eq!(std::fs::read(&outfile)?, expected);
+    }
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--output-encoding", "ebcdic", "-o"])
+        .arg(dir.path().join("ebcdic"))
+        .arg("test")
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown encoding `ebcdic`"));
+
+    Ok(())
+}
+
+#[test]
+fn replace_preserving_case() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "color: Color\nCOLOR_NAME")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["replace", "--preserve-case", "color", "colour"])
+        .arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("colour: Colour\nCOLOUR_NAME\n"));
+
+    Ok(())
+}
+
+#[test]
+fn limit_the_rate_of_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    for line in 0..5 {
+        writeln!(file, "error {}", line)?;
+    }
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--rate-limit", "2/h", "error"]).arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 1: error 0\nLINE# 2: error 1\n"))
+        .stderr(predicate::str::contains(
+            "dropped 3 matching lines over the rate limit",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn print_context_until_a_line_matches() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "2024-01-02 10:00 started\n2024-01-02 10:01 panic: lost\n  at main.rs:4\n  at lib.rs:9\n2024-01-02 10:02 retrying"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--after-context-until", r"^\d{4}-", "panic"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 2: 2024-01-02 10:01 panic: lost\nLINE# 3-   at main.rs:4\nLINE# 4-   at lib.rs:9\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn search_whole_records() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(
+        file,
+        "name: web\nport: 80\n\nname: db\nport: 5432\n\nname: cache\nport: 6379"
+    )?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--paragraph", "543"]).arg(file.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::eq("LINE# 4- name: db\nLINE# 5: port: 5432\n"));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--record-separator", "^name: db$", "-E", "^port: [68]"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "LINE# 1- name: web\nLINE# 2: port: 80\nLINE# 3- \n--\nLINE# 5- port: 5432\nLINE# 6- \nLINE# 7- name: cache\nLINE# 8: port: 6379\n",
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn write_merged_json_regions() -> Result<(), Box<dyn std::error::Error>> {
+    let mut file = NamedTempFile::new()?;
+    writeln!(file, "a\nerror 1\nb\nerror 2\nc\nd\ne")?;
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--json-regions", "-C", "1", "error"])
+        .arg(file.path());
+    cmd.assert().success().stdout(predicate::eq(
+        "{\"end\":5,\"lines\":[\
+         {\"line\":\"a\",\"line_number\":1,\"type\":\"context\"},\
+         {\"line\":\"error 1\",\"line_number\":2,\"type\":\"match\"},\
+         {\"line\":\"b\",\"line_number\":3,\"type\":\"context\"},\
+         {\"line\":\"error 2\",\"line_number\":4,\"type\":\"match\"},\
+         {\"line\":\"c\",\"line_number\":5,\"type\":\"context\"}\
+         ],\"start\":1,\"type\":\"region\"}\n",
+    ));
+
+    let mut cmd = Command::cargo_bin("grrs")?;
+    cmd.args(["--json-regions", "--only-matching", "error"])
+        .arg(file.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
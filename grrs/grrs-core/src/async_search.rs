@@ -0,0 +1,131 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::Matcher;
+use crate::search::{Match, Progress, SearchSummary, Searcher};
+use crate::sink::Sink;
+use futures_util::stream::{self, Stream};
+use std::borrow::Cow;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+impl Searcher {
+    /// Searches the lines read from an asynchronous reader with any
+    /// grrs_core::matcher::Matcher, numbering them from 1 and sending events to
+    /// the sink as with grrs_core::Searcher::search_sink(). Requires the `async`
+    /// feature.
+    ///
+    /// Reading yields to the runtime rather than blocking a worker thread, and
+    /// the future can be spawned when the matcher is `Sync` and the sink `Send`.
+    pub async fn search_async_reader<R, M, K>(
+        &self,
+        matcher: &M,
+        reader: R,
+        sink: &mut K,
+    ) -> Result<SearchSummary, GrrsError>
+    where
+        R: AsyncBufRead + Unpin,
+        M: Matcher + ?Sized,
+        K: Sink + ?Sized,
+    {
+        let mut progress = Progress::new(self.options())?;
+        let mut lines = reader.lines();
+        let mut num = 0;
+        while let Some(line) = lines
+            .next_line()
+            .await
+            .map_err(|err| GrrsError::reading(err, num + 1))?
+        {
+            num += 1;
+            if progress.line(matcher, num, line, sink)?.is_break() {
+                break;
+            }
+        }
+
+        sink.finish()?;
+
+        Ok(progress.summary)
+    }
+}
+
+/// Yields every matching line read from an asynchronous reader as it's found,
+/// numbering lines from 1, like grrs_core::matches_in(), so matches can be
+/// consumed with stream combinators. Requires the `async` feature.
+///
+/// The stream ends after the first read error, which it yields.
+pub fn stream_matches<R, M>(
+    reader: R,
+    matcher: M,
+) -> impl Stream<Item = Result<Match<'static>, GrrsError>>
+where
+    R: AsyncBufRead + Unpin,
+    M: Matcher,
+{
+    let state = Some((reader.lines(), matcher, 0));
+    stream::unfold(state, |state| async move {
+        let (mut lines, matcher, mut num) = state?;
+        loop {
+            num += 1;
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(err) => return Some((Err(GrrsError::reading(err, num)), None)),
+            };
+            let spans: Vec<_> = matcher.find_iter(&line).collect();
+            if !spans.is_empty() {
+                let found = Match {
+                    line_number: num,
+                    line: Cow::Owned(line),
+                    spans,
+                };
+                return Some((Ok(found), Some((lines, matcher, num))));
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::LiteralMatcher;
+    use crate::sink::{CollectSink, SinkEvent};
+    use futures_util::StreamExt;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[tokio::test]
+    async fn search_an_async_reader() -> Result<(), GrrsError> {
+        let searcher = Searcher::builder().max_count(1).build();
+        let matcher = LiteralMatcher::new("lorem");
+        let mut sink = CollectSink::default();
+        let search = searcher.search_async_reader(&matcher, &b"lorem\nipsum\nlorem"[..], &mut sink);
+        assert_send(&search);
+        let summary = search.await?;
+        assert_eq!(
+            sink.events,
+            vec![SinkEvent::Match {
+                line_number: 1,
+                line: "lorem".into()
+            }]
+        );
+        assert_eq!(summary.total_lines, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stream_all_matches() {
+        let reader = &b"lorem ipsum\ndolor\nsum\n\xff"[..];
+        let found: Vec<_> = stream_matches(reader, LiteralMatcher::new("um"))
+            .map(|found| found.map(|m| (m.line_number, m.line.into_owned())))
+            .collect()
+            .await;
+        assert_eq!(found.len(), 3);
+        assert_eq!(
+            found[0].as_ref().ok(),
+            Some(&(1, "lorem ipsum".to_string()))
+        );
+        assert_eq!(found[1].as_ref().ok(), Some(&(3, "sum".to_string())));
+        assert!(matches!(
+            found[2],
+            Err(GrrsError::EncodingError { line_number: 4 })
+        ));
+    }
+}
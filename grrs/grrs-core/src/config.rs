@@ -0,0 +1,374 @@
+use crate::error::{GrrsError, Result};
+use crate::options::SearchOptions;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use toml::map::Entry;
+use toml::{Table, Value};
+
+/// The name of the per-project configuration file.
+pub const PROJECT_FILE: &str = ".grrsrc";
+
+/// Defaults read from TOML configuration files, which command line flags take
+/// precedence over. Requires the `config` feature.
+///
+/// Search options go in a `[search]` table, named as in
+/// grrs_core::SearchOptions, and a top-level `history = true` opts in to
+/// recording searched patterns; unknown tables or keys are rejected. A project
+/// can share its own defaults in a `.grrsrc` file of the same format, which
+/// overrides the user's file key by key.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::config::Config;
+/// # fn main() -> Result<(), Error> {
+/// let config = Config::from_toml("[search]\nsmart_case = true\ncontext = 2\n")?;
+/// assert!(config.search.smart_case);
+/// assert_eq!(config.search.context, Some(2));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub search: SearchOptions,
+    /// Record the patterns searched for, so they can be listed and recalled
+    pub history: bool,
+}
+
+impl Config {
+    /// Parses a configuration from TOML text.
+    pub fn from_toml(text: &str) -> Result<Config, GrrsError> {
+        toml::from_str(text).map_err(|err| GrrsError::InvalidConfig(err.message().to_string()))
+    }
+
+    /// Reads the configuration file at the supplied path, or returns `None` if
+    /// it doesn't exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<Option<Config>, GrrsError> {
+        match read_table(path.as_ref())? {
+            None => Ok(None),
+            Some(table) => Ok(Some(Config::from_table(table)?)),
+        }
+    }
+
+    /// Reads whichever of the supplied configuration files exist, with each
+    /// file's keys taking precedence over those of the files before it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use anyhow::{Error, Result};
+    /// use grrs_core::config::Config;
+    /// # fn main() -> Result<(), Error> {
+    /// let dir = tempfile::tempdir()?;
+    /// std::fs::write(dir.path().join("user.toml"), "[search]\nignore_case = true\nmax_count = 5\n")?;
+    /// std::fs::write(dir.path().join(".grrsrc"), "[search]\nmax_count = 1\n")?;
+    /// let config = Config::load_all(&[dir.path().join("user.toml"), dir.path().join(".grrsrc")])?;
+    /// assert!(config.search.ignore_case);
+    /// assert_eq!(config.search.max_count, Some(1));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn load_all<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+    ) -> Result<Config, GrrsError> {
+        let mut merged = Table::new();
+        for path in paths {
+            if let Some(table) = read_table(path.as_ref())? {
+                merge(&mut merged, table);
+            }
+        }
+
+        Config::from_table(merged)
+    }
+
+    /// Returns the nearest `.grrsrc` in the supplied directory or any of its
+    /// ancestors, so every directory of a project shares the same defaults.
+    pub fn find_project_file(dir: impl AsRef<Path>) -> Option<PathBuf> {
+        dir.as_ref()
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+    }
+
+    fn from_table(table: Table) -> Result<Config, GrrsError> {
+        Value::Table(table)
+            .try_into()
+            .map_err(|err: toml::de::Error| GrrsError::InvalidConfig(err.message().to_string()))
+    }
+
+    /// Returns where the user's configuration file is expected to be:
+    /// `grrs/config.toml` under `$XDG_CONFIG_HOME`, or under `~/.config` when
+    /// that isn't set.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(dir.join("grrs").join("config.toml"))
+    }
+}
+
+/// Patterns saved under names, such as `deprecated-api` or `pii`, along with
+/// the search options each is meant to be searched with, so a team can share
+/// canonical searches. Requires the `config` feature.
+///
+/// They're kept in a TOML file with a table for each name, holding the
+/// `pattern` and any options named as in grrs_core::SearchOptions.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::config::{SavedPattern, SavedPatterns};
+/// use grrs_core::SearchOptions;
+/// # fn main() -> Result<(), Error> {
+/// let dir = tempfile::tempdir()?;
+/// let path = dir.path().join("patterns.toml");
+/// let options = SearchOptions { ignore_case: true, ..Default::default() };
+/// let mut saved = SavedPatterns::load(&path)?;
+/// saved.insert("todo", SavedPattern::new("todo:", &options, |name| name == "ignore_case"));
+/// saved.save(&path)?;
+/// assert_eq!(std::fs::read_to_string(&path)?, "[todo]\npattern = \"todo:\"\nignore_case = true\n");
+///
+/// let todo = SavedPatterns::load(&path)?.get("todo").cloned().unwrap();
+/// let configured = SearchOptions { max_count: Some(1), ..Default::default() };
+/// let options = todo.options(&configured)?;
+/// assert!(options.ignore_case);
+/// assert_eq!(options.max_count, Some(1));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SavedPatterns {
+    patterns: BTreeMap<String, SavedPattern>,
+}
+
+impl SavedPatterns {
+    /// Reads the saved patterns, or none if the file doesn't exist, checking
+    /// that each pattern's options are ones that exist.
+    pub fn load(path: impl AsRef<Path>) -> Result<SavedPatterns, GrrsError> {
+        let path = path.as_ref();
+        let invalid = |reason: &str| {
+            let path = crate::display_path(path);
+            GrrsError::InvalidConfig(format!("{}: {}", path.display(), reason))
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(SavedPatterns::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let saved: SavedPatterns = toml::from_str(&text).map_err(|err| invalid(err.message()))?;
+        for (name, pattern) in &saved.patterns {
+            (pattern.options(&SearchOptions::default()))
+                .map_err(|err| invalid(&format!("pattern `{}`: {}", name, err)))?;
+        }
+
+        Ok(saved)
+    }
+
+    /// Writes the saved patterns, creating the directory they're kept in if
+    /// need be.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GrrsError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Patterns and options are plain strings and values, which always
+        // serialize.
+        fs::write(path, toml::to_string(self).unwrap())?;
+
+        Ok(())
+    }
+
+    /// Returns the pattern saved under a name.
+    pub fn get(&self, name: &str) -> Option<&SavedPattern> {
+        self.patterns.get(name)
+    }
+
+    /// Saves a pattern under a name, replacing any saved under it before.
+    pub fn insert(&mut self, name: impl Into<String>, pattern: SavedPattern) {
+        self.patterns.insert(name.into(), pattern);
+    }
+
+    /// Returns each name and the pattern saved under it, in order of name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SavedPattern)> {
+        (self.patterns.iter()).map(|(name, pattern)| (name.as_str(), pattern))
+    }
+
+    /// Returns where the saved patterns are expected to be:
+    /// `grrs/patterns.toml` beside the user's configuration file.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(Config::default_path()?.with_file_name("patterns.toml"))
+    }
+}
+
+/// A pattern saved with some search options, see grrs_core::config::SavedPatterns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPattern {
+    pub pattern: String,
+    /// The options saved with the pattern, named as in grrs_core::SearchOptions
+    #[serde(flatten)]
+    options: Table,
+}
+
+impl SavedPattern {
+    /// Saves a pattern with each of the options that `is_set` reports by name,
+    /// such as the flags given alongside it, leaving the others to be taken
+    /// from wherever the pattern is searched for.
+    pub fn new(
+        pattern: impl Into<String>,
+        options: &SearchOptions,
+        is_set: impl Fn(&str) -> bool,
+    ) -> SavedPattern {
+        // Options serialize to a table of plain values, leaving out those
+        // that are None.
+        let mut options = match Value::try_from(options) {
+            Ok(Value::Table(table)) => table,
+            _ => Table::new(),
+        };
+        options.retain(|name, _| is_set(name));
+
+        SavedPattern {
+            pattern: pattern.into(),
+            options,
+        }
+    }
+
+    /// Returns the configured options with those saved with the pattern
+    /// taking precedence.
+    pub fn options(&self, configured: &SearchOptions) -> Result<SearchOptions, GrrsError> {
+        let mut options = match Value::try_from(configured) {
+            Ok(Value::Table(table)) => table,
+            _ => Table::new(),
+        };
+        // Unlike in TOML, options that are None are still named in JSON.
+        let known = serde_json::to_value(SearchOptions::default()).unwrap();
+        for (name, value) in &self.options {
+            if known.get(name).is_none() {
+                return Err(GrrsError::InvalidConfig(format!(
+                    "unknown option `{}`",
+                    name
+                )));
+            }
+            options.insert(name.clone(), value.clone());
+        }
+
+        Value::Table(options)
+            .try_into()
+            .map_err(|err: toml::de::Error| GrrsError::InvalidConfig(err.message().to_string()))
+    }
+}
+
+/// Reads and checks a single configuration file, returning `None` if it
+/// doesn't exist.
+fn read_table(path: &Path) -> Result<Option<Table>, GrrsError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let invalid = |reason: &str| {
+        let path = crate::display_path(path);
+        GrrsError::InvalidConfig(format!("{}: {}", path.display(), reason))
+    };
+    let table: Table = toml::from_str(&text).map_err(|err| invalid(err.message()))?;
+    match Config::from_table(table.clone()) {
+        Ok(_) => Ok(Some(table)),
+        Err(GrrsError::InvalidConfig(reason)) => Err(invalid(&reason)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Overlays one table of configuration onto another, merging nested tables.
+fn merge(base: &mut Table, layer: Table) {
+    for (key, value) in layer {
+        match base.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(mut entry) => match (entry.get_mut(), value) {
+                (Value::Table(base), Value::Table(layer)) => merge(base, layer),
+                (slot, value) => *slot = value,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_a_config() -> Result<(), GrrsError> {
+        let config = Config::from_toml("[search]\nignore_case = true\nbefore_context = 3\n")?;
+        assert_eq!(
+            config.search,
+            SearchOptions {
+                ignore_case: true,
+                before_context: 3,
+                ..SearchOptions::default()
+            }
+        );
+        assert_eq!(Config::from_toml("")?, Config::default());
+        assert!(Config::from_toml("history = true\n")?.history);
+        assert!(matches!(
+            Config::from_toml("[colors]\nmatch = \"red\"\n"),
+            Err(GrrsError::InvalidConfig(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_saved_options() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("patterns.toml");
+        fs::write(
+            &path,
+            "[pii]\npattern = \"\\\\d{3}\"\nregex = true\ncontext = 1\n",
+        )?;
+        let options = SavedPatterns::load(&path)?
+            .get("pii")
+            .unwrap()
+            .options(&SearchOptions::default())?;
+        assert_eq!((options.regex, options.context), (true, Some(1)));
+        fs::write(&path, "[pii]\npattern = \"x\"\ncolour = true\n")?;
+        assert!(matches!(
+            SavedPatterns::load(&path),
+            Err(GrrsError::InvalidConfig(reason)) if reason.contains("unknown option `colour`")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn load_a_missing_config() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        assert_eq!(Config::load(dir.path().join("config.toml"))?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_a_project_file() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        let nested = dir.path().join("a/b");
+        fs::create_dir_all(&nested)?;
+        assert_eq!(Config::find_project_file(&nested), None);
+        fs::write(dir.path().join("a").join(PROJECT_FILE), "")?;
+        assert_eq!(
+            Config::find_project_file(&nested),
+            Some(dir.path().join("a").join(PROJECT_FILE))
+        );
+
+        Ok(())
+    }
+}
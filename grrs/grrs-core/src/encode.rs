@@ -0,0 +1,181 @@
+use crate::error::GrrsError;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// An encoding to write text in, for tools that don't read UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// UTF-16 starting with a little-endian byte order mark, as Windows
+    /// tools call Unicode
+    Utf16Le,
+    /// UTF-16 starting with a big-endian byte order mark
+    Utf16Be,
+    /// ISO-8859-1, with `?` in place of the characters it has no byte for
+    Latin1,
+}
+
+impl FromStr for Encoding {
+    type Err = GrrsError;
+
+    fn from_str(input: &str) -> Result<Encoding, GrrsError> {
+        match input.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "utf-16le" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf-16be" | "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "unknown encoding `{}`, expected `utf-8`, `utf-16le`, `utf-16be` or `latin1`",
+                input
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin1",
+        })
+    }
+}
+
+/// Writes UTF-8 text to another writer in another encoding, as it's written.
+/// A character cut off at the end of one write is finished by the next, and
+/// bytes that aren't UTF-8 are written as U+FFFD.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::encode::{Encoding, EncodingWriter};
+/// use std::io::Write;
+/// # fn main() -> Result<(), Error> {
+/// let mut writer = EncodingWriter::new(Vec::new(), Encoding::Utf16Le);
+/// writer.write_all("hé".as_bytes())?;
+/// assert_eq!(writer.into_inner(), b"\xff\xfeh\0\xe9\0");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EncodingWriter<W: Write> {
+    inner: W,
+    encoding: Encoding,
+    /// The start of a character cut off at the end of the last write
+    carry: Vec<u8>,
+    /// Whether anything has been written yet, before which UTF-16 gets its
+    /// byte order mark
+    started: bool,
+}
+
+impl<W: Write> EncodingWriter<W> {
+    pub fn new(inner: W, encoding: Encoding) -> Self {
+        EncodingWriter {
+            inner,
+            encoding,
+            carry: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Returns the inner writer, dropping any character left unfinished.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Encodes text, starting UTF-16 with its byte order mark.
+    fn encode(&mut self, text: &str, encoded: &mut Vec<u8>) {
+        if !self.started {
+            self.started = true;
+            match self.encoding {
+                Encoding::Utf16Le => encoded.extend(b"\xff\xfe"),
+                Encoding::Utf16Be => encoded.extend(b"\xfe\xff"),
+                Encoding::Utf8 | Encoding::Latin1 => {}
+            }
+        }
+        match self.encoding {
+            Encoding::Utf8 => encoded.extend(text.as_bytes()),
+            Encoding::Utf16Le => encoded.extend(text.encode_utf16().flat_map(u16::to_le_bytes)),
+            Encoding::Utf16Be => encoded.extend(text.encode_utf16().flat_map(u16::to_be_bytes)),
+            Encoding::Latin1 => {
+                encoded.extend((text.chars()).map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?')))
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for EncodingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut bytes = std::mem::take(&mut self.carry);
+        bytes.extend_from_slice(buf);
+        let mut encoded = Vec::with_capacity(bytes.len() * 2);
+        let mut rest = &bytes[..];
+        while !rest.is_empty() {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    self.encode(text, &mut encoded);
+                    rest = &[];
+                }
+                Err(err) => {
+                    let (valid, after) = rest.split_at(err.valid_up_to());
+                    // Checked as UTF-8 just above.
+                    self.encode(std::str::from_utf8(valid).unwrap(), &mut encoded);
+                    match err.error_len() {
+                        Some(len) => {
+                            self.encode("\u{FFFD}", &mut encoded);
+                            rest = &after[len..];
+                        }
+                        None => {
+                            self.carry = after.to_vec();
+                            rest = &[];
+                        }
+                    }
+                }
+            }
+        }
+        self.inner.write_all(&encoded)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_characters_split_across_writes() -> io::Result<()> {
+        let text = "naïve €5\n".as_bytes();
+        for (encoding, expected) in [
+            (Encoding::Utf8, "naïve €5\n".as_bytes().to_vec()),
+            (Encoding::Latin1, b"na\xefve ?5\n".to_vec()),
+            (
+                Encoding::Utf16Be,
+                b"\xfe\xff\0n\0a\0\xef\0v\0e\0 \x20\xac\x005\0\n".to_vec(),
+            ),
+        ] {
+            let mut writer = EncodingWriter::new(Vec::new(), encoding);
+            for byte in text {
+                writer.write_all(&[*byte])?;
+            }
+            assert_eq!(writer.into_inner(), expected, "{}", encoding);
+        }
+        let mut writer = EncodingWriter::new(Vec::new(), Encoding::Latin1);
+        writer.write_all(b"a\xffb")?;
+        assert_eq!(writer.into_inner(), b"a?b");
+        assert_eq!("UTF-16LE".parse::<Encoding>().ok(), Some(Encoding::Utf16Le));
+        assert!("ebcdic".parse::<Encoding>().is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+use alloc::string::{String, ToString};
+#[cfg(feature = "std")]
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The errors the library can return, so callers can react to each kind
+/// programmatically.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::RegexMatcher;
+/// use grrs_core::GrrsError;
+/// match RegexMatcher::new("(unclosed", false) {
+///     Err(GrrsError::InvalidPattern { pattern, .. }) => assert_eq!(pattern, "(unclosed"),
+///     _ => panic!("expected an invalid pattern"),
+/// }
+/// ```
+#[derive(Debug, Error)]
+pub enum GrrsError {
+    /// Reading input or writing output failed
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// A file couldn't be opened for searching
+    #[cfg(feature = "std")]
+    #[error("{}", describe_open(.path, *.failure, .source))]
+    Open {
+        path: PathBuf,
+        failure: OpenFailure,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A pattern, or an expression used to find timestamps, didn't compile
+    #[error("invalid pattern `{pattern}`: {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    /// A line of input wasn't valid UTF-8
+    #[error("line {line_number} is not valid UTF-8")]
+    EncodingError { line_number: u64 },
+
+    /// A date or date and time couldn't be parsed
+    #[error("could not parse `{0}` as a date or date and time")]
+    InvalidTimestamp(String),
+
+    /// An option value was malformed
+    #[error("{0}")]
+    InvalidArgument(String),
+
+    /// A configuration file couldn't be parsed
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+}
+
+/// Why a file couldn't be opened, as reported by grrs_core::GrrsError::Open.
+/// Parses from `missing`, `denied`, `directory` or `binary`, for choosing what
+/// to do about each.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenFailure {
+    /// Nothing exists at the path
+    NotFound,
+    /// The file exists but isn't readable
+    PermissionDenied,
+    /// The path is a directory rather than a file
+    IsADirectory,
+    /// The file holds something other than text, such as an executable or an
+    /// image, as told by grrs_core::sniff::ContentType
+    Binary,
+    /// Anything else, described by the underlying error
+    Other,
+}
+
+#[cfg(feature = "std")]
+impl FromStr for OpenFailure {
+    type Err = GrrsError;
+
+    fn from_str(input: &str) -> Result<OpenFailure, GrrsError> {
+        match input {
+            "missing" => Ok(OpenFailure::NotFound),
+            "denied" => Ok(OpenFailure::PermissionDenied),
+            "directory" => Ok(OpenFailure::IsADirectory),
+            "binary" => Ok(OpenFailure::Binary),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "unknown kind of file error `{}`, expected `missing`, `denied`, `directory` or \
+                 `binary`",
+                input
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn describe_open(path: &Path, failure: OpenFailure, source: &std::io::Error) -> String {
+    let path = crate::display_path(path);
+    let path = path.display();
+    match failure {
+        OpenFailure::NotFound => format!("`{}` does not exist", path),
+        OpenFailure::PermissionDenied => format!("permission denied reading `{}`", path),
+        OpenFailure::IsADirectory => format!("`{}` is a directory", path),
+        OpenFailure::Binary => format!("`{}` is {}, not text", path, source),
+        OpenFailure::Other => format!("could not read file `{}`: {}", path, source),
+    }
+}
+
+/// A result whose error defaults to grrs_core::GrrsError.
+pub type Result<T, E = GrrsError> = core::result::Result<T, E>;
+
+impl GrrsError {
+    /// Describes why a pattern didn't compile.
+    pub(crate) fn invalid_pattern(pattern: &str, reason: impl ToString) -> GrrsError {
+        GrrsError::InvalidPattern {
+            pattern: pattern.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Attributes an error opening a file to its path, classifying why it
+    /// happened.
+    #[cfg(feature = "fs")]
+    pub fn opening(path: &Path, source: std::io::Error) -> GrrsError {
+        use std::io::ErrorKind;
+        let failure = match source.kind() {
+            ErrorKind::NotFound => OpenFailure::NotFound,
+            ErrorKind::IsADirectory => OpenFailure::IsADirectory,
+            // Windows denies opening directories as files.
+            ErrorKind::PermissionDenied if path.is_dir() => OpenFailure::IsADirectory,
+            ErrorKind::PermissionDenied => OpenFailure::PermissionDenied,
+            _ => OpenFailure::Other,
+        };
+
+        GrrsError::Open {
+            path: path.to_path_buf(),
+            failure,
+            source,
+        }
+    }
+
+    /// Rejects a file holding something other than text, naming what it holds.
+    #[cfg(feature = "fs")]
+    pub(crate) fn binary(path: &Path, content: crate::sniff::ContentType) -> GrrsError {
+        GrrsError::Open {
+            path: path.to_path_buf(),
+            failure: OpenFailure::Binary,
+            source: std::io::Error::new(std::io::ErrorKind::InvalidData, content.to_string()),
+        }
+    }
+
+    /// Returns why a file couldn't be opened, if that's what went wrong.
+    #[cfg(feature = "std")]
+    pub fn open_failure(&self) -> Option<OpenFailure> {
+        match self {
+            GrrsError::Open { failure, .. } => Some(*failure),
+            _ => None,
+        }
+    }
+
+    /// Returns the file that couldn't be opened, if that's what went wrong.
+    #[cfg(feature = "std")]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            GrrsError::Open { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Attributes an error reading a line to that line, reporting invalid
+    /// UTF-8 as an encoding error.
+    #[cfg(feature = "std")]
+    pub(crate) fn reading(err: std::io::Error, line_number: u64) -> GrrsError {
+        match err.kind() {
+            std::io::ErrorKind::InvalidData => GrrsError::EncodingError { line_number },
+            _ => GrrsError::Io(err),
+        }
+    }
+}
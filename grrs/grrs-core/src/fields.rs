@@ -0,0 +1,466 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::{LiteralMatcher, Matcher};
+#[cfg(feature = "regex")]
+use regex::Regex;
+use serde_json::Value;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Structured log formats that lines can be parsed into fields from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineFormat {
+    /// `key=value key2="quoted value"` pairs
+    Logfmt,
+    /// RFC 3164 or RFC 5424 syslog messages, with the `regex` feature
+    #[cfg(feature = "regex")]
+    Syslog,
+    /// One JSON value per line, with fields named by paths such as `.user.id`
+    Json,
+}
+
+impl FromStr for LineFormat {
+    type Err = GrrsError;
+
+    fn from_str(input: &str) -> Result<LineFormat, GrrsError> {
+        match input {
+            "logfmt" => Ok(LineFormat::Logfmt),
+            #[cfg(feature = "regex")]
+            "syslog" => Ok(LineFormat::Syslog),
+            #[cfg(not(feature = "regex"))]
+            "syslog" => Err(GrrsError::InvalidArgument(
+                "syslog parsing requires the `regex` feature".to_string(),
+            )),
+            "json" => Ok(LineFormat::Json),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "unknown format `{}`, expected `logfmt`, `syslog` or `json`",
+                input
+            ))),
+        }
+    }
+}
+
+/// The named fields parsed out of a single line, in the order they appeared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields {
+    pairs: Vec<(String, String)>,
+}
+
+impl Fields {
+    /// Returns the value of the first field with the supplied name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns all parsed fields as name and value pairs.
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+
+    fn push(&mut self, name: &str, value: &str) {
+        self.pairs.push((name.to_string(), value.to_string()));
+    }
+}
+
+/// Syslog severity keywords, indexed by the severity part of the priority value.
+#[cfg(feature = "regex")]
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// Parses lines of a given structured format into their fields.
+///
+/// Syslog lines yield `timestamp`, `host`, `app`, `pid` and `message` fields,
+/// plus `facility` and `severity` when a `<PRI>` header is present. JSON lines
+/// yield one field per value in the document, named by its path from the root
+/// (`.`, `.level`, `.user.id`, `.tags[0]`); strings are unquoted and any other
+/// value is its compact JSON text.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::fields::{FieldParser, LineFormat};
+/// # fn main() -> Result<(), Error> {
+/// let parser = FieldParser::new(LineFormat::Logfmt);
+/// let fields = parser.parse(r#"level=error msg="disk full" retry"#).unwrap();
+/// assert_eq!(fields.get("level"), Some("error"));
+/// assert_eq!(fields.get("msg"), Some("disk full"));
+/// assert_eq!(fields.get("retry"), Some("true"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FieldParser {
+    format: LineFormat,
+    #[cfg(feature = "regex")]
+    rfc5424: Regex,
+    #[cfg(feature = "regex")]
+    rfc3164: Regex,
+}
+
+impl FieldParser {
+    /// Creates a parser for the supplied format.
+    #[cfg(feature = "regex")]
+    pub fn new(format: LineFormat) -> FieldParser {
+        // Both expressions are fixed and known to be valid.
+        let rfc5424 = Regex::new(
+            r"^<(\d{1,3})>(\d{1,2}) (\S+) (\S+) (\S+) (\S+) (\S+) (-|(?:\[[^\]]*\])+)(?: (.*))?$",
+        )
+        .unwrap();
+        let rfc3164 = Regex::new(
+            r"^(?:<(\d{1,3})>)?([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}) (\S+) ([^:\[\s]+)(?:\[(\d+)\])?: ?(.*)$",
+        )
+        .unwrap();
+
+        FieldParser {
+            format,
+            rfc5424,
+            rfc3164,
+        }
+    }
+
+    /// Creates a parser for the supplied format.
+    #[cfg(not(feature = "regex"))]
+    pub fn new(format: LineFormat) -> FieldParser {
+        FieldParser { format }
+    }
+
+    /// Parses a line into fields, or returns `None` if it isn't in the
+    /// parser's format.
+    pub fn parse(&self, line: &str) -> Option<Fields> {
+        match self.format {
+            LineFormat::Logfmt => parse_logfmt(line),
+            #[cfg(feature = "regex")]
+            LineFormat::Syslog => self.parse_syslog(line),
+            LineFormat::Json => parse_json(line),
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    fn parse_syslog(&self, line: &str) -> Option<Fields> {
+        let mut fields = Fields::default();
+        fn text<'t>(caps: &regex::Captures<'t>, i: usize) -> &'t str {
+            caps.get(i).map_or("", |m| m.as_str())
+        }
+        let pri = if let Some(caps) = self.rfc5424.captures(line) {
+            for (i, name) in ["timestamp", "host", "app", "pid", "msgid"]
+                .iter()
+                .enumerate()
+            {
+                fields.push(name, text(&caps, i + 3));
+            }
+            fields.push("message", text(&caps, 9));
+            caps.get(1).map(|m| m.as_str().to_string())
+        } else if let Some(caps) = self.rfc3164.captures(line) {
+            for (i, name) in ["timestamp", "host", "app", "pid", "message"]
+                .iter()
+                .enumerate()
+            {
+                fields.push(name, text(&caps, i + 2));
+            }
+            caps.get(1).map(|m| m.as_str().to_string())
+        } else {
+            return None;
+        };
+        if let Some(pri) = pri.and_then(|pri| pri.parse::<usize>().ok()) {
+            fields.push("facility", &(pri / 8).to_string());
+            fields.push("severity", SEVERITIES[pri % 8]);
+        }
+
+        Some(fields)
+    }
+}
+
+/// Parses `key=value` pairs, where values may be double-quoted with `\"`
+/// escapes and a bare key stands for `key=true`. Lines without a single
+/// `key=value` pair are not considered logfmt.
+fn parse_logfmt(line: &str) -> Option<Fields> {
+    let mut fields = Fields::default();
+    let mut has_pair = false;
+    let mut chars = line.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+        if chars.peek() != Some(&'=') {
+            fields.push(&key, "true");
+            continue;
+        }
+        chars.next();
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => value.extend(chars.next()),
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+        has_pair = true;
+        fields.push(&key, &value);
+    }
+
+    match has_pair {
+        false => None,
+        true => Some(fields),
+    }
+}
+
+/// Parses a line as a JSON document and flattens every value in it into a field
+/// named by its path.
+fn parse_json(line: &str) -> Option<Fields> {
+    fn flatten(path: String, value: &Value, fields: &mut Fields) {
+        match value {
+            Value::String(text) => fields.push(&path, text),
+            _ => fields.push(&path, &value.to_string()),
+        }
+        let prefix = path.trim_end_matches('.');
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    flatten(format!("{}.{}", prefix, key), child, fields);
+                }
+            }
+            Value::Array(items) => {
+                for (i, child) in items.iter().enumerate() {
+                    flatten(format!("{}[{}]", prefix, i), child, fields);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let value: Value = serde_json::from_str(line).ok()?;
+    let mut fields = Fields::default();
+    flatten(".".to_string(), &value, &mut fields);
+
+    Some(fields)
+}
+
+/// Normalizes a user-supplied JSON path so `level`, `.level` and `["level"]`
+/// style paths all name the field produced for `{"level": ...}`.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(grrs_core::fields::json_path("level"), ".level");
+/// assert_eq!(grrs_core::fields::json_path(".user.id"), ".user.id");
+/// assert_eq!(grrs_core::fields::json_path(r#".["user"].tags[0]"#), ".user.tags[0]");
+/// ```
+pub fn json_path(path: &str) -> String {
+    let path = path.replace("[\"", ".").replace("\"]", "");
+    let path = path.replace(".[", "[").replace("..", ".");
+    match path.starts_with('.') {
+        true => path,
+        false => format!(".{}", path),
+    }
+}
+
+/// A `field=value` condition that a parsed line must satisfy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub field: String,
+    pub value: String,
+}
+
+impl FromStr for Condition {
+    type Err = GrrsError;
+
+    fn from_str(input: &str) -> Result<Condition, GrrsError> {
+        match input.split_once('=') {
+            Some((field, value)) if !field.is_empty() => Ok(Condition {
+                field: field.to_string(),
+                value: value.to_string(),
+            }),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "expected `field=value`, found `{}`",
+                input
+            ))),
+        }
+    }
+}
+
+/// Decides whether structured lines match a pattern, optionally matching the
+/// pattern against a single field rather than the whole line.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::fields::{FieldFilter, LineFormat};
+/// # fn main() -> Result<(), Error> {
+/// let conditions = vec!["level=error".parse()?];
+/// let filter = FieldFilter::new(LineFormat::Logfmt, conditions, Some("msg".to_string()));
+/// assert!(filter.matches("level=error msg=\"disk full\"", "disk"));
+/// assert!(!filter.matches("level=info msg=\"disk full\"", "disk"));
+/// assert!(!filter.matches("level=error msg=timeout disk=sda", "disk"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FieldFilter {
+    parser: FieldParser,
+    conditions: Vec<Condition>,
+    field: Option<String>,
+}
+
+impl FieldFilter {
+    /// Creates a filter requiring every condition to hold, matching the
+    /// pattern against `field` if supplied and against the whole line if not.
+    pub fn new(format: LineFormat, conditions: Vec<Condition>, field: Option<String>) -> Self {
+        FieldFilter {
+            parser: FieldParser::new(format),
+            conditions,
+            field,
+        }
+    }
+
+    /// Finds a match in a line that parses and satisfies the conditions. Lines
+    /// that don't parse never match. When matching against a field, the range
+    /// is offset to where the field's value appears verbatim in the line, or
+    /// covers the whole line if it doesn't (such as for escaped values).
+    pub fn find(&self, line: &str, matcher: &dyn Matcher) -> Option<Range<usize>> {
+        let fields = self.parser.parse(line)?;
+        let satisfied = self
+            .conditions
+            .iter()
+            .all(|condition| fields.get(&condition.field) == Some(condition.value.as_str()));
+        if !satisfied {
+            return None;
+        }
+        match &self.field {
+            None => matcher.find(line),
+            Some(field) => {
+                let value = fields.get(field)?;
+                let found = matcher.find(value)?;
+                Some(match line.find(value) {
+                    Some(offset) => offset + found.start..offset + found.end,
+                    None => 0..line.len(),
+                })
+            }
+        }
+    }
+
+    /// Reports whether the line parses, satisfies the conditions and contains
+    /// the pattern.
+    pub fn matches(&self, line: &str, pattern: &str) -> bool {
+        self.find(line, &LiteralMatcher::new(pattern)).is_some()
+    }
+}
+
+/// A grrs_core::matcher::Matcher applying another matcher through a FieldFilter, so
+/// structured matching plugs into anything that accepts a matcher.
+#[derive(Debug)]
+pub struct FieldMatcher<M> {
+    filter: FieldFilter,
+    matcher: M,
+}
+
+impl<M: Matcher> FieldMatcher<M> {
+    /// Combines a filter with the matcher used for the pattern.
+    pub fn new(filter: FieldFilter, matcher: M) -> FieldMatcher<M> {
+        FieldMatcher { filter, matcher }
+    }
+}
+
+impl<M: Matcher> Matcher for FieldMatcher<M> {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.filter.find(line, &self.matcher)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        Box::new(self.find(line).into_iter())
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        self.find_iter(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_logfmt_fields() {
+        let parser = FieldParser::new(LineFormat::Logfmt);
+        let fields = parser
+            .parse(r#"ts=2024-01-01 msg="say \"hi\"" debug"#)
+            .unwrap();
+        assert_eq!(fields.get("ts"), Some("2024-01-01"));
+        assert_eq!(fields.get("msg"), Some(r#"say "hi""#));
+        assert_eq!(fields.get("debug"), Some("true"));
+        assert_eq!(parser.parse("just some words"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn parse_syslog_fields() {
+        let parser = FieldParser::new(LineFormat::Syslog);
+        let fields = parser
+            .parse("<11>Jan  5 10:00:00 web01 nginx[123]: upstream timed out")
+            .unwrap();
+        assert_eq!(fields.get("host"), Some("web01"));
+        assert_eq!(fields.get("app"), Some("nginx"));
+        assert_eq!(fields.get("pid"), Some("123"));
+        assert_eq!(fields.get("severity"), Some("err"));
+        assert_eq!(fields.get("message"), Some("upstream timed out"));
+
+        let fields = parser
+            .parse("<165>1 2024-01-01T00:00:00Z host app 42 ID47 - started")
+            .unwrap();
+        assert_eq!(fields.get("timestamp"), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(fields.get("severity"), Some("notice"));
+        assert_eq!(fields.get("message"), Some("started"));
+    }
+
+    #[test]
+    fn parse_json_fields() {
+        let parser = FieldParser::new(LineFormat::Json);
+        let fields = parser
+            .parse(r#"{"level":"error","user":{"id":7},"tags":["a","b"]}"#)
+            .unwrap();
+        assert_eq!(fields.get(".level"), Some("error"));
+        assert_eq!(fields.get(".user.id"), Some("7"));
+        assert_eq!(fields.get(".user"), Some(r#"{"id":7}"#));
+        assert_eq!(fields.get(".tags[1]"), Some("b"));
+        assert_eq!(parser.parse("level=error not json"), None);
+    }
+
+    #[test]
+    fn parse_a_condition() -> Result<(), GrrsError> {
+        let condition: Condition = "level=error".parse()?;
+        assert_eq!(condition.field, "level");
+        assert_eq!(condition.value, "error");
+        assert!("=error".parse::<Condition>().is_err());
+
+        Ok(())
+    }
+}
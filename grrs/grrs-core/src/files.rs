@@ -0,0 +1,684 @@
+use crate::error::{GrrsError, OpenFailure, Result};
+use crate::open_input_in;
+use crate::pool::BufferPool;
+use crate::search::SearchSummary;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// What searching several files found: a summary for each file that was
+/// searched, and the error for each that couldn't be, so one unreadable file
+/// doesn't lose the results of the others.
+#[derive(Debug, Default)]
+pub struct FileResults {
+    pub searched: Vec<(PathBuf, SearchSummary)>,
+    /// The files that couldn't be searched, to be reported
+    pub errors: Vec<(PathBuf, GrrsError)>,
+    /// The files that couldn't be opened and were skipped as asked
+    pub skipped: Vec<(PathBuf, GrrsError)>,
+    /// Whether the search stopped at the last of the errors, as asked
+    pub aborted: bool,
+}
+
+impl FileResults {
+    /// Returns the files that had at least one match, in the order searched.
+    pub fn matched(&self) -> impl Iterator<Item = &Path> {
+        (self.searched.iter())
+            .filter(|(_, summary)| summary.found())
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// Reports whether every file was searched, other than those skipped.
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Files away what searching a file came to, returning whether the policy
+    /// says to stop there.
+    fn record(
+        &mut self,
+        path: PathBuf,
+        result: Result<SearchSummary, GrrsError>,
+        policy: &FilePolicy,
+    ) -> bool {
+        let err = match result {
+            Ok(summary) => {
+                self.searched.push((path, summary));
+                return false;
+            }
+            Err(err) => err,
+        };
+        let failure = err.open_failure();
+        if failure.is_some_and(|failure| policy.skip.contains(&failure)) {
+            self.skipped.push((path, err));
+            return false;
+        }
+        self.errors.push((path, err));
+        let too_many = (policy.max_errors).is_some_and(|max| self.errors.len() > max);
+        self.aborted = too_many || failure.is_some_and(|failure| policy.abort.contains(&failure));
+        self.aborted
+    }
+}
+
+/// What grrs_core::search_files() does about files that can't be opened, by
+/// why they couldn't be: skip them quietly, stop searching, or by default note
+/// the error and carry on, until too many have failed. It also sets how big a
+/// buffer files are read through, and the grrs_core::BufferPool it's taken from.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::{FilePolicy, OpenFailure};
+/// let policy = FilePolicy::default()
+///     .skip(OpenFailure::IsADirectory)
+///     .abort_on(OpenFailure::PermissionDenied)
+///     .max_errors(10);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilePolicy {
+    skip: Vec<OpenFailure>,
+    abort: Vec<OpenFailure>,
+    max_errors: Option<usize>,
+    buffer_size: BufferSize,
+    pool: BufferPool,
+}
+
+impl FilePolicy {
+    /// Skips files that fail to open for this reason.
+    pub fn skip(mut self, failure: OpenFailure) -> Self {
+        self.skip.push(failure);
+        self
+    }
+
+    /// Stops searching at the first file that fails to open for this reason.
+    pub fn abort_on(mut self, failure: OpenFailure) -> Self {
+        self.abort.push(failure);
+        self
+    }
+
+    /// Stops searching once more than this many files couldn't be searched,
+    /// not counting those skipped.
+    pub fn max_errors(mut self, max: usize) -> Self {
+        self.max_errors = Some(max);
+        self
+    }
+
+    /// Reads files through buffers of this size.
+    pub fn buffer_size(mut self, size: BufferSize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Takes the buffers files are read through from this pool, rather than
+    /// one of the policy's own.
+    pub fn pool(mut self, pool: BufferPool) -> Self {
+        self.pool = pool;
+        self
+    }
+}
+
+/// How big a buffer files are read through. Parses from `auto` or a number of
+/// bytes, optionally with a `K` or `M` suffix for KiB or MiB.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::BufferSize;
+/// assert_eq!("64K".parse::<BufferSize>()?, BufferSize::Fixed(64 * 1024));
+/// assert_eq!(BufferSize::Adaptive.for_len(100), BufferSize::MIN);
+/// assert_eq!(BufferSize::Adaptive.for_len(100 << 20), BufferSize::MAX);
+/// # Ok::<(), grrs_core::GrrsError>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BufferSize {
+    /// Sized for each file: as small as a small file, so searching many of
+    /// them doesn't set aside far more memory than they hold, and up to
+    /// BufferSize::MAX for big ones, which are read in fewer, longer reads
+    #[default]
+    Adaptive,
+    /// The same number of bytes for every file
+    Fixed(usize),
+}
+
+impl BufferSize {
+    /// The smallest buffer chosen for a file, a page on most systems
+    pub const MIN: usize = 4 * 1024;
+    /// The largest buffer chosen for a file
+    pub const MAX: usize = 1024 * 1024;
+
+    /// Returns the capacity to read a file of this many bytes through.
+    pub fn for_len(self, len: u64) -> usize {
+        match self {
+            BufferSize::Fixed(size) => size.max(1),
+            // One more byte than the file holds finds its end in one read.
+            BufferSize::Adaptive => (usize::try_from(len.saturating_add(1)).unwrap_or(usize::MAX))
+                .min(Self::MAX)
+                .next_power_of_two()
+                .max(Self::MIN),
+        }
+    }
+}
+
+impl FromStr for BufferSize {
+    type Err = GrrsError;
+
+    fn from_str(input: &str) -> Result<BufferSize, GrrsError> {
+        if input == "auto" {
+            return Ok(BufferSize::Adaptive);
+        }
+        let (digits, unit) = match input.strip_suffix(['K', 'k']) {
+            Some(digits) => (digits, 1024),
+            None => match input.strip_suffix(['M', 'm']) {
+                Some(digits) => (digits, 1024 * 1024),
+                None => (input, 1),
+            },
+        };
+        match digits
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+        {
+            Some(size) if size > 0 => Ok(BufferSize::Fixed(size)),
+            _ => Err(GrrsError::InvalidArgument(format!(
+                "invalid buffer size `{}`, expected `auto` or a number of bytes such as `64K`",
+                input
+            ))),
+        }
+    }
+}
+
+/// Opens each file in turn with grrs_core::open_input() and searches it with the
+/// supplied function, carrying on past files that can't be opened or read
+/// unless the policy says otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sink::CollectSink;
+/// use grrs_core::Searcher;
+/// use std::io::Write;
+/// # fn main() -> Result<(), Error> {
+/// let mut file = tempfile::NamedTempFile::new()?;
+/// writeln!(file, "lorem ipsum")?;
+/// let searcher = Searcher::default();
+/// let matcher = searcher.literal("lorem");
+/// let paths = [file.path(), "missing.txt".as_ref()];
+/// let results = grrs_core::search_files(&paths, &Default::default(), |_, reader| {
+///     searcher.search_sink(&matcher, reader, &mut CollectSink::default())
+/// });
+/// assert_eq!(results.matched().collect::<Vec<_>>(), [file.path()]);
+/// assert_eq!(results.errors[0].0, std::path::Path::new("missing.txt"));
+/// # Ok(())
+/// # }
+/// ```
+pub fn search_files<P, F>(
+    paths: impl IntoIterator<Item = P>,
+    policy: &FilePolicy,
+    mut search: F,
+) -> FileResults
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, Box<dyn BufRead>) -> Result<SearchSummary, GrrsError>,
+{
+    let mut results = FileResults::default();
+    for path in paths {
+        let path = path.as_ref();
+        let result = open_input_in(path, policy.buffer_size, &policy.pool)
+            .and_then(|reader| search(path, reader));
+        if results.record(path.to_path_buf(), result, policy) {
+            break;
+        }
+    }
+
+    results
+}
+
+/// Lists the files under each path in turn, descending into directories in
+/// order of name, so the order is the same on every run. Paths that aren't
+/// directories are listed as they are, and directories that can't be read
+/// are listed as errors.
+///
+/// Links to directories are only followed when given as paths, so links can't
+/// lead the walk around in circles, and are left out otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("b"))?;
+/// std::fs::write(dir.path().join("b/c.txt"), "")?;
+/// std::fs::write(dir.path().join("a.txt"), "")?;
+/// let files = grrs_core::walk([dir.path()]).collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(files, [dir.path().join("a.txt"), dir.path().join("b/c.txt")]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn walk<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Walk {
+    let mut pending: Vec<_> = (paths.into_iter())
+        .map(|path| (path.as_ref().to_path_buf(), true))
+        .collect();
+    pending.reverse();
+    Walk { pending }
+}
+
+/// The files found by grrs_core::walk().
+#[derive(Debug)]
+pub struct Walk {
+    /// The paths still to list, last first, and whether each was given
+    pending: Vec<(PathBuf, bool)>,
+}
+
+impl Iterator for Walk {
+    type Item = Result<PathBuf, GrrsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, given) = self.pending.pop()?;
+            let is_link = !given
+                && std::fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_symlink());
+            match is_dir(&path) {
+                // Links to directories found in one are left out, not followed.
+                true if is_link => continue,
+                true => {}
+                false => return Some(Ok(path)),
+            }
+            let entries = std::fs::read_dir(&path).and_then(|entries| {
+                (entries.map(|entry| entry.map(|entry| entry.path())))
+                    .collect::<std::io::Result<Vec<_>>>()
+            });
+            match entries {
+                Ok(mut entries) => {
+                    entries.sort_unstable_by(|a, b| b.cmp(a));
+                    self.pending
+                        .extend(entries.into_iter().map(|entry| (entry, false)));
+                }
+                Err(err) => return Some(Err(GrrsError::opening(&path, err))),
+            }
+        }
+    }
+}
+
+/// A path for a worker of grrs_core::search_tree() to look at.
+struct Job {
+    path: PathBuf,
+    /// Whether the path was given, rather than found in a directory
+    given: bool,
+    is_dir: bool,
+}
+
+/// Searches the files under each path as grrs_core::search_files() does, with
+/// as many worker threads as asked for looking through directories and
+/// searching the files they find at once. Each worker takes the paths it finds
+/// itself first, and the oldest of another's when it runs out, so one big
+/// directory is shared out. Only the files `filter` accepts are searched, and
+/// directories that can't be read are reported, skipped or stopped at like
+/// files.
+///
+/// The results are in the order grrs_core::walk() lists the files, though
+/// which were searched before stopping, if the policy says to, is down to
+/// timing.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sink::CollectSink;
+/// use grrs_core::Searcher;
+/// use std::path::Path;
+/// # fn main() -> Result<(), Error> {
+/// let dir = tempfile::tempdir()?;
+/// std::fs::create_dir(dir.path().join("src"))?;
+/// std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n")?;
+/// std::fs::write(dir.path().join("notes.txt"), "fn main\n")?;
+/// let searcher = Searcher::default();
+/// let matcher = searcher.literal("main");
+/// let is_rust = |path: &Path| path.extension().is_some_and(|extension| extension == "rs");
+/// let results = grrs_core::search_tree([dir.path()], 4, &Default::default(), is_rust, |_, reader| {
+///     searcher.search_sink(&matcher, reader, &mut CollectSink::default())
+/// });
+/// assert_eq!(results.matched().collect::<Vec<_>>(), [dir.path().join("src/main.rs")]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn search_tree<P, F, S>(
+    paths: impl IntoIterator<Item = P>,
+    threads: usize,
+    policy: &FilePolicy,
+    filter: F,
+    search: S,
+) -> FileResults
+where
+    P: AsRef<Path>,
+    F: Fn(&Path) -> bool + Sync,
+    S: Fn(&Path, Box<dyn BufRead>) -> Result<SearchSummary, GrrsError> + Sync,
+{
+    let roots: Vec<_> = paths
+        .into_iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect();
+    let mut queues: Vec<_> = (0..threads.max(1)).map(|_| Mutex::default()).collect();
+    let given = roots.iter().rev().map(|path| Job {
+        path: path.clone(),
+        given: true,
+        is_dir: false,
+    });
+    *queues[0].get_mut().unwrap() = given.collect();
+    let tree = Tree {
+        queues,
+        // The jobs queued or being worked on, so idle workers know when
+        // there'll be no more.
+        pending: AtomicUsize::new(roots.len()),
+        stopped: AtomicBool::new(false),
+        wakes: Mutex::new(0),
+        woken: Condvar::new(),
+        results: Mutex::new(FileResults::default()),
+        policy,
+        filter,
+        search,
+    };
+    std::thread::scope(|scope| {
+        for worker in 0..tree.queues.len() {
+            let tree = &tree;
+            scope.spawn(move || tree.work(worker));
+        }
+    });
+
+    // Put the files back in the order they'd be walked in, which within each
+    // path is the order of their names, a component at a time.
+    let mut results = tree.results.into_inner().unwrap();
+    let key = |path: &Path| {
+        let root = roots.iter().position(|root| path.starts_with(root));
+        (root, path.to_path_buf())
+    };
+    results.searched.sort_by_cached_key(|(path, _)| key(path));
+    results.errors.sort_by_cached_key(|(path, _)| key(path));
+    results.skipped.sort_by_cached_key(|(path, _)| key(path));
+
+    results
+}
+
+/// What the workers of grrs_core::search_tree() share.
+struct Tree<'a, F, S> {
+    queues: Vec<Mutex<VecDeque<Job>>>,
+    pending: AtomicUsize,
+    /// Whether the policy said to stop
+    stopped: AtomicBool,
+    /// Counts the times there may be new jobs, none left or a stop, so idle
+    /// workers can sleep on woken until it changes
+    wakes: Mutex<u64>,
+    woken: Condvar,
+    results: Mutex<FileResults>,
+    policy: &'a FilePolicy,
+    filter: F,
+    search: S,
+}
+
+impl<F, S> Tree<'_, F, S>
+where
+    F: Fn(&Path) -> bool + Sync,
+    S: Fn(&Path, Box<dyn BufRead>) -> Result<SearchSummary, GrrsError> + Sync,
+{
+    /// Takes jobs from the worker's own queue, newest first, or else the
+    /// oldest of another's, until none are left or the search is stopped.
+    fn work(&self, worker: usize) {
+        let others = (1..self.queues.len()).map(|i| (worker + i) % self.queues.len());
+        loop {
+            if self.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            // Read before looking for a job, so a job queued while looking
+            // isn't slept through.
+            let seen = *self.wakes.lock().unwrap();
+            // The worker's own queue is unlocked before looking at others, so
+            // two workers can't wait on each other.
+            let own = self.queues[worker].lock().unwrap().pop_back();
+            let job = own.or_else(|| {
+                (others.clone()).find_map(|i| self.queues[i].lock().unwrap().pop_front())
+            });
+            match job {
+                Some(job) => {
+                    self.run(worker, job);
+                    if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        self.wake();
+                    }
+                }
+                None if self.pending.load(Ordering::SeqCst) == 0 => return,
+                None => {
+                    let mut wakes = self.wakes.lock().unwrap();
+                    while *wakes == seen {
+                        wakes = self.woken.wait(wakes).unwrap();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wakes the idle workers to look for jobs again.
+    fn wake(&self) {
+        *self.wakes.lock().unwrap() += 1;
+        self.woken.notify_all();
+    }
+
+    /// Searches a file, or queues what's in a directory for the workers.
+    fn run(&self, worker: usize, job: Job) {
+        // Links to directories are only followed when given, as in walk().
+        let is_dir = match job.given {
+            true => is_dir(&job.path),
+            false => job.is_dir,
+        };
+        let result = match is_dir {
+            false if !(self.filter)(&job.path) => return,
+            false => open_input_in(&job.path, self.policy.buffer_size, &self.policy.pool)
+                .and_then(|reader| (self.search)(&job.path, reader)),
+            true => match read_dir(&job.path) {
+                Ok(entries) => {
+                    self.pending.fetch_add(entries.len(), Ordering::SeqCst);
+                    self.queues[worker].lock().unwrap().extend(entries);
+                    self.wake();
+                    return;
+                }
+                Err(err) => Err(GrrsError::opening(&job.path, err)),
+            },
+        };
+        let mut results = self.results.lock().unwrap();
+        if !results.aborted && results.record(job.path, result, self.policy) {
+            self.stopped.store(true, Ordering::SeqCst);
+            self.wake();
+        }
+    }
+}
+
+/// Lists what's in a directory, newest job last, so the first by name is
+/// the next a worker takes. Links to directories are left out, as they
+/// aren't followed.
+fn read_dir(path: &Path) -> std::io::Result<Vec<Job>> {
+    #[cfg(feature = "trace")]
+    let _span = tracing::info_span!("walk", path = %path.display()).entered();
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() && is_dir(&entry.path()) {
+            continue;
+        }
+        entries.push(Job {
+            path: entry.path(),
+            given: false,
+            is_dir: file_type.is_dir(),
+        });
+    }
+    entries.sort_unstable_by(|a, b| b.path.cmp(&a.path));
+
+    Ok(entries)
+}
+
+/// Reports whether a path is a directory, following links.
+fn is_dir(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.is_dir())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::CollectSink;
+    use crate::Searcher;
+    use std::io::Write;
+
+    #[test]
+    fn carry_on_past_unreadable_files() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        let mut file = tempfile::NamedTempFile::new()?;
+        writeln!(file, "dolor sit amet")?;
+        let searcher = Searcher::default();
+        let matcher = searcher.literal("lorem");
+        let search =
+            |_: &Path, reader| searcher.search_sink(&matcher, reader, &mut CollectSink::default());
+        let results = search_files([dir.path(), file.path()], &FilePolicy::default(), search);
+        assert!(!results.is_complete());
+        assert_eq!(results.errors.len(), 1);
+        assert_eq!(results.errors[0].0, dir.path());
+        assert_eq!(results.searched.len(), 1);
+        assert_eq!(results.matched().count(), 0);
+
+        let missing = dir.path().join("missing.txt");
+        let policy = FilePolicy::default()
+            .skip(OpenFailure::IsADirectory)
+            .abort_on(OpenFailure::NotFound);
+        let paths = [dir.path(), &missing, file.path()];
+        let results = search_files(paths, &policy, search);
+        assert_eq!(results.skipped.len(), 1);
+        assert_eq!(results.errors[0].0, missing);
+        assert!(results.aborted);
+        assert!(results.searched.is_empty());
+
+        let policy = FilePolicy::default().max_errors(1);
+        let results = search_files([dir.path(), &missing, file.path()], &policy, search);
+        assert_eq!(results.errors.len(), 2);
+        assert!(results.aborted);
+        let results = search_files([dir.path(), file.path()], &policy, search);
+        assert!(!results.aborted);
+
+        Ok(())
+    }
+
+    #[test]
+    fn size_buffers_by_file() {
+        let adaptive = BufferSize::Adaptive;
+        assert_eq!(adaptive.for_len(0), BufferSize::MIN);
+        assert_eq!(adaptive.for_len(20_000), 32 * 1024);
+        assert_eq!(adaptive.for_len(u64::MAX), BufferSize::MAX);
+        assert_eq!(BufferSize::Fixed(0).for_len(20_000), 1);
+        assert_eq!("auto".parse::<BufferSize>().ok(), Some(adaptive));
+        assert_eq!(
+            "2m".parse::<BufferSize>().ok(),
+            Some(BufferSize::Fixed(2 << 20))
+        );
+        assert!("0".parse::<BufferSize>().is_err());
+        assert!("big".parse::<BufferSize>().is_err());
+    }
+
+    #[test]
+    fn walk_directories_in_order() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        for sub in ["b", "b/d", "c"] {
+            std::fs::create_dir(dir.path().join(sub))?;
+        }
+        for file in ["b/d/e.txt", "b/a.txt", "z.txt"] {
+            std::fs::write(dir.path().join(file), "")?;
+        }
+        let missing = dir.path().join("missing.txt");
+        let files = walk([dir.path(), &missing]).collect::<Result<Vec<_>, _>>()?;
+        let expected: Vec<_> = (["b/a.txt", "b/d/e.txt", "z.txt"].iter())
+            .map(|file| dir.path().join(file))
+            .chain(Some(missing))
+            .collect();
+        assert_eq!(files, expected);
+
+        // Links to directories in one are left out, and links to files kept.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("b"), dir.path().join("c/link"))?;
+            std::os::unix::fs::symlink(dir.path().join("z.txt"), dir.path().join("c/z.txt"))?;
+            let files = walk([dir.path().join("c")]).collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(files, [dir.path().join("c/z.txt")]);
+            let files = walk([dir.path().join("c/link")]).collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(files.len(), 2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_a_tree_at_once() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        for sub in 0..4 {
+            std::fs::create_dir(dir.path().join(sub.to_string()))?;
+            for file in 0..25 {
+                let path = dir.path().join(format!("{}/{}.txt", sub, file));
+                std::fs::write(path, if file % 3 == 0 { "lorem" } else { "ipsum" })?;
+            }
+        }
+        let missing = dir.path().join("missing");
+        let searcher = Searcher::default();
+        let matcher = searcher.literal("lorem");
+        let search =
+            |_: &Path, reader| searcher.search_sink(&matcher, reader, &mut CollectSink::default());
+        let roots = [&missing, dir.path()];
+        let pool = BufferPool::new();
+        let policy = FilePolicy::default().pool(pool.clone());
+        let results = search_tree(roots, 3, &policy, |_| true, search);
+        // Each worker had at most one file open at a time.
+        assert!((1..=3).contains(&pool.idle()));
+        let walked = walk(roots).filter_map(|path| path.ok());
+        let expected = search_files(walked, &FilePolicy::default(), search);
+        let paths = |results: &FileResults| -> Vec<PathBuf> {
+            (results.searched.iter())
+                .map(|(path, _)| path.clone())
+                .collect()
+        };
+        assert_eq!(paths(&results).len(), 100);
+        assert_eq!(paths(&results), paths(&expected));
+        assert_eq!(results.matched().count(), 36);
+        assert_eq!(results.errors[0].0, missing);
+
+        let policy = FilePolicy::default().abort_on(OpenFailure::NotFound);
+        let results = search_tree(roots, 3, &policy, |_| true, search);
+        assert!(results.aborted);
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn leave_out_links_to_directories_in_a_tree() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        std::fs::create_dir(dir.path().join("sub"))?;
+        std::fs::write(dir.path().join("sub/a.txt"), "lorem")?;
+        std::fs::write(dir.path().join("b.txt"), "lorem")?;
+        std::os::unix::fs::symlink(dir.path().join("sub"), dir.path().join("link"))?;
+        std::os::unix::fs::symlink(dir.path().join("b.txt"), dir.path().join("c.txt"))?;
+        let searcher = Searcher::default();
+        let matcher = searcher.literal("lorem");
+        let search =
+            |_: &Path, reader| searcher.search_sink(&matcher, reader, &mut CollectSink::default());
+        let results = search_tree([dir.path()], 2, &FilePolicy::default(), |_| true, search);
+        assert!(results.is_complete());
+        let matched: Vec<_> = results.matched().collect();
+        let expected = ["b.txt", "c.txt", "sub/a.txt"].map(|file| dir.path().join(file));
+        assert_eq!(matched, expected);
+
+        // Unless given as a path.
+        let link = dir.path().join("link");
+        let results = search_tree([&link], 2, &FilePolicy::default(), |_| true, search);
+        assert_eq!(results.matched().collect::<Vec<_>>(), [link.join("a.txt")]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,888 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::{Matcher, PatternSet};
+use crate::sink::SinkEvent;
+use serde_json::json;
+use std::io::Write;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Turns search events into bytes, keeping how results look separate from how
+/// they're found.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, JsonFormatter};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "found".into() };
+/// JsonFormatter.format(&event, &mut output)?;
+/// assert_eq!(output, b"{\"line\":\"found\",\"line_number\":3,\"type\":\"match\"}\n");
+/// # Ok(())
+/// # }
+/// ```
+pub trait Formatter {
+    /// Writes a single event.
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError>;
+
+    /// Writes anything held back once the last event has been formatted.
+    fn finish(&mut self, _writer: &mut dyn Write) -> Result<(), GrrsError> {
+        Ok(())
+    }
+}
+
+impl<F: Formatter + ?Sized> Formatter for &mut F {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        (**self).format(event, writer)
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        (**self).finish(writer)
+    }
+}
+
+impl<F: Formatter + ?Sized> Formatter for Box<F> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        (**self).format(event, writer)
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        (**self).finish(writer)
+    }
+}
+
+/// Writes `LINE# 4: text` for matches, `LINE# 3- text` for context and
+/// `LINE# 1= text` for headings, with `--` for breaks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LineFormatter;
+
+impl Formatter for LineFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                writeln!(writer, "LINE# {}: {}", line_number, line)?
+            }
+            SinkEvent::Context { line_number, line } => {
+                writeln!(writer, "LINE# {}- {}", line_number, line)?
+            }
+            SinkEvent::Break => writeln!(writer, "--")?,
+            SinkEvent::Heading { line_number, line } => {
+                writeln!(writer, "LINE# {}= {}", line_number, line)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// What a column counts, for grrs_core::format::column().
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColumnUnit {
+    /// User-perceived characters, so an emoji or a CJK character is one
+    /// column however many bytes it takes
+    #[default]
+    Graphemes,
+    /// Bytes, as some editors expect
+    Bytes,
+}
+
+/// Returns the column of a byte offset within a line, counted from 1. An
+/// offset inside a grapheme cluster is in that cluster's column.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::format::{column, ColumnUnit};
+/// assert_eq!(column("日本語 text", 10, ColumnUnit::Graphemes), 5);
+/// assert_eq!(column("日本語 text", 10, ColumnUnit::Bytes), 11);
+/// ```
+pub fn column(line: &str, offset: usize, unit: ColumnUnit) -> usize {
+    match unit {
+        ColumnUnit::Bytes => offset + 1,
+        ColumnUnit::Graphemes => {
+            let before = (line.grapheme_indices(true))
+                .take_while(|(start, grapheme)| start + grapheme.len() <= offset)
+                .count();
+            before + 1
+        }
+    }
+}
+
+/// Writes matches as `LINE# 4:7: text`, with the column of the first match,
+/// and everything else as grrs_core::format::LineFormatter does.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{ColumnFormatter, Formatter};
+/// use grrs_core::matcher::LiteralMatcher;
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let mut formatter = ColumnFormatter::new(LiteralMatcher::new("found"));
+/// let event = SinkEvent::Match { line_number: 3, line: "I found it".into() };
+/// formatter.format(&event, &mut output)?;
+/// assert_eq!(output, b"LINE# 3:3: I found it\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ColumnFormatter<M: Matcher> {
+    matcher: M,
+    unit: ColumnUnit,
+}
+
+impl<M: Matcher> ColumnFormatter<M> {
+    /// Creates a formatter, which finds columns with the matcher that was
+    /// searched with and counts them in grapheme clusters.
+    pub fn new(matcher: M) -> ColumnFormatter<M> {
+        ColumnFormatter {
+            matcher,
+            unit: ColumnUnit::default(),
+        }
+    }
+
+    /// Sets what columns count.
+    pub fn unit(mut self, unit: ColumnUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl<M: Matcher> Formatter for ColumnFormatter<M> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                let column = first_column(&self.matcher, line, self.unit);
+                writeln!(writer, "LINE# {}:{}: {}", line_number, column, line)?;
+            }
+            _ => LineFormatter.format(event, writer)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the column of the first match in a line, or 1 for the inverted
+/// matches that have no match to point at.
+fn first_column<M: Matcher>(matcher: &M, line: &str, unit: ColumnUnit) -> usize {
+    matcher
+        .find(line)
+        .map_or(1, |span| column(line, span.start, unit))
+}
+
+/// Writes each match within a matching line on a line of its own, as
+/// `LINE# 4: match`, leaving out context, breaks and headings.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, OnlyMatchingFormatter};
+/// use grrs_core::matcher::{LiteralMatcher, Overlapping};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let mut formatter = OnlyMatchingFormatter::new(Overlapping(LiteralMatcher::new("aa")));
+/// let event = SinkEvent::Match { line_number: 3, line: "baaa".into() };
+/// formatter.format(&event, &mut output)?;
+/// assert_eq!(output, b"LINE# 3: aa\nLINE# 3: aa\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct OnlyMatchingFormatter<M: Matcher> {
+    matcher: M,
+}
+
+impl<M: Matcher> OnlyMatchingFormatter<M> {
+    /// Creates a formatter finding matches with the matcher that was searched
+    /// with.
+    pub fn new(matcher: M) -> OnlyMatchingFormatter<M> {
+        OnlyMatchingFormatter { matcher }
+    }
+}
+
+impl<M: Matcher> Formatter for OnlyMatchingFormatter<M> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { line_number, line } = event {
+            for span in self.matcher.find_iter(line) {
+                writeln!(writer, "LINE# {}: {}", line_number, &line[span])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes each event as a JSON object on its own line, with a `type` of
+/// `match`, `context`, `break` or `heading`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        let value = match event {
+            SinkEvent::Match { line_number, line } => {
+                json!({ "type": "match", "line_number": line_number, "line": line })
+            }
+            SinkEvent::Context { line_number, line } => {
+                json!({ "type": "context", "line_number": line_number, "line": line })
+            }
+            SinkEvent::Break => json!({ "type": "break" }),
+            SinkEvent::Heading { line_number, line } => {
+                json!({ "type": "heading", "line_number": line_number, "line": line })
+            }
+        };
+        writeln!(writer, "{}", value)?;
+
+        Ok(())
+    }
+}
+
+/// Writes events as grrs_core::format::JsonFormatter does, adding to each
+/// match the ids of the patterns it matched, as `patterns`.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, PatternJsonFormatter};
+/// use grrs_core::matcher::{LiteralMatcher, PatternSet};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let patterns = PatternSet::new(vec![
+///     Box::new(LiteralMatcher::new("warn")),
+///     Box::new(LiteralMatcher::new("found")),
+/// ]);
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "found".into() };
+/// PatternJsonFormatter::new(&patterns).format(&event, &mut output)?;
+/// assert_eq!(
+///     output,
+///     b"{\"line\":\"found\",\"line_number\":3,\"patterns\":[1],\"type\":\"match\"}\n"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct PatternJsonFormatter<'a> {
+    patterns: &'a PatternSet,
+}
+
+impl PatternJsonFormatter<'_> {
+    /// Creates a formatter reporting which of the patterns searched for each
+    /// match is from.
+    pub fn new(patterns: &PatternSet) -> PatternJsonFormatter<'_> {
+        PatternJsonFormatter { patterns }
+    }
+}
+
+impl Formatter for PatternJsonFormatter<'_> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                let patterns = self.patterns.matching(line);
+                let value = json!({
+                    "type": "match",
+                    "line_number": line_number,
+                    "line": line,
+                    "patterns": patterns,
+                });
+                writeln!(writer, "{}", value)?;
+            }
+            _ => JsonFormatter.format(event, writer)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes each block of matches and their context as one JSON object on its
+/// own line, once the block ends, with the `start` and `end` line numbers it
+/// covers and its events as grrs_core::format::JsonFormatter writes them in
+/// `lines`. Blocks whose context overlaps or touches are searched as one, so
+/// each line is in a single region.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::RegionJsonFormatter;
+/// use grrs_core::matcher::LiteralMatcher;
+/// use grrs_core::sink::WriterSink;
+/// use grrs_core::Searcher;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let mut sink = WriterSink::with_formatter(&mut output, RegionJsonFormatter::default());
+/// let text = "x\nb\nx\nc\nd\ne\nx";
+/// let searcher = Searcher::builder().context(1).build();
+/// searcher.search_sink(&LiteralMatcher::new("x"), text.as_bytes(), &mut sink)?;
+/// drop(sink);
+/// let regions: Vec<serde_json::Value> = (output.split(|&byte| byte == b'\n'))
+///     .filter(|line| !line.is_empty())
+///     .map(serde_json::from_slice)
+///     .collect::<Result<_, _>>()?;
+/// assert_eq!((regions[0]["start"].as_u64(), regions[0]["end"].as_u64()), (Some(1), Some(4)));
+/// assert_eq!(regions[0]["lines"].as_array().map(Vec::len), Some(4));
+/// assert_eq!((regions[1]["start"].as_u64(), regions[1]["end"].as_u64()), (Some(6), Some(7)));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct RegionJsonFormatter {
+    /// The events of the region being read
+    lines: Vec<serde_json::Value>,
+    /// The first and last line numbers of the region's matches and context
+    range: Option<(u64, u64)>,
+}
+
+impl RegionJsonFormatter {
+    /// Writes the region read so far, if it has any lines, and starts another.
+    fn end(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        let lines = std::mem::take(&mut self.lines);
+        if let Some((start, end)) = self.range.take() {
+            let value = json!({ "type": "region", "start": start, "end": end, "lines": lines });
+            writeln!(writer, "{}", value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Formatter for RegionJsonFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        let (kind, line_number, line) = match event {
+            SinkEvent::Match { line_number, line } => ("match", line_number, line),
+            SinkEvent::Context { line_number, line } => ("context", line_number, line),
+            SinkEvent::Heading { line_number, line } => {
+                let value = json!({ "type": "heading", "line_number": line_number, "line": line });
+                self.lines.push(value);
+                return Ok(());
+            }
+            SinkEvent::Break => return self.end(writer),
+        };
+        self.range = match self.range {
+            Some((start, _)) => Some((start, *line_number)),
+            None => Some((*line_number, *line_number)),
+        };
+        let value = json!({ "type": kind, "line_number": line_number, "line": line });
+        self.lines.push(value);
+
+        Ok(())
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        self.end(writer)
+    }
+}
+
+/// The colors matches are highlighted in, as ANSI SGR parameters, given to
+/// each pattern in turn and starting over if there are more patterns.
+pub const PALETTE: [&str; 6] = ["1;31", "1;32", "1;33", "1;34", "1;35", "1;36"];
+
+/// Writes lines as grrs_core::format::LineFormatter does, highlighting each
+/// match for a terminal in the grrs_core::format::PALETTE color of its
+/// pattern.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{ColorFormatter, Formatter};
+/// use grrs_core::matcher::{LiteralMatcher, PatternSet};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let patterns = PatternSet::new(vec![
+///     Box::new(LiteralMatcher::new("I")),
+///     Box::new(LiteralMatcher::new("it")),
+/// ]);
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "I found it".into() };
+/// ColorFormatter::new(&patterns).format(&event, &mut output)?;
+/// assert_eq!(
+///     String::from_utf8(output)?,
+///     "LINE# 3: \x1b[1;31mI\x1b[0m found \x1b[1;32mit\x1b[0m\n"
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub struct ColorFormatter<'a> {
+    patterns: &'a PatternSet,
+}
+
+impl ColorFormatter<'_> {
+    /// Creates a formatter highlighting matches of the patterns searched for.
+    pub fn new(patterns: &PatternSet) -> ColorFormatter<'_> {
+        ColorFormatter { patterns }
+    }
+}
+
+impl Formatter for ColorFormatter<'_> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                write!(writer, "LINE# {}: ", line_number)?;
+                let mut written = 0;
+                for (id, span) in self.patterns.find_each(line) {
+                    // Overlapping matches only color what's left of them.
+                    if span.end <= written {
+                        continue;
+                    }
+                    let start = span.start.max(written);
+                    let color = PALETTE[id % PALETTE.len()];
+                    let (before, found) = (&line[written..start], &line[start..span.end]);
+                    write!(writer, "{}\x1b[{}m{}\x1b[0m", before, color, found)?;
+                    written = span.end;
+                }
+                writeln!(writer, "{}", &line[written..])?;
+            }
+            _ => LineFormatter.format(event, writer)?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes each match as `path:line:column:text`, for piping into fzf, where
+/// the column is that of the first match, counted in grapheme clusters unless
+/// set otherwise. Context and breaks are left out, and every line is flushed
+/// as soon as it's written.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, FzfFormatter};
+/// use grrs_core::matcher::LiteralMatcher;
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let mut formatter = FzfFormatter::new("notes.txt", LiteralMatcher::new("found"));
+/// let event = SinkEvent::Match { line_number: 3, line: "I found it".into() };
+/// formatter.format(&event, &mut output)?;
+/// assert_eq!(output, b"notes.txt:3:3:I found it\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct FzfFormatter<M: Matcher> {
+    path: String,
+    matcher: M,
+    unit: ColumnUnit,
+}
+
+impl<M: Matcher> FzfFormatter<M> {
+    /// Creates a formatter for matches in the file at `path`, which finds
+    /// columns with the matcher that was searched with.
+    pub fn new(path: impl Into<String>, matcher: M) -> FzfFormatter<M> {
+        FzfFormatter {
+            path: path.into(),
+            matcher,
+            unit: ColumnUnit::default(),
+        }
+    }
+
+    /// Sets what columns count.
+    pub fn unit(mut self, unit: ColumnUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl<M: Matcher> Formatter for FzfFormatter<M> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { line_number, line } = event {
+            let column = first_column(&self.matcher, line, self.unit);
+            writeln!(writer, "{}:{}:{}:{}", self.path, line_number, column, line)?;
+            writer.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes lines as `grep -Hn` would, `path:4:text` for matches and
+/// `path-3-text` for context, with `--` for breaks. Headings are written as
+/// `path=1=text`, as `git grep -p` does.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, GrepFormatter};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "found".into() };
+/// GrepFormatter::new("notes.txt").format(&event, &mut output)?;
+/// assert_eq!(output, b"notes.txt:3:found\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GrepFormatter {
+    path: String,
+}
+
+impl GrepFormatter {
+    /// Creates a formatter for lines of the file at `path`.
+    pub fn new(path: impl Into<String>) -> GrepFormatter {
+        GrepFormatter { path: path.into() }
+    }
+}
+
+impl Formatter for GrepFormatter {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        match event {
+            SinkEvent::Match { line_number, line } => {
+                writeln!(writer, "{}:{}:{}", self.path, line_number, line)?
+            }
+            SinkEvent::Context { line_number, line } => {
+                writeln!(writer, "{}-{}-{}", self.path, line_number, line)?
+            }
+            SinkEvent::Break => writeln!(writer, "--")?,
+            SinkEvent::Heading { line_number, line } => {
+                writeln!(writer, "{}={}={}", self.path, line_number, line)?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps another formatter to end each line it writes with `\r\n`, for output
+/// read on Windows.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{CrlfFormatter, Formatter, LineFormatter};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "found".into() };
+/// CrlfFormatter(LineFormatter).format(&event, &mut output)?;
+/// assert_eq!(output, b"LINE# 3: found\r\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrlfFormatter<F: Formatter>(pub F);
+
+impl<F: Formatter> Formatter for CrlfFormatter<F> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        self.0.format(event, &mut Crlf(writer))
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        self.0.finish(&mut Crlf(writer))
+    }
+}
+
+/// Writes through to another writer with every `\n` turned into `\r\n`.
+/// Lines never contain `\n`, so only terminators are changed.
+struct Crlf<'w>(&'w mut dyn Write);
+
+impl Write for Crlf<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for (i, part) in buf.split(|&byte| byte == b'\n').enumerate() {
+            if i > 0 {
+                self.0.write_all(b"\r\n")?;
+            }
+            self.0.write_all(part)?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Writes events as another formatter does, prefixing each line with the
+/// local time it was written, as `2024-01-02 10:30:00.250 `, for input such as
+/// a log being followed, whose lines may lack times of their own.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::format::{Formatter, LineFormatter, TimestampFormatter};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let event = SinkEvent::Match { line_number: 3, line: "found".into() };
+/// TimestampFormatter(LineFormatter).format(&event, &mut output)?;
+/// let output = String::from_utf8(output)?;
+/// assert!(output.ends_with(" LINE# 3: found\n"));
+/// assert_eq!(output.len(), "2024-01-02 10:30:00.250 LINE# 3: found\n".len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimestampFormatter<F: Formatter>(pub F);
+
+impl<F: Formatter> Formatter for TimestampFormatter<F> {
+    fn format(&mut self, event: &SinkEvent, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { .. } | SinkEvent::Context { .. } = event {
+            let now = chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+            write!(writer, "{} ", now)?;
+        }
+        self.0.format(event, writer)
+    }
+
+    fn finish(&mut self, writer: &mut dyn Write) -> Result<(), GrrsError> {
+        self.0.finish(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::LiteralMatcher;
+    use crate::sink::WriterSink;
+    use crate::Searcher;
+
+    #[test]
+    fn format_lines() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter = LineFormatter;
+        formatter.format(
+            &SinkEvent::Context {
+                line_number: 1,
+                line: "one".into(),
+            },
+            &mut output,
+        )?;
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        formatter.format(
+            &SinkEvent::Heading {
+                line_number: 3,
+                line: "fn three() {".into(),
+            },
+            &mut output,
+        )?;
+        assert_eq!(output, b"LINE# 1- one\n--\nLINE# 3= fn three() {\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_for_fzf() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter = FzfFormatter::new("a.log", crate::matcher::LiteralMatcher::new("x"));
+        for event in [
+            SinkEvent::Context {
+                line_number: 1,
+                line: "one".into(),
+            },
+            SinkEvent::Break,
+            SinkEvent::Match {
+                line_number: 4,
+                line: "no match".into(),
+            },
+            SinkEvent::Match {
+                line_number: 5,
+                line: "a: x".into(),
+            },
+        ] {
+            formatter.format(&event, &mut output)?;
+        }
+        assert_eq!(output, b"a.log:4:1:no match\na.log:5:4:a: x\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_like_grep() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter = GrepFormatter::new("src/a:b.rs");
+        for event in [
+            SinkEvent::Context {
+                line_number: 1,
+                line: "one".into(),
+            },
+            SinkEvent::Match {
+                line_number: 2,
+                line: "two: x".into(),
+            },
+            SinkEvent::Break,
+            SinkEvent::Heading {
+                line_number: 4,
+                line: "[four]".into(),
+            },
+        ] {
+            formatter.format(&event, &mut output)?;
+        }
+        assert_eq!(
+            output,
+            b"src/a:b.rs-1-one\nsrc/a:b.rs:2:two: x\n--\nsrc/a:b.rs=4=[four]\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_columns() {
+        let line = "e\u{301}🧑‍🔬 x";
+        assert_eq!(column(line, 0, ColumnUnit::Graphemes), 1);
+        assert_eq!(column(line, 1, ColumnUnit::Graphemes), 1);
+        assert_eq!(column(line, line.len() - 1, ColumnUnit::Graphemes), 4);
+        assert_eq!(column(line, line.len() - 1, ColumnUnit::Bytes), line.len());
+    }
+
+    #[test]
+    fn format_columns() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let matcher = crate::matcher::LiteralMatcher::new("x");
+        let mut formatter = ColumnFormatter::new(matcher).unit(ColumnUnit::Bytes);
+        for event in [
+            SinkEvent::Context {
+                line_number: 1,
+                line: "one".into(),
+            },
+            SinkEvent::Match {
+                line_number: 2,
+                line: "ü x".into(),
+            },
+        ] {
+            formatter.format(&event, &mut output)?;
+        }
+        assert_eq!(output, "LINE# 1- one\nLINE# 2:4: ü x\n".as_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_patterns() -> Result<(), GrrsError> {
+        use crate::matcher::LiteralMatcher;
+        let patterns = PatternSet::new(vec![
+            Box::new(LiteralMatcher::new("a")),
+            Box::new(LiteralMatcher::new("b")),
+        ]);
+        let event = SinkEvent::Match {
+            line_number: 7,
+            line: "bab".into(),
+        };
+        let mut output = Vec::new();
+        ColorFormatter::new(&patterns).format(&event, &mut output)?;
+        PatternJsonFormatter::new(&patterns).format(&event, &mut output)?;
+        PatternJsonFormatter::new(&patterns).format(&SinkEvent::Break, &mut output)?;
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "LINE# 7: \x1b[1;32mb\x1b[0m\x1b[1;31ma\x1b[0m\x1b[1;32mb\x1b[0m\n\
+             {\"line\":\"bab\",\"line_number\":7,\"patterns\":[0,1],\"type\":\"match\"}\n\
+             {\"type\":\"break\"}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_overlapping_matches() -> Result<(), GrrsError> {
+        use crate::matcher::{LiteralMatcher, Overlapping};
+        let patterns = PatternSet::new(vec![
+            Box::new(Overlapping(LiteralMatcher::new("aba"))),
+            Box::new(LiteralMatcher::new("b")),
+        ]);
+        let event = SinkEvent::Match {
+            line_number: 1,
+            line: "xababab".into(),
+        };
+        let mut output = Vec::new();
+        ColorFormatter::new(&patterns).format(&event, &mut output)?;
+        OnlyMatchingFormatter::new(&patterns).format(&event, &mut output)?;
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "LINE# 1: x\x1b[1;31maba\x1b[0m\x1b[1;31mba\x1b[0m\x1b[1;32mb\x1b[0m\n\
+             LINE# 1: aba\nLINE# 1: aba\nLINE# 1: b\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_with_crlf() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter = CrlfFormatter(JsonFormatter);
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        assert_eq!(output, b"{\"type\":\"break\"}\r\n{\"type\":\"break\"}\r\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_with_timestamps() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter = TimestampFormatter(LineFormatter);
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        formatter.format(
+            &SinkEvent::Context {
+                line_number: 1,
+                line: "one".into(),
+            },
+            &mut output,
+        )?;
+        let output = String::from_utf8_lossy(&output);
+        let (time, line) = output.strip_prefix("--\n").unwrap().split_at(23);
+        assert!(chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S%.3f").is_ok());
+        assert_eq!(line, " LINE# 1- one\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_json() -> Result<(), GrrsError> {
+        let mut output = Vec::new();
+        let mut formatter: Box<dyn Formatter> = Box::new(JsonFormatter);
+        formatter.format(
+            &SinkEvent::Context {
+                line_number: 2,
+                line: "a \"quoted\" line".into(),
+            },
+            &mut output,
+        )?;
+        formatter.format(&SinkEvent::Break, &mut output)?;
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "{\"line\":\"a \\\"quoted\\\" line\",\"line_number\":2,\"type\":\"context\"}\n\
+             {\"type\":\"break\"}\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_merged_regions() -> Result<(), GrrsError> {
+        let content = "a\nb\nmatch\nc\nd\nmatch\ne\nf\ng\nh\ni\nmatch";
+        let mut output = Vec::new();
+        let mut sink = WriterSink::with_formatter(&mut output, RegionJsonFormatter::default());
+        let searcher = Searcher::builder().context(2).build();
+        searcher.search_sink(&LiteralMatcher::new("match"), content.as_bytes(), &mut sink)?;
+        drop(sink);
+        let regions: Vec<serde_json::Value> = String::from_utf8_lossy(&output)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(regions.len(), 2);
+        let line_numbers = |region: &serde_json::Value| -> Vec<u64> {
+            (region["lines"].as_array().unwrap().iter())
+                .map(|line| line["line_number"].as_u64().unwrap())
+                .collect()
+        };
+        assert_eq!(
+            (&regions[0]["start"], &regions[0]["end"]),
+            (&json!(1), &json!(8))
+        );
+        assert_eq!(line_numbers(&regions[0]), (1..=8).collect::<Vec<_>>());
+        assert_eq!(line_numbers(&regions[1]), [10, 11, 12]);
+        assert_eq!(regions[1]["lines"][2]["type"], "match");
+
+        Ok(())
+    }
+}
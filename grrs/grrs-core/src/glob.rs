@@ -0,0 +1,125 @@
+use crate::error::{GrrsError, Result};
+use regex::Regex;
+use std::path::Path;
+
+/// A shell-style pattern for file names, such as `*.toml` or `*.{rs,md}`.
+///
+/// `*` and `?` stand for any run of characters and any one character within
+/// a name, `**` for anything at all, `[abc]` and `[!abc]` for any character
+/// in the set or out of it, and `{a,b}` for either alternative. A glob
+/// without a `/` is matched against file names, and one with a `/` against
+/// the end of the path, from the start of a component.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::glob::Glob;
+/// use std::path::Path;
+/// # fn main() -> Result<(), Error> {
+/// let glob = Glob::new("*.{toml,lock}")?;
+/// assert!(glob.is_match(Path::new("grrs/Cargo.toml")));
+/// assert!(!glob.is_match(Path::new("grrs/Cargo.toml.orig")));
+/// assert!(Glob::new("src/**/*.rs")?.is_match(Path::new("grrs/src/bin/main.rs")));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Glob {
+    regex: Regex,
+    whole_path: bool,
+}
+
+impl Glob {
+    /// Compiles a glob, failing if its sets or alternatives aren't closed.
+    pub fn new(glob: &str) -> Result<Glob, GrrsError> {
+        let whole_path = glob.contains('/');
+        let mut regex = String::from(if whole_path { "(?:^|/)" } else { "^" });
+        let mut chars = glob.chars().peekable();
+        let mut alternatives = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    regex.push_str(".*");
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => {
+                    regex.push('[');
+                    if chars.next_if_eq(&'!').is_some() {
+                        regex.push('^');
+                    }
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c @ ('\\' | '[' | '&' | '~')) => {
+                                regex.push('\\');
+                                regex.push(c);
+                            }
+                            Some(c) => regex.push(c),
+                            None => return Err(GrrsError::invalid_pattern(glob, "unclosed `[`")),
+                        }
+                    }
+                    regex.push(']');
+                }
+                '{' => {
+                    alternatives += 1;
+                    regex.push_str("(?:");
+                }
+                ',' if alternatives > 0 => regex.push('|'),
+                '}' if alternatives > 0 => {
+                    alternatives -= 1;
+                    regex.push(')');
+                }
+                c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        if alternatives > 0 {
+            return Err(GrrsError::invalid_pattern(glob, "unclosed `{`"));
+        }
+        regex.push('$');
+        let regex = Regex::new(&regex).map_err(|err| GrrsError::invalid_pattern(glob, err))?;
+
+        Ok(Glob { regex, whole_path })
+    }
+
+    /// Reports whether the glob matches a path, going by its file name alone
+    /// unless the glob has a `/`.
+    pub fn is_match(&self, path: &Path) -> bool {
+        match self.whole_path {
+            false => {
+                (path.file_name()).is_some_and(|name| self.regex.is_match(&name.to_string_lossy()))
+            }
+            true => {
+                let path = path.to_string_lossy().replace('\\', "/");
+                self.regex.is_match(&path)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_file_names() -> Result<(), GrrsError> {
+        let glob = Glob::new("[!.]?le*.t[xo]t")?;
+        assert!(glob.is_match(Path::new("a/file.txt")));
+        assert!(glob.is_match(Path::new("files.tot")));
+        assert!(!glob.is_match(Path::new(".le.txt")));
+        assert!(!glob.is_match(Path::new("file/a.txt")));
+
+        let glob = Glob::new("docs/*.md")?;
+        assert!(glob.is_match(Path::new("grrs/docs/a.md")));
+        assert!(!glob.is_match(Path::new("grrs/mydocs/a.md")));
+        assert!(!glob.is_match(Path::new("docs/a/b.md")));
+
+        assert!(Glob::new("a.(b)+")?.is_match(Path::new("a.(b)+")));
+        assert!(Glob::new("[ab").is_err());
+        assert!(Glob::new("{a,b").is_err());
+
+        Ok(())
+    }
+}
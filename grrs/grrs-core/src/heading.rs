@@ -0,0 +1,169 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::Matcher;
+use crate::search::{SearchSummary, Searcher};
+use crate::sink::{Sink, SinkEvent};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Default regular expression for the lines taken as headings: a function
+/// or type definition, a Markdown header or an INI section.
+pub const DEFAULT_REGEX: &str = concat!(
+    r"^[A-Za-z_][\w\s*&:<>,]*\(|",
+    r"^\s*((pub(\([\w:]+\))?|export|async|static|public|private|protected)\s+)*",
+    r"(fn|def|func|function|class|struct|enum|trait|impl|interface)\b|",
+    r"^#{1,6}\s|",
+    r"^\[[^\]]+\]\s*$",
+);
+
+/// Searches numbered lines as grrs_core::Searcher::search_numbered() does,
+/// sending the nearest line above each group of lines that `headings` matches,
+/// such as the function or section they're in, as a
+/// grrs_core::sink::SinkEvent::Heading at the start of the group.
+///
+/// A heading is sent once, so groups under one already written, or that start
+/// with one, get none.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::{LiteralMatcher, RegexMatcher};
+/// use grrs_core::sink::{CollectSink, SinkEvent};
+/// use grrs_core::Searcher;
+/// # fn main() -> Result<(), Error> {
+/// let code = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}";
+/// let lines = grrs_core::numbered_lines(code.as_bytes());
+/// let headings = RegexMatcher::new(grrs_core::heading::DEFAULT_REGEX, false)?;
+/// let mut sink = CollectSink::default();
+/// grrs_core::heading::search_numbered(
+///     &Searcher::builder().build(),
+///     &LiteralMatcher::new("println"),
+///     &headings,
+///     lines,
+///     &mut sink,
+/// )?;
+/// assert_eq!(
+///     sink.events[0],
+///     SinkEvent::Heading { line_number: 1, line: "fn main() {".into() }
+/// );
+/// # Ok(())
+/// # }
+/// ```
+pub fn search_numbered<S: AsRef<str>>(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    headings: &dyn Matcher,
+    lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary, GrrsError> {
+    let before = searcher.options().before() as u64;
+    let seen = RefCell::new(VecDeque::new());
+    let lines = lines.inspect(|line| {
+        if let Ok((num, line)) = line {
+            let mut seen = seen.borrow_mut();
+            // Only the last heading above the earliest line of context still
+            // to be written can be needed.
+            let start = num.saturating_sub(before);
+            while seen.get(1).is_some_and(|(next, _)| *next < start) {
+                seen.pop_front();
+            }
+            if headings.is_match(line.as_ref()) {
+                seen.push_back((*num, line.as_ref().to_string()));
+            }
+        }
+    });
+
+    let mut sink = HeadingSink {
+        seen: &seen,
+        inner: sink,
+        written: None,
+    };
+    searcher.search_numbered(matcher, lines, &mut sink)
+}
+
+/// Sends the heading of each group of lines before the group's first line.
+struct HeadingSink<'a> {
+    seen: &'a RefCell<VecDeque<(u64, String)>>,
+    inner: &'a mut dyn Sink,
+    /// The number of the last line written
+    written: Option<u64>,
+}
+
+impl Sink for HeadingSink<'_> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        let line_number = match event {
+            SinkEvent::Match { line_number, .. } | SinkEvent::Context { line_number, .. } => {
+                line_number
+            }
+            _ => return self.inner.event(event),
+        };
+        let written = self.written;
+        if written.is_none_or(|last| line_number > last + 1) {
+            // A group starting with a heading needs no other.
+            let heading = (self.seen.borrow().iter().rev())
+                .find(|(num, _)| *num <= line_number)
+                .filter(|(num, _)| *num < line_number && written.is_none_or(|last| *num > last))
+                .cloned();
+            if let Some((num, line)) = heading {
+                self.inner.event(SinkEvent::Heading {
+                    line_number: num,
+                    line: Cow::Owned(line),
+                })?;
+            }
+        }
+        self.written = Some(line_number);
+        self.inner.event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.inner.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::{LiteralMatcher, RegexMatcher};
+    use crate::numbered_lines;
+    use crate::sink::CollectSink;
+
+    #[test]
+    fn show_the_heading_of_each_group() -> Result<(), GrrsError> {
+        let text = "[server]\nport = 80\n\n[client]\nport = 81\nretries = 3\nport = 82\n";
+        let headings = RegexMatcher::new(DEFAULT_REGEX, false)?;
+        let mut sink = CollectSink::default();
+        search_numbered(
+            &Searcher::builder().build(),
+            &LiteralMatcher::new("port"),
+            &headings,
+            numbered_lines(text.as_bytes()),
+            &mut sink,
+        )?;
+        let found: Vec<_> = (sink.events.iter())
+            .map(|event| match event {
+                SinkEvent::Heading { line_number, .. } => format!("={}", line_number),
+                SinkEvent::Match { line_number, .. } => format!(":{}", line_number),
+                _ => "?".to_string(),
+            })
+            .collect();
+        assert_eq!(found, vec!["=1", ":2", "=4", ":5", ":7"]);
+
+        // A heading shown as context isn't repeated.
+        let mut sink = CollectSink::default();
+        search_numbered(
+            &Searcher::builder().before_context(1).build(),
+            &LiteralMatcher::new("81"),
+            &headings,
+            numbered_lines(text.as_bytes()),
+            &mut sink,
+        )?;
+        assert!(matches!(
+            sink.events[0],
+            SinkEvent::Context { line_number: 4, .. }
+        ));
+        assert_eq!(sink.events.len(), 2);
+
+        Ok(())
+    }
+}
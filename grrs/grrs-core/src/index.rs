@@ -0,0 +1,189 @@
+use crate::error::{GrrsError, Result};
+use crate::files::walk;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The trigrams, runs of three bytes, in each of a set of files, for telling
+/// which files can't contain a literal without reading them. Letters are
+/// folded to ASCII lower case, so one index serves searches ignoring case
+/// too, and files are indexed as they're searched, after decoding UTF-16.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::index::Index;
+/// # fn main() -> Result<(), Error> {
+/// let dir = tempfile::tempdir()?;
+/// std::fs::write(dir.path().join("a.txt"), "lorem ipsum")?;
+/// std::fs::write(dir.path().join("b.txt"), "dolor sit")?;
+/// let (index, errors) = Index::build([dir.path()], |_| true);
+/// assert!(errors.is_empty());
+/// assert!(index.may_contain(dir.path().join("a.txt"), "IPSUM", true));
+/// assert!(!index.may_contain(dir.path().join("b.txt"), "ipsum", false));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    /// Each file by its canonical path
+    files: BTreeMap<PathBuf, IndexedFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IndexedFile {
+    /// The file's modification time and length when indexed, which change
+    /// when it's written
+    modified: SystemTime,
+    len: u64,
+    /// The file's trigrams, each packed into the low bytes, sorted
+    trigrams: Vec<u32>,
+}
+
+impl Index {
+    /// Indexes the files that `filter` accepts under each path, as
+    /// grrs_core::walk() lists them, returning with it why each file or
+    /// directory that couldn't be read wasn't, each error naming its path.
+    pub fn build<P: AsRef<Path>>(
+        paths: impl IntoIterator<Item = P>,
+        filter: impl Fn(&Path) -> bool,
+    ) -> (Index, Vec<GrrsError>) {
+        let mut index = Index::default();
+        let mut errors = Vec::new();
+        for path in walk(paths) {
+            let indexed = path.and_then(|path| match filter(&path) {
+                true => index_file(&path).map(Some),
+                false => Ok(None),
+            });
+            match indexed {
+                Ok(Some((canonical, file))) => {
+                    index.files.insert(canonical, file);
+                }
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+
+        (index, errors)
+    }
+
+    /// Reads an index written by grrs_core::index::Index::save().
+    pub fn load(path: impl AsRef<Path>) -> Result<Index, GrrsError> {
+        let path = path.as_ref();
+        let text = std::fs::read(path).map_err(|err| GrrsError::opening(path, err))?;
+        serde_json::from_slice(&text).map_err(|err| {
+            let path = crate::display_path(path);
+            GrrsError::InvalidArgument(format!("`{}` is not an index: {}", path.display(), err))
+        })
+    }
+
+    /// Writes the index as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GrrsError> {
+        let mut file = BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(&mut file, self).map_err(std::io::Error::from)?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// How many files are indexed.
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Reports whether the index has no files.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Reports whether a file may contain the literal, as it must unless the
+    /// index has every trigram of the file as it is now. Ignoring case can
+    /// only be told apart for ASCII letters.
+    pub fn may_contain(&self, path: impl AsRef<Path>, literal: &str, ignore_case: bool) -> bool {
+        if ignore_case && !literal.is_ascii() {
+            return true;
+        }
+        let file = match path.as_ref().canonicalize() {
+            Ok(canonical) => match self.files.get(&canonical) {
+                Some(file) if version(&canonical).ok() == Some((file.modified, file.len)) => file,
+                _ => return true,
+            },
+            Err(_) => return true,
+        };
+
+        trigrams(literal.as_bytes()).all(|trigram| file.trigrams.binary_search(&trigram).is_ok())
+    }
+}
+
+/// Reads a file's trigrams, as it's searched, along with its canonical path.
+fn index_file(path: &Path) -> Result<(PathBuf, IndexedFile), GrrsError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|err| GrrsError::opening(path, err))?;
+    let (modified, len) = version(&canonical).map_err(|err| GrrsError::opening(path, err))?;
+    let mut content = Vec::new();
+    (crate::open_input(path)?.read_to_end(&mut content))
+        .map_err(|err| GrrsError::opening(path, err))?;
+    let mut trigrams: Vec<_> = trigrams(&content).collect();
+    trigrams.sort_unstable();
+    trigrams.dedup();
+
+    Ok((
+        canonical,
+        IndexedFile {
+            modified,
+            len,
+            trigrams,
+        },
+    ))
+}
+
+fn version(path: &Path) -> std::io::Result<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path)?;
+
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+/// Packs each run of three bytes, folded to ASCII lower case, into a u32.
+fn trigrams(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes.windows(3).map(|window| {
+        let [a, b, c] = [0, 1, 2].map(|i| u32::from(window[i].to_ascii_lowercase()));
+        a << 16 | b << 8 | c
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_out_files_missing_a_literal() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, "Lorem Ipsum\n")?;
+        std::fs::write(dir.path().join("skipped.txt"), "dolor\n")?;
+        let (index, errors) = Index::build([dir.path()], |path| !path.ends_with("skipped.txt"));
+        assert!(errors.is_empty());
+        assert_eq!(index.len(), 1);
+
+        let saved = dir.path().join("index.json");
+        index.save(&saved)?;
+        let index = Index::load(&saved)?;
+        assert!(index.may_contain(&notes, "Ipsum", false));
+        assert!(index.may_contain(&notes, "ipsum", true));
+        assert!(!index.may_contain(&notes, "dolor", false));
+        // Too short to have a trigram, or in a file that isn't indexed.
+        assert!(index.may_contain(&notes, "do", false));
+        assert!(index.may_contain(dir.path().join("skipped.txt"), "dolor", false));
+
+        // A file that's been written since could contain anything.
+        std::fs::write(&notes, "Lorem Ipsum dolor\n")?;
+        assert!(index.may_contain(&notes, "dolor", false));
+        assert!(Index::load(&notes).is_err());
+
+        Ok(())
+    }
+}
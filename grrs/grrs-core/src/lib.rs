@@ -0,0 +1,562 @@
+//! Without the default `std` feature only grrs_core::matcher is available, and
+//! builds for `no_std` targets with an allocator. Without the default `fs`
+//! feature, functions that open, create or delete files are compiled out, as
+//! for WebAssembly.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+use format::{Formatter, LineFormatter};
+#[cfg(feature = "fs")]
+use sink::FileSink;
+#[cfg(feature = "std")]
+use sink::{Sink, SinkEvent, WriterSink};
+#[cfg(feature = "fs")]
+use sniff::{ContentType, Utf16Reader};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
+use std::fs::{remove_file, File};
+#[cfg(feature = "std")]
+use std::io::Write;
+#[cfg(feature = "fs")]
+use std::io::{BufRead, BufWriter};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "async")]
+mod async_search;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod encode;
+mod error;
+#[cfg(feature = "std")]
+pub mod fields;
+#[cfg(feature = "fs")]
+mod files;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "regex")]
+pub mod glob;
+#[cfg(feature = "regex")]
+pub mod heading;
+#[cfg(feature = "fs")]
+pub mod index;
+pub mod matcher;
+#[cfg(feature = "std")]
+mod options;
+#[cfg(feature = "std")]
+mod pattern;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "regex")]
+pub mod record;
+#[cfg(feature = "std")]
+mod search;
+#[cfg(feature = "std")]
+pub mod sink;
+#[cfg(feature = "std")]
+pub mod sniff;
+#[cfg(feature = "regex")]
+pub mod timestamp;
+#[cfg(feature = "std")]
+pub mod window;
+
+#[cfg(feature = "async")]
+pub use async_search::stream_matches;
+#[cfg(feature = "std")]
+pub use error::OpenFailure;
+pub use error::{GrrsError, Result};
+#[cfg(feature = "fs")]
+pub use files::{search_files, search_tree, walk, BufferSize, FilePolicy, FileResults, Walk};
+#[cfg(feature = "std")]
+pub use options::SearchOptions;
+#[cfg(feature = "std")]
+pub use pattern::CompiledPattern;
+#[cfg(feature = "std")]
+pub use pool::{BufferPool, PooledBuffer, PooledReader};
+#[cfg(feature = "std")]
+pub use search::{
+    matches_in, numbered_lines, numbered_lines_lossy, preserve_case, replace_matches,
+    replace_matches_with, search_reader_with, search_with, Match, SearchSummary, Searcher,
+    SearcherBuilder,
+};
+
+/// Writes pattern matches from supplied string slice with line number to output.
+/// Lines are numbered from `num` onward. This is a shorthand for searching with
+/// a default grrs_core::Searcher, and returns the same grrs_core::SearchSummary.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let line_num = 1;
+/// let mut output = Vec::new();
+/// grrs_core::print_matches("This prints results", &line_num, "prints", &mut output)?;
+/// assert_eq!(output, b"LINE# 1: This prints results\n");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn print_matches(
+    content: &str,
+    num: &u64,
+    pattern: &str,
+    writer: impl Write,
+) -> Result<SearchSummary, GrrsError> {
+    let mut sink = WriterSink::new(writer);
+    search_into(content, num, pattern, &mut sink)
+}
+
+/// Writes a single line with line number to output, formatted the same as a match
+/// written by grrs_core::print_matches(), for callers that have already decided it matches.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// grrs_core::print_line("Already matched", &7, &mut output)?;
+/// assert_eq!(output, b"LINE# 7: Already matched\n");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+pub fn print_line(line: &str, num: &u64, mut writer: impl Write) -> Result<(), GrrsError> {
+    let event = SinkEvent::Match {
+        line_number: *num,
+        line: line.into(),
+    };
+    LineFormatter.format(&event, &mut writer)
+}
+
+/// Writes pattern matches from supplied string slice with line number to new file of
+/// which the name is supplied. Lines are numbered from `num` onward, as with
+/// grrs_core::print_matches().
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use std::io::Read;
+/// # fn main() -> Result<(), Error> {
+/// std::fs::File::create("test_write_file.txt")?;
+/// let outfile = std::path::PathBuf::from("test_write_file.txt");
+/// let num = 1;
+/// grrs_core::write_matches("lorem ipsum\ndolor sit amet", &num, "lorem", &outfile)?;
+/// let mut file = std::fs::File::open("test_write_file.txt")?;
+/// let mut contents = String::new();
+/// file.read_to_string(&mut contents)?;
+/// # std::fs::remove_file(outfile)?;
+/// assert_eq!(contents, "LINE# 1: lorem ipsum\n");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn write_matches(
+    content: &str,
+    num: &u64,
+    pattern: &str,
+    outfile: impl AsRef<Path>,
+) -> Result<SearchSummary, GrrsError> {
+    let mut sink = FileSink::append(outfile)?;
+    search_into(content, num, pattern, &mut sink)
+}
+
+/// Writes pattern matches from supplied string slice with line number to a file
+/// that is already open, at its current position, as with grrs_core::write_matches().
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use std::io::{Read, Seek, SeekFrom};
+/// # fn main() -> Result<(), Error> {
+/// let mut file = tempfile::tempfile()?;
+/// grrs_core::write_matches_to_file("lorem ipsum\ndolor sit amet", &1, "dolor", &file)?;
+/// let mut contents = String::new();
+/// file.seek(SeekFrom::Start(0))?;
+/// file.read_to_string(&mut contents)?;
+/// assert_eq!(contents, "LINE# 2: dolor sit amet\n");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn write_matches_to_file(
+    content: &str,
+    num: &u64,
+    pattern: &str,
+    file: &File,
+) -> Result<SearchSummary, GrrsError> {
+    let mut sink = WriterSink::new(BufWriter::new(file));
+    search_into(content, num, pattern, &mut sink)
+}
+
+/// Searches content with a default Searcher, numbering lines from `num`.
+#[cfg(feature = "std")]
+fn search_into(
+    content: &str,
+    num: &u64,
+    pattern: &str,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary, GrrsError> {
+    let searcher = Searcher::default();
+    let lines = (*num..).zip(content.lines()).map(Ok);
+    searcher.search_numbered(&searcher.literal(pattern), lines, sink)
+}
+
+/// Opens a file for searching, telling what it holds by its first bytes with
+/// grrs_core::sniff::ContentType::sniff(). UTF-16 text is read as UTF-8, and a
+/// byte order mark is skipped. With the `compression` feature, gzip files are
+/// decompressed as they're read, and what they hold is told in turn. Files
+/// that can't be opened, including directories, produce
+/// grrs_core::GrrsError::Open, and so do binary files. The file is read
+/// through a buffer sized for it, as grrs_core::BufferSize::Adaptive does.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use std::io::{BufRead, Write};
+/// # fn main() -> Result<(), Error> {
+/// let mut file = tempfile::NamedTempFile::new()?;
+/// writeln!(file, "lorem ipsum")?;
+/// let reader = grrs_core::open_input(file.path())?;
+/// assert_eq!(reader.lines().next().transpose()?, Some("lorem ipsum".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn open_input(path: impl AsRef<Path>) -> Result<Box<dyn BufRead>, GrrsError> {
+    open_input_with(path, BufferSize::Adaptive)
+}
+
+/// Opens a file as grrs_core::open_input() does, reading it through a buffer
+/// of the given size.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::BufferSize;
+/// use std::io::{BufRead, Write};
+/// # fn main() -> Result<(), Error> {
+/// let mut file = tempfile::NamedTempFile::new()?;
+/// writeln!(file, "lorem ipsum")?;
+/// let mut reader = grrs_core::open_input_with(file.path(), BufferSize::Fixed(4))?;
+/// assert_eq!(reader.fill_buf()?, b"lore");
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn open_input_with(
+    path: impl AsRef<Path>,
+    buffer: BufferSize,
+) -> Result<Box<dyn BufRead>, GrrsError> {
+    open_input_in(path, buffer, &BufferPool::new())
+}
+
+/// Opens a file as grrs_core::open_input_with() does, taking the buffers it's
+/// read through from a grrs_core::BufferPool and giving them back once the
+/// reader is dropped.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::{BufferPool, BufferSize};
+/// # fn main() -> Result<(), Error> {
+/// let file = tempfile::NamedTempFile::new()?;
+/// let pool = BufferPool::new();
+/// for _ in 0..3 {
+///     drop(grrs_core::open_input_in(file.path(), BufferSize::Adaptive, &pool)?);
+/// }
+/// assert_eq!(pool.idle(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn open_input_in(
+    path: impl AsRef<Path>,
+    buffer: BufferSize,
+    pool: &BufferPool,
+) -> Result<Box<dyn BufRead>, GrrsError> {
+    let path = path.as_ref();
+    match sniff_file(path, buffer, pool)? {
+        (content, _) if content.is_binary() => Err(GrrsError::binary(path, content)),
+        (_, reader) => Ok(reader),
+    }
+}
+
+/// Opens a file as grrs_core::open_input() does, but returns binary files too,
+/// along with what the file was found to hold.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sniff::ContentType;
+/// # fn main() -> Result<(), Error> {
+/// let file = tempfile::NamedTempFile::new()?;
+/// std::fs::write(file.path(), b"\xff\xfeh\0i\0")?;
+/// let (content, mut reader) = grrs_core::open_sniffed(file.path(), Default::default())?;
+/// let mut text = String::new();
+/// reader.read_to_string(&mut text)?;
+/// assert_eq!((content, text.as_str()), (ContentType::Utf16Le, "hi"));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn open_sniffed(
+    path: impl AsRef<Path>,
+    buffer: BufferSize,
+) -> Result<(ContentType, Box<dyn BufRead>), GrrsError> {
+    sniff_file(path.as_ref(), buffer, &BufferPool::new())
+}
+
+/// Reads content that isn't in a file of its own, such as a file as git had
+/// it, as grrs_core::open_input() reads a file, naming the path it came from
+/// in any error.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use std::io::Read;
+/// # fn main() -> Result<(), Error> {
+/// let mut reader = grrs_core::open_bytes("notes.txt", b"\xff\xfeh\0i\0".to_vec())?;
+/// let mut text = String::new();
+/// reader.read_to_string(&mut text)?;
+/// assert_eq!(text, "hi");
+/// assert!(grrs_core::open_bytes("image.png", b"\x89PNG\0".to_vec()).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "fs")]
+pub fn open_bytes(path: impl AsRef<Path>, content: Vec<u8>) -> Result<Box<dyn BufRead>, GrrsError> {
+    let path = path.as_ref();
+    let capacity = BufferSize::Adaptive.for_len(content.len() as u64);
+    let reader = Box::new(std::io::Cursor::new(content));
+    match sniffed(reader, capacity, &BufferPool::new()) {
+        Ok((content, _)) if content.is_binary() => Err(GrrsError::binary(path, content)),
+        Ok((_, reader)) => Ok(reader),
+        Err(err) => Err(GrrsError::opening(path, err)),
+    }
+}
+
+/// Opens a file and tells what it holds, reading it through pooled buffers.
+#[cfg(feature = "fs")]
+fn sniff_file(
+    path: &Path,
+    buffer: BufferSize,
+    pool: &BufferPool,
+) -> Result<(ContentType, Box<dyn BufRead>), GrrsError> {
+    let file = File::open(path).map_err(|err| GrrsError::opening(path, err))?;
+    let metadata = file.metadata().ok();
+    // Directories open on some platforms, only to fail once they're read.
+    if metadata.as_ref().is_some_and(|metadata| metadata.is_dir()) {
+        let source = std::io::Error::from(std::io::ErrorKind::IsADirectory);
+        return Err(GrrsError::opening(path, source));
+    }
+    // A file whose size isn't known is taken to be big.
+    let capacity = buffer.for_len(metadata.map_or(u64::MAX, |metadata| metadata.len()));
+
+    sniffed(Box::new(pool.reader(capacity, file)), capacity, pool)
+        .map_err(|err| GrrsError::opening(path, err))
+}
+
+/// Routes a reader by what its first bytes show it holds: decoding UTF-16,
+/// decompressing gzip and leaving anything else as it is.
+#[cfg(feature = "fs")]
+fn sniffed(
+    mut reader: Box<dyn BufRead>,
+    capacity: usize,
+    pool: &BufferPool,
+) -> std::io::Result<(ContentType, Box<dyn BufRead>)> {
+    let head = reader.fill_buf()?;
+    let content = ContentType::sniff(head);
+    match content {
+        ContentType::Text if head.starts_with(b"\xef\xbb\xbf") => reader.consume(3),
+        ContentType::Utf16Le | ContentType::Utf16Be => {
+            reader.consume(2);
+            let decoder = Utf16Reader::new(reader, content == ContentType::Utf16Be);
+            return Ok((content, Box::new(pool.reader(capacity, decoder))));
+        }
+        #[cfg(feature = "compression")]
+        ContentType::Gzip => {
+            let decoder = flate2::read::MultiGzDecoder::new(reader);
+            return sniffed(Box::new(pool.reader(capacity, decoder)), capacity, pool);
+        }
+        _ => {}
+    }
+
+    Ok((content, reader))
+}
+
+/// Detects if file by supplied name exists and deletes it if so.
+/// To be called before grrs_core::write_matches() to make sure a new, empty file
+/// is created, or else the output will be appended to supplied file.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use std::io::Write;
+/// # fn main() -> Result<(), Error> {
+/// # let mut file = std::fs::File::create("test_purge_file.txt")?;
+/// # writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+/// let outfile = std::path::PathBuf::from("test_purge_file.txt");
+/// grrs_core::purge_file(&outfile)?;
+/// match &outfile.exists() {
+///     false => Some(outfile),
+///     true => return panic!("file was not purged"),
+/// };
+/// # Ok(())
+/// }
+/// ```
+#[cfg(feature = "fs")]
+pub fn purge_file(outfile: impl AsRef<Path>) -> Result<(), GrrsError> {
+    let outfile = outfile.as_ref();
+    if outfile.exists() {
+        remove_file(outfile)?;
+    }
+
+    Ok(())
+}
+
+/// Drops the `\\?\` prefix of a Windows extended-length path where the path
+/// means the same without it, turning `\\?\C:\dir` into `C:\dir` and
+/// `\\?\UNC\server\share` into `\\server\share`, for showing it to people.
+/// Other paths are returned as they are.
+///
+/// The prefix is only needed to open paths longer than `MAX_PATH`, and the
+/// standard library adds it itself when it is.
+///
+/// # Example
+///
+/// ```rust
+/// use std::path::Path;
+/// let path = Path::new(r"\\?\UNC\server\share\node_modules\a.js");
+/// assert_eq!(grrs_core::display_path(path), Path::new(r"\\server\share\node_modules\a.js"));
+/// ```
+#[cfg(feature = "std")]
+pub fn display_path(path: &Path) -> Cow<'_, Path> {
+    let text = match path.to_str().and_then(|text| text.strip_prefix(r"\\?\")) {
+        Some(text) => text,
+        None => return Cow::Borrowed(path),
+    };
+    if let Some(share) = text.strip_prefix(r"UNC\") {
+        return Cow::Owned(PathBuf::from(format!(r"\\{}", share)));
+    }
+    let mut chars = text.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(drive), Some(':'), Some('\\') | None) if drive.is_ascii_alphabetic() => {
+            Cow::Borrowed(Path::new(text))
+        }
+        _ => Cow::Borrowed(path),
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    #[cfg(feature = "fs")]
+    use std::io::prelude::*;
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn classify_open_failures() -> Result<(), GrrsError> {
+        let dir = tempfile::tempdir()?;
+        let failure = |path: &Path| open_input(path).err().and_then(|err| err.open_failure());
+        assert_eq!(failure(dir.path()), Some(OpenFailure::IsADirectory));
+        assert_eq!(
+            failure(&dir.path().join("missing.txt")),
+            Some(OpenFailure::NotFound)
+        );
+        let err = open_input(dir.path()).err().map(|err| err.to_string());
+        assert_eq!(
+            err,
+            Some(format!("`{}` is a directory", dir.path().display()))
+        );
+
+        let image = dir.path().join("image.txt");
+        std::fs::write(&image, b"\x89PNG\r\n\x1a\n\0\0")?;
+        assert_eq!(failure(&image), Some(OpenFailure::Binary));
+        let err = open_input(&image).err().map(|err| err.to_string());
+        let expected = format!("`{}` is a PNG image, not text", image.display());
+        assert_eq!(err, Some(expected));
+
+        Ok(())
+    }
+
+    #[test]
+    fn display_extended_length_paths() {
+        for (path, shown) in [
+            (r"\\?\C:\dir\file.txt", r"C:\dir\file.txt"),
+            (r"\\?\d:", r"d:"),
+            (r"\\?\UNC\server\share", r"\\server\share"),
+            (r"\\?\Volume{1234}\dir", r"\\?\Volume{1234}\dir"),
+            ("relative/file.txt", "relative/file.txt"),
+        ] {
+            assert_eq!(display_path(Path::new(path)), Path::new(shown));
+        }
+    }
+
+    #[test]
+    fn print_a_match() -> Result<(), GrrsError> {
+        let mut result = Vec::new();
+        let num = 1;
+        print_matches("lorem ipsum\ndolor sit amet", &num, "lorem", &mut result)?;
+        assert_eq!(result, b"LINE# 1: lorem ipsum\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn number_past_i32() -> Result<(), GrrsError> {
+        let mut result = Vec::new();
+        let num = 3_000_000_000;
+        print_matches("lorem\nlorem", &num, "lorem", &mut result)?;
+        assert_eq!(
+            result,
+            b"LINE# 3000000000: lorem\nLINE# 3000000001: lorem\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn write_a_match() -> Result<(), GrrsError> {
+        File::create("test_write_file.txt")?;
+        let outfile = PathBuf::from("test_write_file.txt");
+        let num = 1;
+        write_matches("lorem ipsum\ndolor sit amet", &num, "lorem", &outfile)?;
+        let mut file = File::open("test_write_file.txt")?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        remove_file(outfile)?;
+        assert_eq!(contents, "LINE# 1: lorem ipsum\n");
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn purge_a_file() -> Result<(), GrrsError> {
+        let mut file = File::create("test_purge_file.txt")?;
+        writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
+        let outfile = PathBuf::from("test_purge_file.txt");
+        purge_file(&outfile)?;
+        match &outfile.exists() {
+            false => Some(outfile),
+            true => panic!("file was not purged"),
+        };
+
+        Ok(())
+    }
+}
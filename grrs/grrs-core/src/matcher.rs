@@ -0,0 +1,597 @@
+use crate::error::{GrrsError, Result};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+use memchr::memmem::Finder;
+#[cfg(feature = "regex")]
+use regex::{Regex, RegexBuilder};
+
+/// A strategy for finding a pattern within a single line.
+///
+/// Implementations only need to provide grrs_core::Matcher::find(); the iterator form
+/// is derived from it, though implementations that can do better (for example
+/// regexes with anchors or word boundaries) should override it.
+///
+/// Matchers must be `Send` and `Sync`, so one built for a query can be shared
+/// by a pool of worker threads, for example behind an `Arc`.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::{LiteralMatcher, Matcher};
+/// let matcher = LiteralMatcher::new("ab");
+/// assert_eq!(matcher.find("xxabxab"), Some(2..4));
+/// assert_eq!(matcher.find_iter("xxabxab").collect::<Vec<_>>(), vec![2..4, 5..7]);
+/// ```
+pub trait Matcher: Send + Sync {
+    /// Returns the byte range of the first match within the line, if any.
+    fn find(&self, line: &str) -> Option<Range<usize>>;
+
+    /// Returns the byte ranges of all non-overlapping matches within the line.
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        let mut start = 0;
+        Box::new(core::iter::from_fn(move || {
+            if start > line.len() {
+                return None;
+            }
+            let found = self.find(&line[start..])?;
+            let found = start + found.start..start + found.end;
+            start = match found.is_empty() {
+                false => found.end,
+                true => line[found.end..]
+                    .chars()
+                    .next()
+                    .map_or(line.len() + 1, |c| found.end + c.len_utf8()),
+            };
+            Some(found)
+        }))
+    }
+
+    /// Returns the byte ranges of every match within the line, including those
+    /// that overlap, by looking for the next one from just after the start of
+    /// each match rather than its end.
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        overlapping(line, move |start| {
+            let found = self.find(&line[start..])?;
+            Some(start + found.start..start + found.end)
+        })
+    }
+
+    /// Reports whether the line contains a match.
+    fn is_match(&self, line: &str) -> bool {
+        self.find(line).is_some()
+    }
+
+    /// Reports whether matches never take in a line terminator, so the first
+    /// found in many lines at once is in the first line that matches, and
+    /// whole buffers can be searched without splitting them into lines, see
+    /// grrs_core::Searcher::search_slice(). Regexes don't promise this, as
+    /// `^`, `$` and classes such as `\s` work differently across lines.
+    fn within_lines(&self) -> bool {
+        false
+    }
+}
+
+/// Calls `find_at` with each position to search from, starting over one
+/// character after the start of every match it returns.
+fn overlapping<'a, F>(line: &'a str, find_at: F) -> Box<dyn Iterator<Item = Range<usize>> + 'a>
+where
+    F: Fn(usize) -> Option<Range<usize>> + 'a,
+{
+    let mut start = 0;
+    Box::new(core::iter::from_fn(move || {
+        if start > line.len() {
+            return None;
+        }
+        let found = find_at(start)?;
+        start = line[found.start..]
+            .chars()
+            .next()
+            .map_or(line.len() + 1, |c| found.start + c.len_utf8());
+        Some(found)
+    }))
+}
+
+impl<M: Matcher + ?Sized> Matcher for &M {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        (**self).find(line)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_iter(line)
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_overlapping(line)
+    }
+
+    fn within_lines(&self) -> bool {
+        (**self).within_lines()
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Box<M> {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        (**self).find(line)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_iter(line)
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_overlapping(line)
+    }
+
+    fn within_lines(&self) -> bool {
+        (**self).within_lines()
+    }
+}
+
+impl<M: Matcher + ?Sized> Matcher for Arc<M> {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        (**self).find(line)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_iter(line)
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        (**self).find_overlapping(line)
+    }
+
+    fn within_lines(&self) -> bool {
+        (**self).within_lines()
+    }
+}
+
+/// Matches a fixed string, optionally ignoring case.
+///
+/// Case-insensitive matching uses Unicode simple case folding, or only ASCII
+/// case folding without the `regex` feature, so reported ranges always refer
+/// to the original line.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    pattern: String,
+    folded: Option<Folded>,
+    /// memchr's vectorised searcher for the pattern, unless turned off
+    finder: Option<Finder<'static>>,
+}
+
+#[cfg(feature = "regex")]
+type Folded = Regex;
+#[cfg(not(feature = "regex"))]
+type Folded = AhoCorasick;
+
+impl LiteralMatcher {
+    /// Creates a case-sensitive matcher for the supplied string.
+    pub fn new(pattern: &str) -> LiteralMatcher {
+        LiteralMatcher {
+            pattern: pattern.to_string(),
+            folded: None,
+            finder: Some(Finder::new(pattern).into_owned()),
+        }
+    }
+
+    /// Finds case-sensitive matches with SIMD instructions, picked for the
+    /// CPU at run time with the `std` feature, if enabled, as it is by
+    /// default. Disabled, matches are found with `str::find()`, for ruling the
+    /// vectorised search out when debugging.
+    pub fn simd(mut self, yes: bool) -> LiteralMatcher {
+        self.finder = yes.then(|| Finder::new(&self.pattern).into_owned());
+        self
+    }
+
+    /// Ignores letter case when matching if enabled.
+    pub fn case_insensitive(mut self, yes: bool) -> LiteralMatcher {
+        self.folded = match yes {
+            false => None,
+            true => Some(fold(&self.pattern)),
+        };
+        self
+    }
+}
+
+/// Builds a searcher matching the supplied string regardless of case.
+#[cfg(feature = "regex")]
+fn fold(pattern: &str) -> Folded {
+    // An escaped literal is always a valid expression.
+    RegexBuilder::new(&regex::escape(pattern))
+        .case_insensitive(true)
+        .build()
+        .unwrap()
+}
+
+/// Builds a searcher matching the supplied string regardless of ASCII case.
+#[cfg(not(feature = "regex"))]
+fn fold(pattern: &str) -> Folded {
+    // A single pattern is always within the automaton's limits.
+    AhoCorasickBuilder::new()
+        .ascii_case_insensitive(true)
+        .build([pattern])
+        .unwrap()
+}
+
+impl Matcher for LiteralMatcher {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        let start = match (&self.folded, &self.finder) {
+            (Some(folded), _) => return folded.find(line).map(|m| m.range()),
+            (None, Some(finder)) => finder.find(line.as_bytes()),
+            (None, None) => line.find(self.pattern.as_str()),
+        };
+        start.map(|start| start..start + self.pattern.len())
+    }
+
+    fn within_lines(&self) -> bool {
+        !self.pattern.contains(['\n', '\r'])
+    }
+}
+
+/// Matches a regular expression. Requires the `regex` feature.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::{Matcher, RegexMatcher};
+/// # fn main() -> Result<(), Error> {
+/// let matcher = RegexMatcher::new(r"\berr(or)?\b", false)?;
+/// assert_eq!(matcher.find("an error occurred"), Some(3..8));
+/// assert!(!matcher.is_match("terrible"));
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+#[cfg(feature = "regex")]
+impl RegexMatcher {
+    /// Compiles the supplied expression, optionally ignoring letter case.
+    pub fn new(pattern: &str, case_insensitive: bool) -> Result<RegexMatcher, GrrsError> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|err| GrrsError::invalid_pattern(pattern, err))?;
+
+        Ok(RegexMatcher { regex })
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Matcher for RegexMatcher {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.regex.find(line).map(|m| m.range())
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        Box::new(self.regex.find_iter(line).map(|m| m.range()))
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        // Searching from an offset rather than a slice keeps anchors and word
+        // boundaries seeing the whole line.
+        overlapping(line, move |start| {
+            Some(self.regex.find_at(line, start)?.range())
+        })
+    }
+}
+
+/// Reports every match of another matcher from
+/// grrs_core::matcher::Matcher::find_iter(), including overlapping ones, for
+/// anything that goes through the matches on a line.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::{LiteralMatcher, Matcher, Overlapping};
+/// let matcher = Overlapping(LiteralMatcher::new("aba"));
+/// assert_eq!(matcher.find_iter("ababa").collect::<Vec<_>>(), vec![0..3, 2..5]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Overlapping<M>(pub M);
+
+impl<M: Matcher> Matcher for Overlapping<M> {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.0.find(line)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        self.0.find_overlapping(line)
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        self.0.find_overlapping(line)
+    }
+
+    fn within_lines(&self) -> bool {
+        self.0.within_lines()
+    }
+}
+
+/// Matches any of several fixed strings in a single pass using an Aho-Corasick
+/// automaton. Where patterns overlap, the one supplied first wins.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::{Matcher, MultiMatcher};
+/// # fn main() -> Result<(), Error> {
+/// let matcher = MultiMatcher::new(&["warn", "error"], false)?;
+/// assert_eq!(matcher.find_iter("error, then warn").collect::<Vec<_>>(), vec![0..5, 12..16]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MultiMatcher {
+    automaton: AhoCorasick,
+    within_lines: bool,
+}
+
+impl MultiMatcher {
+    /// Builds an automaton for the supplied patterns, optionally ignoring ASCII
+    /// letter case.
+    pub fn new<P: AsRef<str>>(
+        patterns: &[P],
+        case_insensitive: bool,
+    ) -> Result<MultiMatcher, GrrsError> {
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .ascii_case_insensitive(case_insensitive)
+            .build(patterns.iter().map(|pattern| pattern.as_ref()))
+            .map_err(|err| GrrsError::invalid_pattern(&patterns_text(patterns), err))?;
+        let within_lines =
+            (patterns.iter()).all(|pattern| !pattern.as_ref().contains(['\n', '\r']));
+
+        Ok(MultiMatcher {
+            automaton,
+            within_lines,
+        })
+    }
+}
+
+/// Joins multiple patterns for display in an error.
+fn patterns_text<P: AsRef<str>>(patterns: &[P]) -> String {
+    let patterns: Vec<_> = patterns.iter().map(|pattern| pattern.as_ref()).collect();
+    patterns.join("|")
+}
+
+impl Matcher for MultiMatcher {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.automaton.find(line).map(|m| m.range())
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        Box::new(self.automaton.find_iter(line).map(|m| m.range()))
+    }
+
+    fn within_lines(&self) -> bool {
+        self.within_lines
+    }
+}
+
+/// Matches any of several matchers, keeping track of which found what, for
+/// searches with more than one pattern. Patterns are numbered from 0 in the
+/// order they're supplied.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::{LiteralMatcher, Matcher, PatternSet};
+/// let patterns = PatternSet::new(vec![
+///     Box::new(LiteralMatcher::new("warn")),
+///     Box::new(LiteralMatcher::new("error")),
+/// ]);
+/// assert_eq!(patterns.find_each("error, then warn"), vec![(1, 0..5), (0, 12..16)]);
+/// assert_eq!(patterns.matching("a warning"), vec![0]);
+/// assert_eq!(patterns.find("a warning"), Some(2..6));
+/// ```
+pub struct PatternSet {
+    matchers: Vec<Box<dyn Matcher>>,
+}
+
+impl PatternSet {
+    /// Creates a set matching any of the supplied matchers.
+    pub fn new(matchers: Vec<Box<dyn Matcher>>) -> PatternSet {
+        PatternSet { matchers }
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.matchers.len()
+    }
+
+    /// Reports whether the set has no patterns, and so matches nothing.
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// Returns the ids of the patterns found in the line.
+    pub fn matching(&self, line: &str) -> Vec<usize> {
+        (self.matchers.iter().enumerate())
+            .filter(|(_, matcher)| matcher.is_match(line))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Returns the matches within the line, each with the id of its pattern,
+    /// in the order they start. Where matches of different patterns overlap,
+    /// the one starting first wins, then the one whose pattern was supplied
+    /// first; a pattern's own matches only overlap if its matcher reports them
+    /// that way, as grrs_core::matcher::Overlapping does.
+    pub fn find_each(&self, line: &str) -> Vec<(usize, Range<usize>)> {
+        let mut found: Vec<_> = (self.matchers.iter().enumerate())
+            .flat_map(|(id, matcher)| matcher.find_iter(line).map(move |span| (id, span)))
+            .collect();
+        found.sort_by_key(|(id, span)| (span.start, *id));
+        let mut last = None;
+        let mut end = 0;
+        found.retain(|(id, span)| {
+            let kept = span.start >= end || last == Some(*id);
+            if kept {
+                last = Some(*id);
+                end = end.max(span.end).max(span.start + 1);
+            }
+            kept
+        });
+
+        found
+    }
+}
+
+impl Matcher for PatternSet {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        let found = self
+            .matchers
+            .iter()
+            .filter_map(|matcher| matcher.find(line));
+        found.min_by_key(|span| span.start)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        Box::new(self.find_each(line).into_iter().map(|(_, span)| span))
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        let mut found: Vec<_> = (self.matchers.iter())
+            .flat_map(|matcher| matcher.find_overlapping(line))
+            .collect();
+        found.sort_by_key(|span| span.start);
+        Box::new(found.into_iter())
+    }
+
+    fn within_lines(&self) -> bool {
+        // Otherwise every pattern would look through the rest of a buffer
+        // again after each match of another.
+        match &self.matchers[..] {
+            [matcher] => matcher.within_lines(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_matches() {
+        let matcher = LiteralMatcher::new("Test");
+        assert_eq!(matcher.find("A Test"), Some(2..6));
+        assert_eq!(matcher.find("a test"), None);
+        let matcher = matcher.case_insensitive(true);
+        assert_eq!(matcher.find("a tEsT"), Some(2..6));
+        assert_eq!(matcher.find_iter("test TEST").count(), 2);
+    }
+
+    #[test]
+    fn match_without_simd() {
+        let haystack = "é".repeat(40) + "needle in a haystack";
+        for simd in [true, false] {
+            let matcher = LiteralMatcher::new("needle").simd(simd);
+            assert_eq!(matcher.find(&haystack), Some(80..86));
+            assert_eq!(matcher.find("needl"), None);
+            let matcher = LiteralMatcher::new("").simd(simd);
+            assert_eq!(matcher.find_iter("ab").count(), 3);
+        }
+    }
+
+    #[test]
+    fn empty_matches_advance() {
+        let matcher = LiteralMatcher::new("");
+        assert_eq!(
+            matcher.find_iter("aé").collect::<Vec<_>>(),
+            vec![0..0, 1..1, 3..3]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn regex_and_multi_matches() -> Result<(), GrrsError> {
+        assert!(RegexMatcher::new("(", false).is_err());
+        let matcher = RegexMatcher::new("^a", false)?;
+        assert_eq!(matcher.find_iter("aaa").collect::<Vec<_>>(), vec![0..1]);
+        let matcher = MultiMatcher::new(&["foo", "BAR"], true)?;
+        assert_eq!(matcher.find("xx bar foo"), Some(3..6));
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn overlapping_matches() -> Result<(), GrrsError> {
+        let matcher = LiteralMatcher::new("aa");
+        assert_eq!(matcher.find_iter("aaaa").count(), 2);
+        let found: Vec<_> = matcher.find_overlapping("aaaa").collect();
+        assert_eq!(found, vec![0..2, 1..3, 2..4]);
+        let matcher = RegexMatcher::new(r"\b\w\w", false)?;
+        let found: Vec<_> = matcher.find_overlapping("abc de").collect();
+        assert_eq!(found, vec![0..2, 4..6]);
+        assert_eq!(Overlapping(matcher).find_iter("abc de").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_each_pattern() {
+        let patterns = PatternSet::new(vec![
+            Box::new(LiteralMatcher::new("ab")),
+            Box::new(LiteralMatcher::new("abc")),
+            Box::new(LiteralMatcher::new("c")),
+        ]);
+        assert_eq!(
+            patterns.find_each("abc c"),
+            vec![(0, 0..2), (2, 2..3), (2, 4..5)]
+        );
+        assert_eq!(patterns.matching("xabc"), vec![0, 1, 2]);
+        assert_eq!(patterns.find("x c ab"), Some(2..3));
+        assert!(!PatternSet::new(Vec::new()).is_match("ab"));
+    }
+
+    #[test]
+    fn share_between_threads() {
+        fn assert_shareable<T: Send + Sync + Clone>() {}
+        assert_shareable::<LiteralMatcher>();
+        assert_shareable::<MultiMatcher>();
+        assert_shareable::<Arc<dyn Matcher>>();
+
+        let matcher: Arc<dyn Matcher> = Arc::new(LiteralMatcher::new("b"));
+        let found: Vec<_> = ["abc", "xyz"]
+            .iter()
+            .map(|line| {
+                let matcher = Arc::clone(&matcher);
+                std::thread::spawn(move || matcher.is_match(line))
+            })
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        assert_eq!(found, vec![true, false]);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::{GrrsError, Result};
+#[cfg(feature = "regex")]
+use crate::matcher::RegexMatcher;
+use crate::matcher::{LiteralMatcher, Matcher};
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "cli")]
+use structopt::StructOpt;
+
+/// The knobs controlling how a search matches and which lines it reports.
+///
+/// The same struct is flattened into the command line parser, with the `cli`
+/// feature, and accepted by grrs_core::Searcher, so flags and library options
+/// can't drift apart. It also (de)serializes with serde, with missing fields
+/// taking their defaults.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::{SearchOptions, Searcher};
+/// # fn main() -> Result<(), Error> {
+/// let options: SearchOptions = serde_json::from_str(r#"{"word_regexp": true, "max_count": 1}"#)?;
+/// let mut output = Vec::new();
+/// Searcher::new(options).search("cat", "concat\ncat\nthe cat", &mut output)?;
+/// assert_eq!(output, b"LINE# 2: cat\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(StructOpt))]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Match the pattern regardless of letter case
+    #[cfg_attr(feature = "cli", structopt(short, long))]
+    pub ignore_case: bool,
+
+    /// Ignore letter case unless the pattern contains an uppercase letter
+    #[cfg_attr(feature = "cli", structopt(short = "S", long))]
+    pub smart_case: bool,
+
+    /// Treat the pattern as a regular expression
+    #[cfg_attr(feature = "cli", structopt(short = "E", long))]
+    pub regex: bool,
+
+    /// Only match the pattern where it forms a whole word
+    #[cfg_attr(feature = "cli", structopt(short, long))]
+    pub word_regexp: bool,
+
+    /// Report the lines that don't match instead of those that do
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub invert_match: bool,
+
+    /// Print this many lines of context before each match
+    #[cfg_attr(feature = "cli", structopt(short = "B", long, default_value = "0"))]
+    pub before_context: usize,
+
+    /// Print this many lines of context after each match
+    #[cfg_attr(feature = "cli", structopt(short = "A", long, default_value = "0"))]
+    pub after_context: usize,
+
+    /// Print this many lines of context around each match
+    #[cfg_attr(feature = "cli", structopt(short = "C", long))]
+    pub context: Option<usize>,
+
+    /// Print the lines after each match as context up to the next line
+    /// matching this regular expression, such as the rest of a stack trace up
+    /// to the next line starting with a timestamp, in place of
+    /// --after-context
+    #[cfg_attr(feature = "cli", structopt(long, value_name = "regex"))]
+    pub after_context_until: Option<String>,
+
+    /// Print every line, as context around the matches, so matches stand out
+    /// in the whole input rather than being shown alone
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub passthru: bool,
+
+    /// Stop reading after this many matching lines
+    #[cfg_attr(feature = "cli", structopt(short, long))]
+    pub max_count: Option<u64>,
+
+    /// Find fixed strings without SIMD instructions, for debugging
+    #[cfg_attr(feature = "cli", structopt(long))]
+    pub no_simd: bool,
+}
+
+impl SearchOptions {
+    /// Reports whether the supplied pattern should be matched ignoring case.
+    pub fn case_insensitive_for(&self, pattern: &str) -> bool {
+        self.ignore_case || (self.smart_case && !pattern.chars().any(char::is_uppercase))
+    }
+
+    /// Returns the lines of context to print before each match, letting
+    /// `context` stand in for an unset `before_context`.
+    pub fn before(&self) -> usize {
+        match self.before_context {
+            0 => self.context.unwrap_or(0),
+            lines => lines,
+        }
+    }
+
+    /// Returns the lines of context to print after each match, letting
+    /// `context` stand in for an unset `after_context`.
+    pub fn after(&self) -> usize {
+        match self.after_context {
+            0 => self.context.unwrap_or(0),
+            lines => lines,
+        }
+    }
+
+    /// Builds the matcher for the line that ends the context after a match,
+    /// if there is one, ignoring case as the pattern does.
+    pub fn context_until(&self) -> Result<Option<Box<dyn Matcher>>, GrrsError> {
+        match &self.after_context_until {
+            #[cfg(feature = "regex")]
+            Some(until) => {
+                let matcher = RegexMatcher::new(until, self.case_insensitive_for(until))?;
+                Ok(Some(Box::new(matcher)))
+            }
+            #[cfg(not(feature = "regex"))]
+            Some(_) => Err(GrrsError::InvalidArgument(
+                "--after-context-until requires the `regex` feature".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns a copy of these options with each field that `is_set` reports
+    /// by name, such as `ignore_case`, taken from `overrides` instead. This lets
+    /// flags given on the command line take precedence over configured defaults.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use grrs_core::SearchOptions;
+    /// let configured = SearchOptions { ignore_case: true, max_count: Some(5), ..Default::default() };
+    /// let flags = SearchOptions { max_count: Some(1), ..Default::default() };
+    /// let options = configured.with_overrides(&flags, |name| name == "max_count");
+    /// assert!(options.ignore_case);
+    /// assert_eq!(options.max_count, Some(1));
+    /// ```
+    pub fn with_overrides(
+        &self,
+        overrides: &SearchOptions,
+        is_set: impl Fn(&str) -> bool,
+    ) -> SearchOptions {
+        // Both sides serialize to objects of plain values, which always
+        // round-trip.
+        let mut merged = serde_json::to_value(self).unwrap();
+        if let (Some(merged), serde_json::Value::Object(overrides)) = (
+            merged.as_object_mut(),
+            serde_json::to_value(overrides).unwrap(),
+        ) {
+            for (name, value) in overrides {
+                if is_set(&name) {
+                    merged.insert(name, value);
+                }
+            }
+        }
+
+        serde_json::from_value(merged).unwrap()
+    }
+
+    /// Builds the matcher these options describe for the supplied pattern.
+    pub fn matcher(&self, pattern: &str) -> Result<Box<dyn Matcher>, GrrsError> {
+        let case_insensitive = self.case_insensitive_for(pattern);
+        match (self.regex, self.word_regexp) {
+            (false, false) => Ok(Box::new(
+                LiteralMatcher::new(pattern)
+                    .case_insensitive(case_insensitive)
+                    .simd(!self.no_simd),
+            )),
+            #[cfg(feature = "regex")]
+            (false, true) => Ok(Box::new(RegexMatcher::new(
+                &format!(r"\b{}\b", regex::escape(pattern)),
+                case_insensitive,
+            )?)),
+            #[cfg(feature = "regex")]
+            (true, false) => Ok(Box::new(RegexMatcher::new(pattern, case_insensitive)?)),
+            #[cfg(feature = "regex")]
+            (true, true) => Ok(Box::new(RegexMatcher::new(
+                &format!(r"\b(?:{})\b", pattern),
+                case_insensitive,
+            )?)),
+            #[cfg(not(feature = "regex"))]
+            _ => Err(GrrsError::InvalidArgument(
+                "regex and word matching require the `regex` feature".to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smart_case() {
+        let options = SearchOptions {
+            smart_case: true,
+            ..SearchOptions::default()
+        };
+        assert!(options.case_insensitive_for("error"));
+        assert!(!options.case_insensitive_for("Error"));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn build_matchers() -> Result<(), GrrsError> {
+        let options = SearchOptions {
+            regex: true,
+            word_regexp: true,
+            ..SearchOptions::default()
+        };
+        let matcher = options.matcher("err|warn")?;
+        assert!(matcher.is_match("a warn here"));
+        assert!(!matcher.is_match("warning"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn context_fallback() {
+        let options = SearchOptions {
+            after_context: 3,
+            context: Some(1),
+            ..SearchOptions::default()
+        };
+        assert_eq!((options.before(), options.after()), (1, 3));
+    }
+
+    #[test]
+    #[cfg(feature = "regex")]
+    fn build_context_until() -> Result<(), GrrsError> {
+        assert!(SearchOptions::default().context_until()?.is_none());
+        let options = SearchOptions {
+            after_context_until: Some(r"^\d{4}-".to_string()),
+            ..SearchOptions::default()
+        };
+        let until = options.context_until()?.unwrap();
+        assert!(until.is_match("2024-01-02 started"));
+        assert!(!until.is_match("  at main.rs:4"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,94 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::Matcher;
+use crate::options::SearchOptions;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// A pattern compiled once, with the case, regex and word options it was built
+/// with, so its expression or automaton can be reused for any number of files.
+/// Cloning only bumps a reference count, and clones can be sent to other
+/// threads.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::Searcher;
+/// # fn main() -> Result<(), Error> {
+/// let searcher = Searcher::builder().case_insensitive(true).build();
+/// let pattern = searcher.compile("lorem")?;
+/// for content in &["Lorem ipsum", "dolor sit amet", "LOREM"] {
+///     let mut output = Vec::new();
+///     searcher.search_matcher(&pattern, content, &mut output)?;
+///     assert_eq!(output.is_empty(), *content == "dolor sit amet");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CompiledPattern {
+    pattern: Arc<str>,
+    matcher: Arc<dyn Matcher>,
+}
+
+impl CompiledPattern {
+    /// Compiles the pattern as the supplied options describe.
+    pub fn new(pattern: &str, options: &SearchOptions) -> Result<CompiledPattern, GrrsError> {
+        Ok(CompiledPattern {
+            pattern: Arc::from(pattern),
+            matcher: Arc::from(options.matcher(pattern)?),
+        })
+    }
+
+    /// Returns the pattern as it was supplied.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Debug for CompiledPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CompiledPattern")
+            .field(&self.pattern)
+            .finish()
+    }
+}
+
+impl Matcher for CompiledPattern {
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        self.matcher.find(line)
+    }
+
+    fn find_iter<'a>(&'a self, line: &'a str) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        self.matcher.find_iter(line)
+    }
+
+    fn find_overlapping<'a>(
+        &'a self,
+        line: &'a str,
+    ) -> Box<dyn Iterator<Item = Range<usize>> + 'a> {
+        self.matcher.find_overlapping(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_a_pattern() -> Result<(), GrrsError> {
+        let options = SearchOptions {
+            word_regexp: true,
+            ..SearchOptions::default()
+        };
+        let pattern = CompiledPattern::new("cat", &options)?;
+        let copy = pattern.clone();
+        assert!(Arc::ptr_eq(&pattern.matcher, &copy.matcher));
+        assert!(copy.is_match("the cat sat"));
+        assert!(!copy.is_match("concatenate"));
+        assert_eq!(pattern.as_str(), "cat");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::{self, BufRead, Read};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// Buffers given back once a file has been read or searched, for reading or
+/// searching the next, so a search of many files doesn't allocate and free
+/// them for each one. Clones share the same buffers, so one pool can be
+/// handed to a grrs_core::FilePolicy and a grrs_core::Searcher and used by all
+/// their worker threads.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::BufferPool;
+/// let pool = BufferPool::new();
+/// let buffer = pool.take(4096);
+/// assert_eq!(buffer.len(), 4096);
+/// drop(buffer);
+/// assert_eq!(pool.idle(), 1);
+/// let buffer = pool.take(16);
+/// assert!(buffer.capacity() >= 4096);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BufferPool {
+    idle: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl BufferPool {
+    /// Creates an empty pool.
+    pub fn new() -> BufferPool {
+        BufferPool::default()
+    }
+
+    /// Takes a buffer of this many bytes, reusing one given back if there
+    /// is one, whose bytes are left over from its last use.
+    pub fn take(&self, len: usize) -> PooledBuffer {
+        let mut buffer = self.idle.lock().unwrap().pop().unwrap_or_default();
+        buffer.resize(len, 0);
+        PooledBuffer {
+            buffer,
+            pool: self.clone(),
+        }
+    }
+
+    /// Returns how many buffers are waiting to be reused.
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Reads through a buffer of this many bytes taken from the pool.
+    pub fn reader<R: Read>(&self, capacity: usize, inner: R) -> PooledReader<R> {
+        PooledReader {
+            inner,
+            buffer: self.take(capacity.max(1)),
+            pos: 0,
+            filled: 0,
+        }
+    }
+}
+
+/// Two pools are equal if they share their buffers.
+impl PartialEq for BufferPool {
+    fn eq(&self, other: &BufferPool) -> bool {
+        Arc::ptr_eq(&self.idle, &other.idle)
+    }
+}
+
+impl Eq for BufferPool {}
+
+/// A buffer taken from a grrs_core::BufferPool, given back when dropped.
+#[derive(Debug)]
+pub struct PooledBuffer {
+    buffer: Vec<u8>,
+    pool: BufferPool,
+}
+
+impl Deref for PooledBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.buffer
+    }
+}
+
+impl DerefMut for PooledBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        if buffer.capacity() > 0 {
+            self.pool.idle.lock().unwrap().push(buffer);
+        }
+    }
+}
+
+/// Reads as std::io::BufReader does, but through a buffer taken from a
+/// grrs_core::BufferPool and given back once the reader is dropped.
+#[derive(Debug)]
+pub struct PooledReader<R> {
+    inner: R,
+    buffer: PooledBuffer,
+    /// How much of the buffer has been consumed
+    pos: usize,
+    /// How much of the buffer was filled by the last read
+    filled: usize,
+}
+
+impl<R: Read> Read for PooledReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Big reads skip the buffer, as they do with BufReader.
+        if self.pos == self.filled && buf.len() >= self.buffer.len() {
+            return self.inner.read(buf);
+        }
+        let read = self.fill_buf()?.read(buf)?;
+        self.consume(read);
+        Ok(read)
+    }
+}
+
+impl<R: Read> BufRead for PooledReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            self.filled = self.inner.read(&mut self.buffer)?;
+            self.pos = 0;
+        }
+        Ok(&self.buffer[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuse_buffers_between_readers() -> io::Result<()> {
+        let pool = BufferPool::new();
+        for text in ["lorem ipsum\ndolor", "sit amet"] {
+            let mut reader = pool.reader(4, text.as_bytes());
+            assert_eq!(reader.fill_buf()?, &text.as_bytes()[..4]);
+            let mut read = String::new();
+            reader.read_to_string(&mut read)?;
+            assert_eq!(read, text);
+            assert_eq!(pool.idle(), 0);
+            drop(reader);
+            assert_eq!(pool.idle(), 1);
+        }
+        assert_eq!(pool, pool.clone());
+        assert_ne!(pool, BufferPool::new());
+
+        Ok(())
+    }
+}
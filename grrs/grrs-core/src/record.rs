@@ -0,0 +1,150 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::Matcher;
+use crate::search::{SearchSummary, Searcher};
+use crate::sink::{Sink, SinkEvent};
+use std::borrow::Cow;
+
+/// Regular expression for the lines separating paragraphs: blank ones.
+pub const PARAGRAPH_SEPARATOR: &str = r"^\s*$";
+
+/// Searches numbered lines a record at a time, records being the runs of
+/// lines between those `separators` matches, as awk does with `RS`. Every line
+/// of a record with any line that would be reported is sent, those lines as
+/// matches and the rest as context, with a break between records. Separators
+/// are never sent, and max_count counts records.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::{LiteralMatcher, RegexMatcher};
+/// use grrs_core::record::PARAGRAPH_SEPARATOR;
+/// use grrs_core::sink::WriterSink;
+/// use grrs_core::Searcher;
+/// # fn main() -> Result<(), Error> {
+/// let text = "name: a\nport: 80\n\nname: b\nport: 81";
+/// let mut output = Vec::new();
+/// grrs_core::record::search_numbered(
+///     &Searcher::default(),
+///     &LiteralMatcher::new("81"),
+///     &RegexMatcher::new(PARAGRAPH_SEPARATOR, false)?,
+///     grrs_core::numbered_lines(text.as_bytes()),
+///     &mut WriterSink::new(&mut output),
+/// )?;
+/// assert_eq!(output, b"LINE# 4- name: b\nLINE# 5: port: 81\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn search_numbered<S: AsRef<str>>(
+    searcher: &Searcher,
+    matcher: &dyn Matcher,
+    separators: &dyn Matcher,
+    lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+    sink: &mut dyn Sink,
+) -> Result<SearchSummary, GrrsError> {
+    let options = searcher.options();
+    let mut found = Records {
+        record: Vec::new(),
+        written: 0,
+        summary: SearchSummary::default(),
+    };
+    for line in lines {
+        if options.max_count.is_some_and(|max| found.written >= max) {
+            break;
+        }
+        let (num, line) = line?;
+        found.summary.total_lines += 1;
+        if separators.is_match(line.as_ref()) {
+            found.end(sink)?;
+            continue;
+        }
+        let is_match = matcher.is_match(line.as_ref()) != options.invert_match;
+        found.record.push((num, line, is_match));
+    }
+    if options.max_count.is_none_or(|max| found.written < max) {
+        found.end(sink)?;
+    }
+    sink.finish()?;
+
+    Ok(found.summary)
+}
+
+/// The lines of the record being read, and what's been found before it.
+struct Records<S> {
+    /// Each line of the record, and whether it's reported
+    record: Vec<(u64, S, bool)>,
+    /// How many records have been sent
+    written: u64,
+    summary: SearchSummary,
+}
+
+impl<S: AsRef<str>> Records<S> {
+    /// Sends the record read so far if any of its lines is reported, and
+    /// starts another.
+    fn end(&mut self, sink: &mut dyn Sink) -> Result<(), GrrsError> {
+        if self.record.iter().any(|(_, _, is_match)| *is_match) {
+            if self.written > 0 {
+                sink.event(SinkEvent::Break)?;
+            }
+            for (line_number, line, is_match) in &self.record {
+                let line = Cow::Borrowed(line.as_ref());
+                sink.event(match is_match {
+                    true => SinkEvent::Match {
+                        line_number: *line_number,
+                        line,
+                    },
+                    false => SinkEvent::Context {
+                        line_number: *line_number,
+                        line,
+                    },
+                })?;
+                self.summary.matched_lines += u64::from(*is_match);
+            }
+            self.written += 1;
+        }
+        self.record.clear();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::{LiteralMatcher, RegexMatcher};
+    use crate::numbered_lines;
+    use crate::sink::CollectSink;
+
+    #[test]
+    fn send_whole_records() -> Result<(), GrrsError> {
+        let text = "%\na\nport 1\n%\nb\n%\nport 2\nport 3\n";
+        let separators = RegexMatcher::new("^%$", false)?;
+        let search = |searcher: &Searcher| -> Result<_, GrrsError> {
+            let mut sink = CollectSink::default();
+            let summary = search_numbered(
+                searcher,
+                &LiteralMatcher::new("port"),
+                &separators,
+                numbered_lines(text.as_bytes()),
+                &mut sink,
+            )?;
+            let found: Vec<_> = (sink.events.iter())
+                .map(|event| match event {
+                    SinkEvent::Match { line_number, .. } => format!(":{}", line_number),
+                    SinkEvent::Context { line_number, .. } => format!("-{}", line_number),
+                    _ => "--".to_string(),
+                })
+                .collect();
+            Ok((found, summary))
+        };
+
+        let (found, summary) = search(&Searcher::default())?;
+        assert_eq!(found, vec!["-2", ":3", "--", ":7", ":8"]);
+        assert_eq!(summary.matched_lines, 3);
+        assert_eq!(summary.total_lines, 8);
+        let (found, _) = search(&Searcher::builder().max_count(1).build())?;
+        assert_eq!(found, vec!["-2", ":3"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1102 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::{LiteralMatcher, Matcher};
+use crate::options::SearchOptions;
+use crate::pattern::CompiledPattern;
+use crate::pool::BufferPool;
+use crate::sink::{Sink, SinkEvent, WriterSink};
+use memchr::{memchr, memchr_iter, memrchr};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::ops::{ControlFlow, Range};
+
+/// Searches content for a pattern and writes the matching lines, and any
+/// requested context lines around them, to output.
+///
+/// A Searcher is `Send` and `Sync` and only holds its options and a shared
+/// grrs_core::BufferPool, so it's cheap to clone into, or share between, worker
+/// threads.
+///
+/// Matching lines are written as `LINE# 4: text` and context lines as
+/// `LINE# 3- text`, with a `--` separator between groups that aren't adjacent.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::Searcher;
+/// # fn main() -> Result<(), Error> {
+/// let searcher = Searcher::builder().case_insensitive(true).context(1).build();
+/// let mut output = Vec::new();
+/// searcher.search("lorem", "Lorem ipsum\ndolor sit amet\nconsectetur", &mut output)?;
+/// assert_eq!(output, b"LINE# 1: Lorem ipsum\nLINE# 2- dolor sit amet\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Searcher {
+    options: SearchOptions,
+    pool: BufferPool,
+}
+
+/// Configures and builds a Searcher, see grrs_core::Searcher::builder().
+#[derive(Debug, Clone, Default)]
+pub struct SearcherBuilder {
+    options: SearchOptions,
+    pool: BufferPool,
+}
+
+impl SearcherBuilder {
+    /// Replaces every option at once, such as with options parsed from the
+    /// command line or deserialized from a file.
+    pub fn options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Matches the pattern regardless of letter case when enabled.
+    pub fn case_insensitive(mut self, yes: bool) -> Self {
+        self.options.ignore_case = yes;
+        self
+    }
+
+    /// Ignores letter case unless the pattern contains an uppercase letter
+    /// when enabled.
+    pub fn smart_case(mut self, yes: bool) -> Self {
+        self.options.smart_case = yes;
+        self
+    }
+
+    /// Treats string patterns as regular expressions when enabled.
+    pub fn regex(mut self, yes: bool) -> Self {
+        self.options.regex = yes;
+        self
+    }
+
+    /// Only matches string patterns that form whole words when enabled.
+    pub fn word_regexp(mut self, yes: bool) -> Self {
+        self.options.word_regexp = yes;
+        self
+    }
+
+    /// Reports the lines that don't match instead when enabled.
+    pub fn invert_match(mut self, yes: bool) -> Self {
+        self.options.invert_match = yes;
+        self
+    }
+
+    /// Finds fixed strings with SIMD instructions when enabled, as by
+    /// default, see grrs_core::matcher::LiteralMatcher::simd().
+    pub fn simd(mut self, yes: bool) -> Self {
+        self.options.no_simd = !yes;
+        self
+    }
+
+    /// Writes this many lines of context both before and after each match.
+    pub fn context(self, lines: usize) -> Self {
+        self.before_context(lines).after_context(lines)
+    }
+
+    /// Writes this many lines of context before each match.
+    pub fn before_context(mut self, lines: usize) -> Self {
+        self.options.before_context = lines;
+        self
+    }
+
+    /// Writes this many lines of context after each match.
+    pub fn after_context(mut self, lines: usize) -> Self {
+        self.options.after_context = lines;
+        self
+    }
+
+    /// Writes every line that doesn't match as context when enabled.
+    pub fn passthru(mut self, yes: bool) -> Self {
+        self.options.passthru = yes;
+        self
+    }
+
+    /// Stops reading after this many matching lines.
+    pub fn max_count(mut self, count: u64) -> Self {
+        self.options.max_count = Some(count);
+        self
+    }
+
+    /// Takes the buffers holding lines cut off between reads from this pool,
+    /// such as the one files are read through.
+    pub fn pool(mut self, pool: BufferPool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Builds the configured Searcher.
+    pub fn build(self) -> Searcher {
+        Searcher {
+            options: self.options,
+            pool: self.pool,
+        }
+    }
+}
+
+impl Searcher {
+    /// Starts configuring a Searcher, which defaults to a case-sensitive search
+    /// without context.
+    pub fn builder() -> SearcherBuilder {
+        SearcherBuilder::default()
+    }
+
+    /// Creates a Searcher from a complete set of options.
+    pub fn new(options: SearchOptions) -> Searcher {
+        Searcher {
+            options,
+            pool: BufferPool::new(),
+        }
+    }
+
+    /// Returns the options this Searcher was built with.
+    pub fn options(&self) -> &SearchOptions {
+        &self.options
+    }
+
+    /// Returns the literal matcher this Searcher uses for a string pattern,
+    /// ignoring the regex and word options.
+    pub fn literal(&self, pattern: &str) -> LiteralMatcher {
+        LiteralMatcher::new(pattern)
+            .case_insensitive(self.options.case_insensitive_for(pattern))
+            .simd(!self.options.no_simd)
+    }
+
+    /// Returns the matcher this Searcher uses for a string pattern.
+    pub fn matcher(&self, pattern: &str) -> Result<Box<dyn Matcher>, GrrsError> {
+        self.options.matcher(pattern)
+    }
+
+    /// Compiles a string pattern once for reuse across many searches, see
+    /// grrs_core::CompiledPattern.
+    pub fn compile(&self, pattern: &str) -> Result<CompiledPattern, GrrsError> {
+        CompiledPattern::new(pattern, &self.options)
+    }
+
+    /// Reports whether a single line would be reported for the pattern.
+    pub fn is_match(&self, pattern: &str, line: &str) -> Result<bool, GrrsError> {
+        Ok(self.matcher(pattern)?.is_match(line) != self.options.invert_match)
+    }
+
+    /// Searches the lines of a string slice, numbering them from 1.
+    pub fn search(
+        &self,
+        pattern: &str,
+        content: &str,
+        writer: impl Write,
+    ) -> Result<SearchSummary, GrrsError> {
+        self.search_matcher(&*self.matcher(pattern)?, content, writer)
+    }
+
+    /// Searches the lines of a string slice with any grrs_core::matcher::Matcher,
+    /// numbering them from 1. The Searcher's case, regex and word options only
+    /// apply to string patterns; matchers carry their own.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use anyhow::{Error, Result};
+    /// use grrs_core::matcher::RegexMatcher;
+    /// # fn main() -> Result<(), Error> {
+    /// let matcher = RegexMatcher::new(r"^\w+ sit", false)?;
+    /// let mut output = Vec::new();
+    /// grrs_core::Searcher::default().search_matcher(&matcher, "lorem ipsum\ndolor sit amet", &mut output)?;
+    /// assert_eq!(output, b"LINE# 2: dolor sit amet\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search_matcher(
+        &self,
+        matcher: &dyn Matcher,
+        content: &str,
+        writer: impl Write,
+    ) -> Result<SearchSummary, GrrsError> {
+        let lines = (1..).zip(content.lines()).map(Ok);
+        self.search_numbered(matcher, lines, &mut WriterSink::new(writer))
+    }
+
+    /// Searches the lines read from a reader, numbering them from 1.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use anyhow::{Error, Result};
+    /// # fn main() -> Result<(), Error> {
+    /// let reader = std::io::Cursor::new("lorem ipsum\ndolor sit amet");
+    /// let mut output = Vec::new();
+    /// grrs_core::Searcher::default().search_reader("dolor", reader, &mut output)?;
+    /// assert_eq!(output, b"LINE# 2: dolor sit amet\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search_reader(
+        &self,
+        pattern: &str,
+        reader: impl BufRead,
+        writer: impl Write,
+    ) -> Result<SearchSummary, GrrsError> {
+        self.search_reader_matcher(&*self.matcher(pattern)?, reader, writer)
+    }
+
+    /// Searches the lines read from a reader with any grrs_core::matcher::Matcher,
+    /// numbering them from 1.
+    pub fn search_reader_matcher(
+        &self,
+        matcher: &dyn Matcher,
+        reader: impl BufRead,
+        writer: impl Write,
+    ) -> Result<SearchSummary, GrrsError> {
+        self.search_sink(matcher, reader, &mut WriterSink::new(writer))
+    }
+
+    /// Searches the lines read from a reader with any grrs_core::matcher::Matcher,
+    /// numbering them from 1 and sending each match, context line and break
+    /// to the supplied grrs_core::sink::Sink.
+    pub fn search_sink(
+        &self,
+        matcher: &dyn Matcher,
+        reader: impl BufRead,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        self.search_numbered(matcher, numbered_lines(reader), sink)
+    }
+
+    /// Searches a whole buffer of UTF-8 text, such as a file read into memory,
+    /// numbering lines from 1 and sending events to the sink.
+    ///
+    /// Lines are only found around matches, so nothing is copied or looked at
+    /// line by line where nothing matches, as long as the matcher keeps to
+    /// lines (see grrs_core::matcher::Matcher::within_lines()) and there's no
+    /// context or inverted matching to report. Otherwise the buffer's lines
+    /// are searched in turn, still borrowed rather than copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use anyhow::{Error, Result};
+    /// use grrs_core::matcher::LiteralMatcher;
+    /// use grrs_core::sink::WriterSink;
+    /// # fn main() -> Result<(), Error> {
+    /// let mut output = Vec::new();
+    /// let content = b"lorem ipsum\r\ndolor sit\namet\n";
+    /// let matcher = LiteralMatcher::new("sit");
+    /// let summary = grrs_core::Searcher::default().search_slice(&matcher, content, &mut WriterSink::new(&mut output))?;
+    /// assert_eq!(summary.total_lines, 3);
+    /// assert_eq!(output, b"LINE# 2: dolor sit\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search_slice(
+        &self,
+        matcher: &dyn Matcher,
+        content: &[u8],
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        if !self.in_place(matcher) {
+            return match std::str::from_utf8(content) {
+                Ok(text) => self.search_numbered(matcher, (1..).zip(text.lines()).map(Ok), sink),
+                // The lines before one that isn't UTF-8 are still searched.
+                Err(_) => self.search_sink(matcher, content, sink),
+            };
+        }
+        let mut summary = SearchSummary::default();
+        let _ = self.find_lines(matcher, content, &mut summary, sink)?;
+        sink.finish()?;
+
+        Ok(summary)
+    }
+
+    /// Searches the lines read from a reader as grrs_core::Searcher::search_sink()
+    /// does, but a buffer at a time, finding lines around matches as
+    /// grrs_core::Searcher::search_slice() does. Only lines cut off at the end of
+    /// a buffer are copied.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use anyhow::{Error, Result};
+    /// use grrs_core::sink::WriterSink;
+    /// use std::io::BufReader;
+    /// # fn main() -> Result<(), Error> {
+    /// let reader = BufReader::with_capacity(4, &b"lorem ipsum\ndolor sit\namet"[..]);
+    /// let searcher = grrs_core::Searcher::default();
+    /// let mut output = Vec::new();
+    /// let summary = searcher.search_buffered(&searcher.literal("sit"), reader, &mut WriterSink::new(&mut output))?;
+    /// assert_eq!(summary.total_lines, 3);
+    /// assert_eq!(output, b"LINE# 2: dolor sit\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn search_buffered(
+        &self,
+        matcher: &dyn Matcher,
+        mut reader: impl BufRead,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        if !self.in_place(matcher) {
+            return self.search_sink(matcher, reader, sink);
+        }
+        let mut summary = SearchSummary::default();
+        // The start of a line cut off at the end of the last buffer.
+        let mut partial = self.pool.take(0);
+        loop {
+            let buffer = (reader.fill_buf())
+                .map_err(|err| GrrsError::reading(err, summary.total_lines + 1))?;
+            if buffer.is_empty() {
+                let _ = self.find_lines(matcher, &partial, &mut summary, sink)?;
+                break;
+            }
+            let read = buffer.len();
+            let flow = match memrchr(b'\n', buffer) {
+                None => {
+                    partial.extend_from_slice(buffer);
+                    ControlFlow::Continue(())
+                }
+                Some(last) => {
+                    let (mut lines, rest) = buffer.split_at(last + 1);
+                    let mut flow = ControlFlow::Continue(());
+                    if !partial.is_empty() {
+                        let end = memchr(b'\n', lines).map_or(lines.len(), |i| i + 1);
+                        partial.extend_from_slice(&lines[..end]);
+                        lines = &lines[end..];
+                        flow = self.find_lines(matcher, &partial, &mut summary, sink)?;
+                        partial.clear();
+                    }
+                    if flow.is_continue() {
+                        flow = self.find_lines(matcher, lines, &mut summary, sink)?;
+                    }
+                    partial.extend_from_slice(rest);
+                    flow
+                }
+            };
+            reader.consume(read);
+            if flow.is_break() {
+                break;
+            }
+        }
+
+        sink.finish()?;
+
+        Ok(summary)
+    }
+
+    /// Reports whether lines can be found around matches rather than looked
+    /// at in turn, as they can for a matcher that keeps to lines when there's
+    /// no context, passed through lines or inverted matching to report.
+    fn in_place(&self, matcher: &dyn Matcher) -> bool {
+        let options = &self.options;
+        matcher.within_lines()
+            && !options.invert_match
+            && options.before() == 0
+            && options.after() == 0
+            && options.after_context_until.is_none()
+            && !options.passthru
+    }
+
+    /// Searches lines of text in place, from the start of one to the end of
+    /// another or of the input, numbering them on from the lines already
+    /// counted in the summary. Breaks once no later line could be reported.
+    fn find_lines(
+        &self,
+        matcher: &dyn Matcher,
+        content: &[u8],
+        summary: &mut SearchSummary,
+        sink: &mut dyn Sink,
+    ) -> Result<ControlFlow<()>, GrrsError> {
+        let (text, valid) = match std::str::from_utf8(content) {
+            Ok(text) => (text, true),
+            // The lines before one that isn't UTF-8 are still searched.
+            Err(err) => {
+                let valid = &content[..err.valid_up_to()];
+                let end = memrchr(b'\n', valid).map_or(0, |i| i + 1);
+                (
+                    std::str::from_utf8(&valid[..end]).unwrap_or_default(),
+                    false,
+                )
+            }
+        };
+        let content = text.as_bytes();
+        let limit_reached = |summary: &SearchSummary| {
+            (self.options.max_count).is_some_and(|max| summary.matched_lines >= max)
+        };
+        // Where the next line starts, and its number.
+        let (mut start, mut line_number) = (0, summary.total_lines + 1);
+        while start < text.len() && !limit_reached(summary) {
+            let found = match matcher.find(&text[start..]) {
+                Some(found) => start + found.start,
+                None => break,
+            };
+            let line_start =
+                memrchr(b'\n', &content[start..found]).map_or(start, |i| start + i + 1);
+            line_number += memchr_iter(b'\n', &content[start..line_start]).count() as u64;
+            let line_end = memchr(b'\n', &content[found..]).map_or(text.len(), |i| found + i);
+            let line = &text[line_start..line_end];
+            sink.event(SinkEvent::Match {
+                line_number,
+                line: Cow::Borrowed(line.strip_suffix('\r').unwrap_or(line)),
+            })?;
+            summary.matched_lines += 1;
+            start = line_end + 1;
+            line_number += 1;
+        }
+        summary.total_lines = line_number - 1;
+        // Lines after the last match are only counted if they were searched.
+        if limit_reached(summary) {
+            return Ok(ControlFlow::Break(()));
+        }
+        let rest = content.get(start..).unwrap_or_default();
+        let unterminated = !rest.is_empty() && !rest.ends_with(b"\n");
+        summary.total_lines += memchr_iter(b'\n', rest).count() as u64 + u64::from(unterminated);
+        match valid {
+            true => Ok(ControlFlow::Continue(())),
+            false => Err(GrrsError::EncodingError {
+                line_number: summary.total_lines + 1,
+            }),
+        }
+    }
+
+    /// Searches lines that have already been numbered, sending events to the
+    /// sink and finishing it once the lines run out.
+    ///
+    /// Numbering is left to the caller so lines can be dropped before they're
+    /// considered, such as those outside a grrs_core::timestamp::TimeWindow, while
+    /// matches keep their original line numbers. Context never crosses a gap
+    /// in the numbering without a break.
+    pub fn search_numbered<S: AsRef<str>>(
+        &self,
+        matcher: &dyn Matcher,
+        lines: impl Iterator<Item = Result<(u64, S), GrrsError>>,
+        sink: &mut dyn Sink,
+    ) -> Result<SearchSummary, GrrsError> {
+        let mut progress = Progress::new(&self.options)?;
+        for line in lines {
+            let (num, line) = line?;
+            if progress.line(matcher, num, line, sink)?.is_break() {
+                break;
+            }
+        }
+
+        sink.finish()?;
+
+        Ok(progress.summary)
+    }
+}
+
+/// Where a search has got to between lines, so lines can be fed to it one at a
+/// time by either an iterator or an asynchronous reader.
+pub(crate) struct Progress<'o, S> {
+    options: &'o SearchOptions,
+    before: VecDeque<(u64, S)>,
+    after_remaining: usize,
+    last_written: Option<u64>,
+    /// The matcher for the line ending the context after a match, if any
+    until: Option<Box<dyn Matcher>>,
+    pub(crate) summary: SearchSummary,
+}
+
+impl<'o, S: AsRef<str>> Progress<'o, S> {
+    pub(crate) fn new(options: &'o SearchOptions) -> Result<Progress<'o, S>, GrrsError> {
+        Ok(Progress {
+            options,
+            before: VecDeque::with_capacity(options.before()),
+            after_remaining: 0,
+            last_written: None,
+            until: options.context_until()?,
+            summary: SearchSummary::default(),
+        })
+    }
+
+    /// Considers the next line, sending any events it causes to the sink, and
+    /// breaks once no later line could be reported.
+    pub(crate) fn line<M: Matcher + ?Sized, K: Sink + ?Sized>(
+        &mut self,
+        matcher: &M,
+        num: u64,
+        line: S,
+        sink: &mut K,
+    ) -> Result<ControlFlow<()>, GrrsError> {
+        let (before_context, after_context) = match self.until {
+            // Context runs on until a line ends it.
+            Some(_) => (self.options.before(), usize::MAX),
+            None => (self.options.before(), self.options.after()),
+        };
+        let has_context = before_context > 0 || after_context > 0;
+        if self.last_written.is_some_and(|last| last + 1 < num) {
+            self.after_remaining = 0;
+        }
+        if self.before.back().is_some_and(|(n, _)| n + 1 < num) {
+            self.before.clear();
+        }
+        let limit_reached = self
+            .options
+            .max_count
+            .is_some_and(|max| self.summary.matched_lines >= max);
+        if limit_reached && self.after_remaining == 0 {
+            return Ok(ControlFlow::Break(()));
+        }
+        self.summary.total_lines += 1;
+        let is_match = matcher.is_match(line.as_ref()) != self.options.invert_match;
+        if self.after_remaining > 0
+            && (self.until.as_ref()).is_some_and(|until| until.is_match(line.as_ref()))
+        {
+            self.after_remaining = 0;
+        }
+        if is_match && !limit_reached {
+            let group_start = self.before.front().map_or(num, |(n, _)| *n);
+            if has_context && self.last_written.is_some_and(|last| last + 1 < group_start) {
+                sink.event(SinkEvent::Break)?;
+            }
+            for (line_number, context) in self.before.drain(..) {
+                let line = Cow::Borrowed(context.as_ref());
+                sink.event(SinkEvent::Context { line_number, line })?;
+            }
+            let line = Cow::Borrowed(line.as_ref());
+            sink.event(SinkEvent::Match {
+                line_number: num,
+                line,
+            })?;
+            self.summary.matched_lines += 1;
+            self.last_written = Some(num);
+            self.after_remaining = after_context;
+        } else if self.after_remaining > 0 {
+            let line = Cow::Borrowed(line.as_ref());
+            sink.event(SinkEvent::Context {
+                line_number: num,
+                line,
+            })?;
+            self.last_written = Some(num);
+            self.after_remaining -= 1;
+        } else if self.options.passthru {
+            let line = Cow::Borrowed(line.as_ref());
+            sink.event(SinkEvent::Context {
+                line_number: num,
+                line,
+            })?;
+            self.last_written = Some(num);
+        } else if before_context > 0 {
+            if self.before.len() == before_context {
+                self.before.pop_front();
+            }
+            self.before.push_back((num, line));
+        }
+
+        Ok(ControlFlow::Continue(()))
+    }
+}
+
+/// How many lines a search read and how many of them matched.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let summary = grrs_core::Searcher::default().search("um", "lorem ipsum\ndolor\nsum", &mut output)?;
+/// assert_eq!(summary, grrs_core::SearchSummary { matched_lines: 2, total_lines: 3 });
+/// assert!(summary.found());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SearchSummary {
+    pub matched_lines: u64,
+    pub total_lines: u64,
+}
+
+impl SearchSummary {
+    /// Reports whether any line matched.
+    pub fn found(&self) -> bool {
+        self.matched_lines > 0
+    }
+}
+
+/// Numbers the lines read from a reader from 1, for use with
+/// grrs_core::Searcher::search_numbered(). Lines end at `\n` or `\r\n`, neither
+/// of which is kept, and a final `\r` is dropped too. Lines that aren't valid
+/// UTF-8 produce grrs_core::GrrsError::EncodingError.
+pub fn numbered_lines(
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<(u64, String), GrrsError>> {
+    (1..).zip(reader.lines()).map(|(num, line)| {
+        let mut line = line.map_err(|err| GrrsError::reading(err, num))?;
+        if line.ends_with('\r') {
+            line.pop();
+        }
+
+        Ok((num, line))
+    })
+}
+
+/// Numbers the lines read from a reader from 1, like grrs_core::numbered_lines(),
+/// but replaces bytes that aren't valid UTF-8 with U+FFFD instead of stopping
+/// at them, so the rest of the line and the file can still be searched.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let reader = &b"fine\nbad \xff\r\n"[..];
+/// let lines = grrs_core::numbered_lines_lossy(reader).collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(lines, [(1, "fine".to_string()), (2, "bad \u{fffd}".to_string())]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn numbered_lines_lossy(
+    mut reader: impl BufRead,
+) -> impl Iterator<Item = Result<(u64, String), GrrsError>> {
+    let mut buf = Vec::new();
+    (1..).map_while(move |num| {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => None,
+            Ok(_) => {
+                // Terminators are dropped as BufRead::lines() drops them.
+                let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                Some(Ok((num, String::from_utf8_lossy(line).into_owned())))
+            }
+            Err(err) => Some(Err(GrrsError::reading(err, num))),
+        }
+    })
+}
+
+/// A single matching line, with the byte ranges of every match within it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match<'a> {
+    pub line_number: u64,
+    pub line: Cow<'a, str>,
+    pub spans: Vec<Range<usize>>,
+}
+
+impl Match<'_> {
+    /// Copies a borrowed line so the match can outlive the searched content.
+    pub fn into_owned(self) -> Match<'static> {
+        Match {
+            line_number: self.line_number,
+            line: Cow::Owned(self.line.into_owned()),
+            spans: self.spans,
+        }
+    }
+}
+
+/// Lazily yields every matching line of a string slice, numbering lines from 1,
+/// so results can be filtered, mapped or collected without going through a sink.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::LiteralMatcher;
+/// let matcher = LiteralMatcher::new("um");
+/// let found: Vec<_> = grrs_core::matches_in("lorem ipsum\ndolor\nsum summa", &matcher)
+///     .map(|m| (m.line_number, m.spans))
+///     .collect();
+/// assert_eq!(found, vec![(1, vec![9..11]), (3, vec![1..3, 5..7])]);
+/// ```
+pub fn matches_in<'a>(
+    content: &'a str,
+    matcher: &'a dyn Matcher,
+) -> impl Iterator<Item = Match<'a>> + 'a {
+    (1..)
+        .zip(content.lines())
+        .filter_map(move |(line_number, line)| {
+            let spans: Vec<_> = matcher.find_iter(line).collect();
+            match spans.is_empty() {
+                true => None,
+                false => Some(Match {
+                    line_number,
+                    line: Cow::Borrowed(line),
+                    spans,
+                }),
+            }
+        })
+}
+
+/// Calls `callback` with every matching line of a string slice in turn,
+/// stopping as soon as it returns `ControlFlow::Break`. Returns whether the
+/// search ran to completion or was stopped early.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::LiteralMatcher;
+/// use std::ops::ControlFlow;
+/// let mut first = None;
+/// let flow = grrs_core::search_with("a test\nmore\nanother test", &LiteralMatcher::new("test"), |m| {
+///     first = Some(m.line_number);
+///     ControlFlow::Break(())
+/// });
+/// assert_eq!(flow, ControlFlow::Break(()));
+/// assert_eq!(first, Some(1));
+/// ```
+pub fn search_with<F>(content: &str, matcher: &dyn Matcher, mut callback: F) -> ControlFlow<()>
+where
+    F: FnMut(&Match) -> ControlFlow<()>,
+{
+    matches_in(content, matcher).try_for_each(|found| callback(&found))
+}
+
+/// Calls `callback` with every matching line read from a reader in turn, like
+/// grrs_core::search_with(), reading no further once it returns `ControlFlow::Break`.
+pub fn search_reader_with<F>(
+    reader: impl BufRead,
+    matcher: &dyn Matcher,
+    mut callback: F,
+) -> Result<ControlFlow<()>, GrrsError>
+where
+    F: FnMut(&Match) -> ControlFlow<()>,
+{
+    for line in numbered_lines(reader) {
+        let (line_number, line) = line?;
+        let spans: Vec<_> = matcher.find_iter(&line).collect();
+        if spans.is_empty() {
+            continue;
+        }
+        let found = Match {
+            line_number,
+            line: Cow::Borrowed(&line),
+            spans,
+        };
+        if callback(&found).is_break() {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Replaces every match in a line with a replacement, which is inserted
+/// literally. The line is borrowed if nothing matched.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::LiteralMatcher;
+/// let replaced = grrs_core::replace_matches("sum summa", &LiteralMatcher::new("um"), "UM");
+/// assert_eq!(replaced, "sUM sUMma");
+/// ```
+pub fn replace_matches<'a>(
+    line: &'a str,
+    matcher: &dyn Matcher,
+    replacement: &str,
+) -> Cow<'a, str> {
+    replace_matches_with(line, matcher, |_| Cow::Borrowed(replacement))
+}
+
+/// Replaces every match in a line with what `replace` returns for the text
+/// it matched. The line is borrowed if nothing matched.
+///
+/// # Example
+///
+/// ```rust
+/// use grrs_core::matcher::LiteralMatcher;
+/// let matcher = LiteralMatcher::new("color").case_insensitive(true);
+/// let replaced = grrs_core::replace_matches_with("Color, COLOR", &matcher, |matched| {
+///     grrs_core::preserve_case(matched, "colour").into()
+/// });
+/// assert_eq!(replaced, "Colour, COLOUR");
+/// ```
+pub fn replace_matches_with<'a, 'r>(
+    line: &'a str,
+    matcher: &dyn Matcher,
+    mut replace: impl FnMut(&str) -> Cow<'r, str>,
+) -> Cow<'a, str> {
+    let mut spans = matcher.find_iter(line).peekable();
+    if spans.peek().is_none() {
+        return Cow::Borrowed(line);
+    }
+    let mut replaced = String::with_capacity(line.len());
+    let mut last = 0;
+    for span in spans {
+        replaced.push_str(&line[last..span.start]);
+        replaced.push_str(&replace(&line[span.start..span.end]));
+        last = span.end;
+    }
+    replaced.push_str(&line[last..]);
+
+    Cow::Owned(replaced)
+}
+
+/// Gives a replacement the casing of the text it replaces: upper case for
+/// text of more than one letter all in upper case, lower case for text all in
+/// lower case, and a capital first letter for text starting with one. Text
+/// cased any other way leaves the replacement as it is.
+pub fn preserve_case(matched: &str, replacement: &str) -> String {
+    let mut letters = matched.chars().filter(|c| c.is_alphabetic()).peekable();
+    let first = match letters.peek() {
+        Some(&first) => first,
+        None => return replacement.to_string(),
+    };
+    let (mut upper, mut lower) = (0, 0);
+    for letter in letters {
+        upper += usize::from(letter.is_uppercase());
+        lower += usize::from(letter.is_lowercase());
+    }
+    match (upper, lower) {
+        (upper, 0) if upper > 1 => replacement.to_uppercase(),
+        (0, _) => replacement.to_lowercase(),
+        _ if first.is_uppercase() => {
+            let mut chars = replacement.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        _ => replacement.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_case_insensitive() -> Result<(), GrrsError> {
+        let searcher = Searcher::builder().case_insensitive(true).build();
+        let mut result = Vec::new();
+        searcher.search("LOREM", "lorem ipsum\ndolor sit amet", &mut result)?;
+        assert_eq!(result, b"LINE# 1: lorem ipsum\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_with_context() -> Result<(), GrrsError> {
+        let content = "a\nb\nmatch\nc\nd\ne\nf\nmatch\ng";
+        let searcher = Searcher::builder().context(1).build();
+        let mut result = Vec::new();
+        searcher.search("match", content, &mut result)?;
+        assert_eq!(
+            String::from_utf8_lossy(&result),
+            "LINE# 2- b\nLINE# 3: match\nLINE# 4- c\n--\nLINE# 7- f\nLINE# 8: match\nLINE# 9- g\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_with_context_until() -> Result<(), GrrsError> {
+        let content = "1 start\n1 Error: lost\n  at a\n  at b\n2 done\n2 Error: again\n  at c";
+        let options = SearchOptions {
+            after_context_until: Some(r"^\d ".to_string()),
+            ..SearchOptions::default()
+        };
+        let mut result = Vec::new();
+        let summary = Searcher::new(options).search("Error", content, &mut result)?;
+        assert_eq!(
+            String::from_utf8_lossy(&result),
+            "LINE# 2: 1 Error: lost\nLINE# 3-   at a\nLINE# 4-   at b\n--\n\
+             LINE# 6: 2 Error: again\nLINE# 7-   at c\n"
+        );
+        assert_eq!(summary.total_lines, 7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_passing_every_line_through() -> Result<(), GrrsError> {
+        let searcher = Searcher::builder().passthru(true).build();
+        let mut result = Vec::new();
+        let summary = searcher.search("match", "a\nmatch\nb\nmatch", &mut result)?;
+        assert_eq!(
+            String::from_utf8_lossy(&result),
+            "LINE# 1- a\nLINE# 2: match\nLINE# 3- b\nLINE# 4: match\n"
+        );
+        assert_eq!(summary.matched_lines, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_inverted_with_max_count() -> Result<(), GrrsError> {
+        let searcher = Searcher::builder().invert_match(true).max_count(2).build();
+        let mut result = Vec::new();
+        let summary = searcher.search("b", "a\nb\nc\nd", &mut result)?;
+        assert_eq!(result, b"LINE# 1: a\nLINE# 3: c\n");
+        assert_eq!(
+            summary,
+            SearchSummary {
+                matched_lines: 2,
+                total_lines: 3,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn iterate_matches() {
+        let matcher = LiteralMatcher::new("dolor");
+        let found: Vec<_> = matches_in("lorem ipsum\ndolor sit dolor", &matcher).collect();
+        assert_eq!(
+            found,
+            vec![Match {
+                line_number: 2,
+                line: "dolor sit dolor".into(),
+                spans: vec![0..5, 10..15],
+            }]
+        );
+    }
+
+    #[test]
+    fn stop_searching_early() -> Result<(), GrrsError> {
+        let matcher = LiteralMatcher::new("test");
+        let reader = "test 1\ntest 2\ntest 3".as_bytes();
+        let mut seen = Vec::new();
+        let flow = search_reader_with(reader, &matcher, |found| {
+            seen.push(found.line_number);
+            match found.line_number {
+                2 => ControlFlow::Break(()),
+                _ => ControlFlow::Continue(()),
+            }
+        })?;
+        assert_eq!(flow, ControlFlow::Break(()));
+        assert_eq!(seen, vec![1, 2]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn strip_line_terminators() -> Result<(), GrrsError> {
+        let reader = &b"one\r\ntwo\nthree\r"[..];
+        let lines = numbered_lines(reader).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            lines,
+            [(1, "one".into()), (2, "two".into()), (3, "three".into())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_invalid_utf8() {
+        let reader = &b"fine\n\xff\xfe\n"[..];
+        let mut sink = crate::sink::CollectSink::default();
+        let result = Searcher::default().search_sink(&LiteralMatcher::new("x"), reader, &mut sink);
+        assert!(matches!(
+            result,
+            Err(GrrsError::EncodingError { line_number: 2 })
+        ));
+    }
+
+    #[test]
+    fn search_a_slice_as_lines() -> Result<(), GrrsError> {
+        let searchers = [
+            Searcher::default(),
+            Searcher::builder().max_count(2).build(),
+            Searcher::builder().context(1).build(),
+        ];
+        for content in ["a test\r\n\nno\ntest test\nlast test", "test\n\n"] {
+            for searcher in &searchers {
+                for pattern in ["test", "", "no", "x"] {
+                    let matcher = searcher.literal(pattern);
+                    let mut by_line = crate::sink::CollectSink::default();
+                    let lines = (1..).zip(content.lines()).map(Ok);
+                    let expected = searcher.search_numbered(&matcher, lines, &mut by_line)?;
+                    let mut by_slice = crate::sink::CollectSink::default();
+                    let summary =
+                        searcher.search_slice(&matcher, content.as_bytes(), &mut by_slice)?;
+                    assert_eq!((summary, &by_slice.events), (expected, &by_line.events));
+                    let mut by_buffer = crate::sink::CollectSink::default();
+                    let reader = std::io::BufReader::with_capacity(3, content.as_bytes());
+                    let summary = searcher.search_buffered(&matcher, reader, &mut by_buffer)?;
+                    assert_eq!((summary, by_buffer.events), (expected, by_line.events));
+                }
+            }
+        }
+        let matcher = LiteralMatcher::new("x");
+        let mut sink = crate::sink::CollectSink::default();
+        let result = Searcher::default().search_slice(&matcher, b"x\n\xff", &mut sink);
+        assert!(matches!(
+            result,
+            Err(GrrsError::EncodingError { line_number: 2 })
+        ));
+        let reader = std::io::BufReader::with_capacity(1, &b"x\n\xff"[..]);
+        let result = Searcher::default().search_buffered(&matcher, reader, &mut sink);
+        assert!(matches!(
+            result,
+            Err(GrrsError::EncodingError { line_number: 2 })
+        ));
+        assert_eq!(sink.events.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_invalid_utf8() -> Result<(), GrrsError> {
+        let reader = &b"fine\n\xff\xfe test\nlast"[..];
+        let mut sink = crate::sink::CollectSink::default();
+        let lines = numbered_lines_lossy(reader);
+        let summary =
+            Searcher::default().search_numbered(&LiteralMatcher::new("t"), lines, &mut sink)?;
+        assert_eq!(summary.total_lines, 3);
+        assert_eq!(
+            sink.events[0],
+            SinkEvent::Match {
+                line_number: 2,
+                line: "\u{fffd}\u{fffd} test".into()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn replace_every_match() {
+        let matcher = LiteralMatcher::new("o");
+        assert_eq!(replace_matches("lorem dolor", &matcher, "0"), "l0rem d0l0r");
+        assert!(matches!(
+            replace_matches("ipsum", &matcher, "0"),
+            Cow::Borrowed("ipsum")
+        ));
+    }
+
+    #[test]
+    fn replace_preserving_case() {
+        for (matched, expected) in [
+            ("color", "colour"),
+            ("Color", "Colour"),
+            ("COLOR", "COLOUR"),
+            ("C", "Colour"),
+            ("coLOR", "colour"),
+            ("123", "colour"),
+        ] {
+            assert_eq!(preserve_case(matched, "colour"), expected, "{}", matched);
+        }
+        assert_eq!(preserve_case("Color", "hue-shift"), "Hue-shift");
+    }
+
+    #[test]
+    fn share_a_searcher() -> Result<(), GrrsError> {
+        fn assert_shareable<T: Send + Sync + Clone>() {}
+        assert_shareable::<Searcher>();
+
+        let searcher = Searcher::builder().case_insensitive(true).build();
+        let matcher = searcher.matcher("lorem")?;
+        let found = std::thread::scope(|scope| {
+            let workers: Vec<_> = ["Lorem", "ipsum"]
+                .iter()
+                .map(|content| {
+                    let (searcher, matcher) = (&searcher, &matcher);
+                    scope.spawn(move || {
+                        let mut output = Vec::new();
+                        searcher.search_matcher(&**matcher, content, &mut output)?;
+                        Ok::<_, GrrsError>(output)
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        assert_eq!(found, vec![b"LINE# 1: Lorem\n".to_vec(), Vec::new()]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,477 @@
+#[cfg(feature = "fs")]
+use crate::encode::{Encoding, EncodingWriter};
+use crate::error::{GrrsError, Result};
+use crate::format::{Formatter, LineFormatter};
+use crate::matcher::{Matcher, PatternSet};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+#[cfg(feature = "fs")]
+use std::fs::{File, OpenOptions};
+#[cfg(feature = "fs")]
+use std::io::BufWriter;
+use std::io::{Stdout, Write};
+use std::ops::AddAssign;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// A structured event produced while searching, in the order lines were read.
+/// (De)serializes as an object with a `type` of `match`, `context`, `break`
+/// or `heading`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkEvent<'a> {
+    /// A line that matched the pattern
+    Match {
+        line_number: u64,
+        line: Cow<'a, str>,
+    },
+    /// A line written as context around a match
+    Context {
+        line_number: u64,
+        line: Cow<'a, str>,
+    },
+    /// A gap between two groups of context that aren't adjacent
+    Break,
+    /// The nearest heading above a group of lines, such as the function or
+    /// section they're in
+    Heading {
+        line_number: u64,
+        line: Cow<'a, str>,
+    },
+}
+
+impl SinkEvent<'_> {
+    /// Copies any borrowed line so the event can outlive the searched content.
+    pub fn into_owned(self) -> SinkEvent<'static> {
+        match self {
+            SinkEvent::Match { line_number, line } => SinkEvent::Match {
+                line_number,
+                line: Cow::Owned(line.into_owned()),
+            },
+            SinkEvent::Context { line_number, line } => SinkEvent::Context {
+                line_number,
+                line: Cow::Owned(line.into_owned()),
+            },
+            SinkEvent::Break => SinkEvent::Break,
+            SinkEvent::Heading { line_number, line } => SinkEvent::Heading {
+                line_number,
+                line: Cow::Owned(line.into_owned()),
+            },
+        }
+    }
+}
+
+/// A destination for search events, such as a terminal, a file or a collection.
+pub trait Sink {
+    /// Receives a single event.
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError>;
+
+    /// Called once the search is complete, to flush any buffered output.
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        Ok(())
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for &mut S {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        (**self).event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        (**self).finish()
+    }
+}
+
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        (**self).event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        (**self).finish()
+    }
+}
+
+/// Renders events to any writer through a grrs_core::format::Formatter, by default
+/// as `LINE# 4: text` for matches and `LINE# 3- text` for context, with `--`
+/// for breaks.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sink::{Sink, SinkEvent, WriterSink};
+/// # fn main() -> Result<(), Error> {
+/// let mut output = Vec::new();
+/// let mut sink = WriterSink::new(&mut output);
+/// sink.event(SinkEvent::Context { line_number: 1, line: "before".into() })?;
+/// sink.event(SinkEvent::Match { line_number: 2, line: "found".into() })?;
+/// sink.finish()?;
+/// drop(sink);
+/// assert_eq!(output, b"LINE# 1- before\nLINE# 2: found\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WriterSink<W: Write, F: Formatter = LineFormatter> {
+    writer: W,
+    formatter: F,
+}
+
+impl<W: Write> WriterSink<W> {
+    /// Wraps the supplied writer.
+    pub fn new(writer: W) -> WriterSink<W> {
+        WriterSink::with_formatter(writer, LineFormatter)
+    }
+}
+
+impl<W: Write, F: Formatter> WriterSink<W, F> {
+    /// Wraps the supplied writer, rendering events with the supplied formatter.
+    pub fn with_formatter(writer: W, formatter: F) -> WriterSink<W, F> {
+        WriterSink { writer, formatter }
+    }
+
+    /// Returns the wrapped writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl WriterSink<Stdout> {
+    /// Writes to standard output.
+    pub fn stdout() -> WriterSink<Stdout> {
+        WriterSink::new(std::io::stdout())
+    }
+}
+
+impl<W: Write, F: Formatter> Sink for WriterSink<W, F> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        self.formatter.format(&event, &mut self.writer)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.formatter.finish(&mut self.writer)?;
+        self.writer.flush()?;
+
+        Ok(())
+    }
+}
+
+/// Renders events like grrs_core::sink::WriterSink into a buffered file,
+/// written in UTF-8 unless another encoding is given.
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct FileSink<F: Formatter = LineFormatter> {
+    inner: WriterSink<EncodingWriter<BufWriter<File>>, F>,
+}
+
+#[cfg(feature = "fs")]
+impl FileSink {
+    /// Creates the file, truncating it if it already exists.
+    pub fn create(path: impl AsRef<Path>) -> Result<FileSink, GrrsError> {
+        Ok(FileSink::from_file(File::create(path)?))
+    }
+
+    /// Opens the file for appending, creating it if it doesn't exist.
+    pub fn append(path: impl AsRef<Path>) -> Result<FileSink, GrrsError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(FileSink::from_file(file))
+    }
+
+    /// Writes to a file that is already open, at its current position.
+    pub fn from_file(file: File) -> FileSink {
+        let writer = EncodingWriter::new(BufWriter::new(file), Encoding::Utf8);
+        FileSink {
+            inner: WriterSink::new(writer),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<F: Formatter> FileSink<F> {
+    /// Renders events with the supplied formatter instead.
+    pub fn formatter<G: Formatter>(self, formatter: G) -> FileSink<G> {
+        FileSink {
+            inner: WriterSink::with_formatter(self.inner.writer, formatter),
+        }
+    }
+
+    /// Writes the file in this encoding instead, from the start of what's
+    /// written.
+    pub fn encoding(self, encoding: Encoding) -> FileSink<F> {
+        let writer = EncodingWriter::new(self.inner.writer.into_inner(), encoding);
+        FileSink {
+            inner: WriterSink::with_formatter(writer, self.inner.formatter),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<F: Formatter> Sink for FileSink<F> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        self.inner.event(event)
+    }
+
+    fn finish(&mut self) -> Result<(), GrrsError> {
+        self.inner.finish()
+    }
+}
+
+/// Collects owned copies of every event, for callers that want to inspect
+/// results rather than render them.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sink::{CollectSink, SinkEvent};
+/// # fn main() -> Result<(), Error> {
+/// let mut sink = CollectSink::default();
+/// grrs_core::Searcher::default().search_sink(
+///     &grrs_core::matcher::LiteralMatcher::new("sit"),
+///     "lorem ipsum\ndolor sit amet".as_bytes(),
+///     &mut sink,
+/// )?;
+/// assert_eq!(sink.events, vec![SinkEvent::Match { line_number: 2, line: "dolor sit amet".into() }]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CollectSink {
+    pub events: Vec<SinkEvent<'static>>,
+}
+
+impl Sink for CollectSink {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        self.events.push(event.into_owned());
+
+        Ok(())
+    }
+}
+
+/// Totals for the lines a search matched, as grrs_core::sink::StatsSink
+/// counts them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LineStats {
+    pub lines: u64,
+    /// The matches within the lines, so a line with three counts three. A
+    /// line matched with nothing in it to count, as with inverted matching,
+    /// counts once.
+    pub matches: u64,
+    /// Runs of characters between whitespace, as `wc` counts words
+    pub words: u64,
+    /// The bytes of the lines, each with the newline ending it
+    pub bytes: u64,
+}
+
+impl AddAssign for LineStats {
+    fn add_assign(&mut self, other: LineStats) {
+        self.lines += other.lines;
+        self.matches += other.matches;
+        self.words += other.words;
+        self.bytes += other.bytes;
+    }
+}
+
+/// Adds up grrs_core::sink::LineStats for every matching line, finding the
+/// matches within them with the matcher searched with.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::LiteralMatcher;
+/// use grrs_core::sink::StatsSink;
+/// # fn main() -> Result<(), Error> {
+/// let matcher = LiteralMatcher::new("o");
+/// let mut sink = StatsSink::new(&matcher);
+/// grrs_core::Searcher::default().search_sink(&matcher, "foo o\nbar\nbo".as_bytes(), &mut sink)?;
+/// assert_eq!((sink.stats.lines, sink.stats.matches), (2, 4));
+/// assert_eq!((sink.stats.words, sink.stats.bytes), (3, 9));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatsSink<M: Matcher> {
+    matcher: M,
+    pub stats: LineStats,
+}
+
+impl<M: Matcher> StatsSink<M> {
+    /// Creates a sink that has seen no lines yet.
+    pub fn new(matcher: M) -> StatsSink<M> {
+        StatsSink {
+            matcher,
+            stats: LineStats::default(),
+        }
+    }
+}
+
+impl<M: Matcher> Sink for StatsSink<M> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { line, .. } = event {
+            self.stats += LineStats {
+                lines: 1,
+                matches: self.matcher.find_iter(&line).count().max(1) as u64,
+                words: line.split_whitespace().count() as u64,
+                bytes: line.len() as u64 + 1,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps track of which patterns of a grrs_core::matcher::PatternSet the
+/// matching lines have had between them, for finding the files that contain
+/// every pattern somewhere, if not on one line.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::{LiteralMatcher, PatternSet};
+/// use grrs_core::sink::PatternHits;
+/// # fn main() -> Result<(), Error> {
+/// let patterns = PatternSet::new(vec![
+///     Box::new(LiteralMatcher::new("foo")),
+///     Box::new(LiteralMatcher::new("bar")),
+/// ]);
+/// let mut sink = PatternHits::new(&patterns);
+/// grrs_core::Searcher::default().search_sink(&patterns, "foo\nbaz\nbar".as_bytes(), &mut sink)?;
+/// assert!(sink.all());
+/// # Ok(())
+/// # }
+/// ```
+pub struct PatternHits<'a> {
+    patterns: &'a PatternSet,
+    hit: Vec<bool>,
+}
+
+impl PatternHits<'_> {
+    /// Creates a sink that has seen none of the patterns yet.
+    pub fn new(patterns: &PatternSet) -> PatternHits<'_> {
+        PatternHits {
+            patterns,
+            hit: vec![false; patterns.len()],
+        }
+    }
+
+    /// Reports whether every pattern has been seen.
+    pub fn all(&self) -> bool {
+        self.hit.iter().all(|&hit| hit)
+    }
+}
+
+impl Sink for PatternHits<'_> {
+    fn event(&mut self, event: SinkEvent) -> Result<(), GrrsError> {
+        if let SinkEvent::Match { line, .. } = event {
+            for id in self.patterns.matching(&line) {
+                self.hit[id] = true;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "fs")]
+    use std::fs::remove_file;
+    #[cfg(feature = "fs")]
+    use std::io::Read;
+
+    #[test]
+    fn round_trip_events() -> Result<(), serde_json::Error> {
+        let event = SinkEvent::Context {
+            line_number: 2,
+            line: "two".into(),
+        };
+        let json = serde_json::to_string(&event)?;
+        assert_eq!(json, r#"{"type":"context","line_number":2,"line":"two"}"#);
+        assert_eq!(serde_json::from_str::<SinkEvent>(&json)?, event);
+
+        Ok(())
+    }
+
+    #[test]
+    fn render_all_events() -> Result<(), GrrsError> {
+        let mut sink = WriterSink::new(Vec::new());
+        sink.event(SinkEvent::Match {
+            line_number: 1,
+            line: "one".into(),
+        })?;
+        sink.event(SinkEvent::Break)?;
+        sink.event(SinkEvent::Context {
+            line_number: 5,
+            line: "five".into(),
+        })?;
+        assert_eq!(sink.into_inner(), b"LINE# 1: one\n--\nLINE# 5- five\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_up_line_stats() -> Result<(), GrrsError> {
+        let matcher = crate::matcher::LiteralMatcher::new("a");
+        let mut sink = StatsSink::new(&matcher);
+        let searcher = crate::Searcher::builder().build();
+        searcher.search_sink(&matcher, "aa\nb\nb a b".as_bytes(), &mut sink)?;
+        let stats = LineStats {
+            lines: 2,
+            matches: 3,
+            words: 4,
+            bytes: 9,
+        };
+        assert_eq!(sink.stats, stats);
+
+        // Inverted matches have nothing to count but themselves.
+        let mut sink = StatsSink::new(&matcher);
+        let searcher = crate::Searcher::builder().invert_match(true).build();
+        searcher.search_sink(&matcher, "aa\nb\nc".as_bytes(), &mut sink)?;
+        assert_eq!(sink.stats.matches, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn track_pattern_hits() -> Result<(), GrrsError> {
+        use crate::matcher::LiteralMatcher;
+        let patterns = PatternSet::new(vec![
+            Box::new(LiteralMatcher::new("a")),
+            Box::new(LiteralMatcher::new("b")),
+            Box::new(LiteralMatcher::new("c")),
+        ]);
+        let mut sink = PatternHits::new(&patterns);
+        let searcher = crate::Searcher::builder().build();
+        searcher.search_sink(&patterns, "ab\nx\nb".as_bytes(), &mut sink)?;
+        assert!(!sink.all());
+        searcher.search_sink(&patterns, "c".as_bytes(), &mut sink)?;
+        assert!(sink.all());
+
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn append_to_a_file() -> Result<(), GrrsError> {
+        let outfile = std::path::PathBuf::from("test_sink_file.txt");
+        for line_number in 1..=2 {
+            let mut sink = FileSink::append(&outfile)?;
+            sink.event(SinkEvent::Match {
+                line_number,
+                line: "lorem".into(),
+            })?;
+            sink.finish()?;
+        }
+        let mut contents = String::new();
+        File::open(&outfile)?.read_to_string(&mut contents)?;
+        remove_file(outfile)?;
+        assert_eq!(contents, "LINE# 1: lorem\nLINE# 2: lorem\n");
+
+        Ok(())
+    }
+}
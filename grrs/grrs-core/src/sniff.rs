@@ -0,0 +1,207 @@
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+/// What a file holds, going by its first bytes rather than its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    /// UTF-8 or ASCII text, or anything else without a NUL byte
+    Text,
+    /// UTF-16 text starting with a little-endian byte order mark
+    Utf16Le,
+    /// UTF-16 text starting with a big-endian byte order mark
+    Utf16Be,
+    Gzip,
+    Zip,
+    Elf,
+    Png,
+    Jpeg,
+    Pdf,
+    /// Anything else with a NUL byte in it
+    Binary,
+}
+
+/// The leading bytes that give away each kind of file.
+const MAGIC: &[(&[u8], ContentType)] = &[
+    (b"\xff\xfe", ContentType::Utf16Le),
+    (b"\xfe\xff", ContentType::Utf16Be),
+    (b"\x1f\x8b", ContentType::Gzip),
+    (b"PK\x03\x04", ContentType::Zip),
+    (b"\x7fELF", ContentType::Elf),
+    (b"\x89PNG\r\n\x1a\n", ContentType::Png),
+    (b"\xff\xd8\xff", ContentType::Jpeg),
+    (b"%PDF-", ContentType::Pdf),
+];
+
+impl ContentType {
+    /// Classifies a file by the first bytes read from it, as grep does by a
+    /// NUL byte among them when nothing more specific is recognised.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use grrs_core::sniff::ContentType;
+    /// assert_eq!(ContentType::sniff(b"lorem ipsum\n"), ContentType::Text);
+    /// assert_eq!(ContentType::sniff(b"\x7fELF\x02\x01\x01\0"), ContentType::Elf);
+    /// assert_eq!(ContentType::sniff(b"\xff\xfeh\0i\0"), ContentType::Utf16Le);
+    /// ```
+    pub fn sniff(head: &[u8]) -> ContentType {
+        match MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+            Some((_, content)) => *content,
+            None if head.contains(&0) => ContentType::Binary,
+            None => ContentType::Text,
+        }
+    }
+
+    /// Reports whether the content can't be searched as text, even once
+    /// decoded or decompressed.
+    pub fn is_binary(self) -> bool {
+        match self {
+            ContentType::Text | ContentType::Utf16Le | ContentType::Utf16Be => false,
+            ContentType::Gzip => !cfg!(feature = "compression"),
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ContentType::Text => "text",
+            ContentType::Utf16Le => "UTF-16LE text",
+            ContentType::Utf16Be => "UTF-16BE text",
+            ContentType::Gzip => "gzip data",
+            ContentType::Zip => "a zip archive",
+            ContentType::Elf => "an ELF executable",
+            ContentType::Png => "a PNG image",
+            ContentType::Jpeg => "a JPEG image",
+            ContentType::Pdf => "a PDF document",
+            ContentType::Binary => "binary data",
+        })
+    }
+}
+
+/// Reads UTF-16 text as UTF-8, replacing unpaired surrogates and a stray last
+/// byte with U+FFFD. The byte order mark, if any, is left to the caller to
+/// skip.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sniff::Utf16Reader;
+/// use std::io::Read;
+/// # fn main() -> Result<(), Error> {
+/// let mut text = String::new();
+/// Utf16Reader::new(&b"h\0\xe9\0!\0"[..], false).read_to_string(&mut text)?;
+/// assert_eq!(text, "hé!");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Utf16Reader<R> {
+    inner: R,
+    big_endian: bool,
+    /// The bytes of a code unit, or the first unit of a surrogate pair, cut
+    /// off at the end of the last read
+    carry: Vec<u8>,
+    decoded: Vec<u8>,
+    /// How much of the decoded text has been read
+    pos: usize,
+}
+
+impl<R: BufRead> Utf16Reader<R> {
+    pub fn new(inner: R, big_endian: bool) -> Self {
+        Utf16Reader {
+            inner,
+            big_endian,
+            carry: Vec::new(),
+            decoded: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    /// Decodes the next of the inner reader's buffers, leaving nothing
+    /// decoded only at the end of input.
+    fn refill(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.pos = 0;
+        let big_endian = self.big_endian;
+        while self.decoded.is_empty() {
+            let available = self.inner.fill_buf()?;
+            if available.is_empty() {
+                if !self.carry.is_empty() {
+                    self.carry.clear();
+                    self.decoded.extend("\u{FFFD}".as_bytes());
+                }
+                return Ok(());
+            }
+            let mut bytes = std::mem::take(&mut self.carry);
+            bytes.extend_from_slice(available);
+            let read = available.len();
+            self.inner.consume(read);
+
+            let mut units: Vec<u16> = (bytes.chunks_exact(2))
+                .map(|pair| match big_endian {
+                    true => u16::from_be_bytes([pair[0], pair[1]]),
+                    false => u16::from_le_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            let mut whole = units.len() * 2;
+            // The other half of a pair may be yet to come.
+            if units
+                .last()
+                .is_some_and(|unit| (0xD800..0xDC00).contains(unit))
+            {
+                units.pop();
+                whole -= 2;
+            }
+            self.carry = bytes[whole..].to_vec();
+            for c in char::decode_utf16(units) {
+                let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+                self.decoded
+                    .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Utf16Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.decoded.len() {
+            self.refill()?;
+        }
+        let read = (&self.decoded[self.pos..]).read(buf)?;
+        self.pos += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_by_first_bytes() {
+        assert_eq!(ContentType::sniff(b""), ContentType::Text);
+        assert_eq!(ContentType::sniff(b"\xef\xbb\xbfa"), ContentType::Text);
+        assert_eq!(ContentType::sniff(b"\x1f\x8b\x08\0"), ContentType::Gzip);
+        assert_eq!(ContentType::sniff(b"\x89PNG\r\n\x1a\n"), ContentType::Png);
+        assert_eq!(ContentType::sniff(b"ELF\0\x01found"), ContentType::Binary);
+        assert!(ContentType::Png.is_binary());
+        assert!(!ContentType::Utf16Be.is_binary());
+    }
+
+    #[test]
+    fn decode_utf16_split_across_reads() -> io::Result<()> {
+        // "a😀" big-endian, then a lone high surrogate, read a byte at a time.
+        let bytes = b"\0a\xd8\x3d\xde\x00\xd8\x3d";
+        let reader = io::BufReader::with_capacity(1, &bytes[..]);
+        let mut text = String::new();
+        Utf16Reader::new(reader, true).read_to_string(&mut text)?;
+        assert_eq!(text, "a\u{1F600}\u{FFFD}");
+
+        Ok(())
+    }
+}
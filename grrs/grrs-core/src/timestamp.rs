@@ -0,0 +1,173 @@
+use crate::error::{GrrsError, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use regex::Regex;
+
+/// Default `strftime`-style format used to parse timestamps found in log lines.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Default regular expression used to locate the timestamp within a log line.
+pub const DEFAULT_REGEX: &str = r"\d{4}-\d{2}-\d{2}[ T]\d{2}:\d{2}:\d{2}";
+
+/// Formats accepted for the `--since` and `--until` bounds, tried in order.
+const BOUND_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+/// Parses a `--since`/`--until` bound such as `2024-01-01 00:00` or `2024-01-02`.
+/// A bare date is taken to mean midnight at the start of that day.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// # fn main() -> Result<(), Error> {
+/// let bound = grrs_core::timestamp::parse_bound("2024-01-02")?;
+/// assert_eq!(bound.to_string(), "2024-01-02 00:00:00");
+/// # Ok(())
+/// # }
+/// ```
+pub fn parse_bound(input: &str) -> Result<NaiveDateTime, GrrsError> {
+    let input = input.trim();
+    for format in BOUND_FORMATS.iter() {
+        if let Ok(bound) = NaiveDateTime::parse_from_str(input, format) {
+            return Ok(bound);
+        }
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| GrrsError::InvalidTimestamp(input.to_string()))
+}
+
+/// Restricts a search to the lines of a log whose timestamp falls within a
+/// window. The lower bound is inclusive and the upper bound is exclusive.
+///
+/// Lines without a timestamp of their own (stack traces, wrapped messages)
+/// belong to the most recent timestamped line before them, so multi-line
+/// records are kept or dropped as a whole. Lines before the first timestamp
+/// are dropped.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::timestamp::{parse_bound, TimeWindow, DEFAULT_FORMAT, DEFAULT_REGEX};
+/// # fn main() -> Result<(), Error> {
+/// let since = Some(parse_bound("2024-01-01 00:00")?);
+/// let until = Some(parse_bound("2024-01-02")?);
+/// let mut window = TimeWindow::new(since, until, DEFAULT_FORMAT, DEFAULT_REGEX)?;
+/// assert!(!window.contains("2023-12-31 23:59:59 ERROR late"));
+/// assert!(window.contains("2024-01-01 12:00:00 ERROR inside"));
+/// assert!(window.contains("    at stack frame"));
+/// assert!(!window.contains("2024-01-02 00:00:00 ERROR too late"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct TimeWindow {
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    format: String,
+    regex: Regex,
+    last: Option<NaiveDateTime>,
+}
+
+impl TimeWindow {
+    /// Creates a window from optional bounds, a `strftime`-style `format` and a
+    /// `regex` that locates the timestamp text within each line.
+    pub fn new(
+        since: Option<NaiveDateTime>,
+        until: Option<NaiveDateTime>,
+        format: &str,
+        regex: &str,
+    ) -> Result<TimeWindow, GrrsError> {
+        let regex = Regex::new(regex).map_err(|err| GrrsError::invalid_pattern(regex, err))?;
+
+        Ok(TimeWindow {
+            since,
+            until,
+            format: format.to_string(),
+            regex,
+            last: None,
+        })
+    }
+
+    /// Reports whether the supplied line falls within the window, remembering
+    /// its timestamp for any untimestamped lines that follow.
+    pub fn contains(&mut self, line: &str) -> bool {
+        if let Some(stamp) = self.timestamp(line) {
+            self.last = Some(stamp);
+        }
+        match self.last {
+            None => false,
+            Some(stamp) => {
+                self.since.is_none_or(|since| stamp >= since)
+                    && self.until.is_none_or(|until| stamp < until)
+            }
+        }
+    }
+
+    /// Extracts and parses the timestamp of a single line, if it has one. An
+    /// ISO 8601 `T` separator is also accepted in place of a space.
+    fn timestamp(&self, line: &str) -> Option<NaiveDateTime> {
+        let found = self.regex.find(line)?.as_str();
+        parse_with(found, &self.format)
+            .or_else(|| parse_with(&found.replacen('T', " ", 1), &self.format))
+    }
+}
+
+/// Parses text as a date and time, or as a bare date at midnight.
+fn parse_with(text: &str, format: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, format)
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, format)
+                .ok()?
+                .and_hms_opt(0, 0, 0)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bounds() -> Result<(), GrrsError> {
+        assert_eq!(
+            parse_bound("2024-01-01 00:00")?.to_string(),
+            "2024-01-01 00:00:00"
+        );
+        assert_eq!(
+            parse_bound("2024-01-02")?.to_string(),
+            "2024-01-02 00:00:00"
+        );
+        assert!(parse_bound("yesterday").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn filter_a_window() -> Result<(), GrrsError> {
+        let since = Some(parse_bound("2024-01-01")?);
+        let mut window = TimeWindow::new(since, None, DEFAULT_FORMAT, DEFAULT_REGEX)?;
+        assert!(!window.contains("no timestamp yet"));
+        assert!(!window.contains("2023-12-31T10:00:00 before"));
+        assert!(window.contains("2024-01-01T10:00:00 after"));
+        assert!(window.contains("continuation"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_format() -> Result<(), GrrsError> {
+        let since = Some(parse_bound("2024-01-01")?);
+        let mut window = TimeWindow::new(since, None, "%d/%m/%Y", r"\d{2}/\d{2}/\d{4}")?;
+        assert!(!window.contains("[31/12/2023] before"));
+        assert!(window.contains("[01/01/2024] after"));
+
+        Ok(())
+    }
+}
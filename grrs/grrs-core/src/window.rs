@@ -0,0 +1,276 @@
+use crate::error::{GrrsError, Result};
+use crate::matcher::Matcher;
+use crate::search::SearchSummary;
+use std::io::Read;
+use std::ops::{ControlFlow, Range};
+
+/// How many bytes are read at a time by default, which is also the longest
+/// match that can be found.
+const DEFAULT_BUFFER: usize = 64 * 1024;
+
+/// A match found by grrs_core::window::WindowSearcher, with part of its line
+/// around it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowMatch {
+    pub line_number: u64,
+    /// The byte offset of the match within its line
+    pub offset: u64,
+    /// The match and the text either side of it
+    pub excerpt: String,
+}
+
+/// Searches lines of any length in bounded memory, by reading a fixed amount
+/// at a time rather than whole lines. Each matching line is reported once,
+/// by its first match, with an excerpt of the line around it.
+///
+/// Matches can be as long as the buffer. A match isn't reported until the line
+/// has been read past its excerpt, so `$` and greedy repetition behave as they
+/// would on the whole line.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::matcher::LiteralMatcher;
+/// use grrs_core::window::WindowSearcher;
+/// # fn main() -> Result<(), Error> {
+/// let line = format!("{}needle{}\n", "a".repeat(100_000), "b".repeat(100_000));
+/// let mut found = Vec::new();
+/// WindowSearcher::new(3).search(&LiteralMatcher::new("needle"), line.as_bytes(), |m| {
+///     found.push(m);
+///     std::ops::ControlFlow::Continue(())
+/// })?;
+/// assert_eq!(found[0].offset, 100_000);
+/// assert_eq!(found[0].excerpt, "aaaneedlebbb");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct WindowSearcher {
+    buffer: usize,
+    excerpt: usize,
+}
+
+impl WindowSearcher {
+    /// Creates a searcher showing up to `excerpt` bytes either side of each
+    /// match.
+    pub fn new(excerpt: usize) -> WindowSearcher {
+        WindowSearcher {
+            buffer: DEFAULT_BUFFER.max(excerpt),
+            excerpt,
+        }
+    }
+
+    /// Sets how many bytes are read at a time, and so the longest match that
+    /// can be found. Memory use stays within about three times this plus
+    /// twice the excerpt.
+    pub fn buffer(mut self, bytes: usize) -> Self {
+        self.buffer = bytes.max(self.excerpt).max(1);
+        self
+    }
+
+    /// Searches a reader, calling back with each match found until the reader
+    /// runs out or the callback breaks. Input that isn't valid UTF-8 produces
+    /// grrs_core::GrrsError::EncodingError.
+    pub fn search<F>(
+        &self,
+        matcher: &dyn Matcher,
+        mut reader: impl Read,
+        mut callback: F,
+    ) -> Result<SearchSummary, GrrsError>
+    where
+        F: FnMut(WindowMatch) -> ControlFlow<()>,
+    {
+        let mut window = Window {
+            searcher: self,
+            matcher,
+            line_number: 1,
+            dropped: 0,
+            matched: false,
+            summary: SearchSummary::default(),
+        };
+        let mut buf: Vec<u8> = Vec::with_capacity(3 * self.buffer + 2 * self.excerpt + 4);
+        let mut chunk = vec![0; self.buffer];
+        loop {
+            let read = reader.read(&mut chunk)?;
+            buf.extend_from_slice(&chunk[..read]);
+            let at_end = read == 0;
+            let text = match std::str::from_utf8(&buf) {
+                Ok(text) => text,
+                // A character may be split across reads.
+                Err(err) if err.error_len().is_none() && !at_end => {
+                    std::str::from_utf8(&buf[..err.valid_up_to()]).unwrap_or_default()
+                }
+                Err(err) => {
+                    let lines = buf[..err.valid_up_to()].iter().filter(|&&b| b == b'\n');
+                    return Err(GrrsError::EncodingError {
+                        line_number: window.line_number + lines.count() as u64,
+                    });
+                }
+            };
+
+            let mut start = 0;
+            while let Some(end) = text[start..].find('\n').map(|end| start + end) {
+                if window
+                    .line(&text[start..end], true, &mut callback)
+                    .is_break()
+                {
+                    return Ok(window.summary);
+                }
+                start = end + 1;
+            }
+            let partial = &text[start..];
+            if at_end {
+                if !partial.is_empty() {
+                    let _ = window.line(partial, true, &mut callback);
+                }
+                return Ok(window.summary);
+            }
+            if window.line(partial, false, &mut callback).is_break() {
+                return Ok(window.summary);
+            }
+
+            // What's kept of an unfinished line is enough to hold any match
+            // up to the buffer's length that's still to be reported, with the
+            // text either side of it.
+            let kept = 2 * (self.buffer + self.excerpt);
+            let keep = match partial.len() > kept {
+                false => start,
+                true => start + floor_char_boundary(partial, partial.len() - kept),
+            };
+            window.dropped += (keep - start) as u64;
+            buf.drain(..keep);
+        }
+    }
+}
+
+/// Where a search has got to within the line it's reading.
+struct Window<'s> {
+    searcher: &'s WindowSearcher,
+    matcher: &'s dyn Matcher,
+    line_number: u64,
+    /// How many bytes of the line have already been let go of
+    dropped: u64,
+    /// Whether the line has already been reported
+    matched: bool,
+    summary: SearchSummary,
+}
+
+impl Window<'_> {
+    /// Searches what's been read of the current line, which is `complete` once
+    /// its end has been read.
+    fn line<F>(&mut self, text: &str, complete: bool, callback: &mut F) -> ControlFlow<()>
+    where
+        F: FnMut(WindowMatch) -> ControlFlow<()>,
+    {
+        // A `\r` at the end of what's been read may yet turn out to end the
+        // line, and is seen again with the rest of it if it doesn't.
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let mut flow = ControlFlow::Continue(());
+        if !self.matched {
+            let found = (self.matcher.find(text))
+                .filter(|span| complete || span.end + self.searcher.excerpt < text.len());
+            if let Some(span) = found {
+                self.matched = true;
+                self.summary.matched_lines += 1;
+                flow = callback(WindowMatch {
+                    line_number: self.line_number,
+                    offset: self.dropped + span.start as u64,
+                    excerpt: text[self.excerpt(text, span)].to_string(),
+                });
+            }
+        }
+        if complete {
+            self.summary.total_lines += 1;
+            self.line_number += 1;
+            self.dropped = 0;
+            self.matched = false;
+        }
+
+        flow
+    }
+
+    /// Returns the range of the text to show around a match.
+    fn excerpt(&self, text: &str, span: Range<usize>) -> Range<usize> {
+        let excerpt = self.searcher.excerpt;
+        let start = floor_char_boundary(text, span.start.saturating_sub(excerpt));
+        let mut end = (span.end + excerpt).min(text.len());
+        while !text.is_char_boundary(end) {
+            end += 1;
+        }
+
+        start..end
+    }
+}
+
+/// Returns the nearest character boundary at or before an offset.
+fn floor_char_boundary(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::LiteralMatcher;
+
+    fn search(searcher: WindowSearcher, pattern: &str, input: &[u8]) -> Result<Vec<WindowMatch>> {
+        let mut found = Vec::new();
+        searcher.search(&LiteralMatcher::new(pattern), input, |m| {
+            found.push(m);
+            ControlFlow::Continue(())
+        })?;
+
+        Ok(found)
+    }
+
+    #[test]
+    fn find_matches_across_reads() -> Result<(), GrrsError> {
+        let searcher = WindowSearcher::new(2).buffer(6);
+        let found = search(
+            searcher,
+            "needle",
+            "short\nhayneedlehay needle\nü needle\r\n".as_bytes(),
+        )?;
+        assert_eq!(
+            found,
+            [
+                WindowMatch {
+                    line_number: 2,
+                    offset: 3,
+                    excerpt: "ayneedleha".into()
+                },
+                WindowMatch {
+                    line_number: 3,
+                    offset: 3,
+                    excerpt: "ü needle".into()
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn find_a_match_far_into_a_line() -> Result<(), GrrsError> {
+        let mut input = vec![b'a'; 1_000_000];
+        input.extend_from_slice(b"needle");
+        let found = search(WindowSearcher::new(1).buffer(16), "needle", &input)?;
+        assert_eq!(found[0].offset, 1_000_000);
+        assert_eq!(found[0].excerpt, "aneedle");
+
+        Ok(())
+    }
+
+    #[test]
+    fn report_invalid_utf8() {
+        let result = search(WindowSearcher::new(1), "x", b"fine\n\xff\n");
+        assert!(matches!(
+            result,
+            Err(GrrsError::EncodingError { line_number: 2 })
+        ));
+    }
+}
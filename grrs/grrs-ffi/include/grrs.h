@@ -0,0 +1,136 @@
+#ifndef GRRS_H
+#define GRRS_H
+
+/* Generated by cbindgen from grrs-ffi/src/lib.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The outcome of a call into the library.
+ */
+typedef enum GrrsStatus {
+  /**
+   * The call succeeded
+   */
+  GrrsStatus_Ok = 0,
+  /**
+   * A required pointer was null, or the pattern wasn't valid UTF-8
+   */
+  GrrsStatus_InvalidArgument = 1,
+  /**
+   * The pattern didn't compile as a regular expression
+   */
+  GrrsStatus_InvalidPattern = 2,
+  /**
+   * The content wasn't valid UTF-8
+   */
+  GrrsStatus_EncodingError = 3,
+  /**
+   * Any other failure
+   */
+  GrrsStatus_Error = 4,
+} GrrsStatus;
+
+/**
+ * What a grrs_ffi::GrrsEvent reports.
+ */
+typedef enum GrrsEventKind {
+  /**
+   * A line that matched the pattern
+   */
+  GrrsEventKind_Match = 0,
+  /**
+   * A line printed as context around a match
+   */
+  GrrsEventKind_Context = 1,
+  /**
+   * A gap between two groups of context that aren't adjacent
+   */
+  GrrsEventKind_Break = 2,
+  /**
+   * The nearest heading above a group of lines, such as the function or
+   * section they're in
+   */
+  GrrsEventKind_Heading = 3,
+} GrrsEventKind;
+
+/**
+ * How a search matches and which lines it reports, mirroring
+ * grrs_core::SearchOptions.
+ */
+typedef struct GrrsOptions {
+  bool ignore_case;
+  bool smart_case;
+  bool regex;
+  bool word_regexp;
+  bool invert_match;
+  uintptr_t before_context;
+  uintptr_t after_context;
+  /**
+   * Stop after this many matching lines, or never if 0
+   */
+  uint64_t max_count;
+} GrrsOptions;
+
+/**
+ * A single line reported by a search. `line` points to `line_len` bytes of
+ * UTF-8, followed by a NUL terminator that isn't counted; it's null, with a
+ * `line_number` of 0, for breaks.
+ */
+typedef struct GrrsEvent {
+  enum GrrsEventKind kind;
+  uint64_t line_number;
+  const char *line;
+  uintptr_t line_len;
+} GrrsEvent;
+
+/**
+ * Everything a search reported, owned by the library until passed to
+ * grrs_free_results().
+ */
+typedef struct GrrsResults {
+  const struct GrrsEvent *events;
+  uintptr_t events_len;
+  uint64_t matched_lines;
+  uint64_t total_lines;
+} GrrsResults;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Searches `content_len` bytes of UTF-8 content for a NUL-terminated pattern,
+ * and on success stores results that must be released with
+ * grrs_free_results() through `results`. Null `options` use the defaults.
+ *
+ * # Safety
+ *
+ * `content` must point to `content_len` readable bytes, `pattern` to a
+ * NUL-terminated string, `options` to a grrs_ffi::GrrsOptions or be null, and
+ * `results` to writable storage for a pointer.
+ */
+enum GrrsStatus grrs_search_buffer(const uint8_t *content,
+                                   uintptr_t content_len,
+                                   const char *pattern,
+                                   const struct GrrsOptions *options,
+                                   struct GrrsResults **results);
+
+/**
+ * Releases results stored by grrs_search_buffer(). Passing null does nothing.
+ *
+ * # Safety
+ *
+ * `results` must be null or have come from grrs_search_buffer(), and must not
+ * be used again afterwards.
+ */
+void grrs_free_results(struct GrrsResults *results);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* GRRS_H */
@@ -0,0 +1,290 @@
+//! A C API for embedding the grrs_core searcher, declared in `include/grrs.h`.
+//! Each build generates the header with cbindgen into `OUT_DIR`, leaving the
+//! source tree alone, and a test fails if the checked-in copy has fallen
+//! behind, so copy the generated one over after changing the API.
+//!
+//! Every type shared with C is `#[repr(C)]`, and results are only ever
+//! allocated and freed by the library, so callers never depend on how Rust
+//! manages memory.
+
+use grrs_core::sink::{CollectSink, SinkEvent};
+use grrs_core::{GrrsError, SearchOptions, Searcher};
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::ptr;
+
+/// The outcome of a call into the library.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrrsStatus {
+    /// The call succeeded
+    Ok = 0,
+    /// A required pointer was null, or the pattern wasn't valid UTF-8
+    InvalidArgument = 1,
+    /// The pattern didn't compile as a regular expression
+    InvalidPattern = 2,
+    /// The content wasn't valid UTF-8
+    EncodingError = 3,
+    /// Any other failure
+    Error = 4,
+}
+
+impl From<&GrrsError> for GrrsStatus {
+    fn from(err: &GrrsError) -> GrrsStatus {
+        match err {
+            GrrsError::InvalidArgument(_) => GrrsStatus::InvalidArgument,
+            GrrsError::InvalidPattern { .. } => GrrsStatus::InvalidPattern,
+            GrrsError::EncodingError { .. } => GrrsStatus::EncodingError,
+            _ => GrrsStatus::Error,
+        }
+    }
+}
+
+/// How a search matches and which lines it reports, mirroring
+/// grrs_core::SearchOptions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GrrsOptions {
+    pub ignore_case: bool,
+    pub smart_case: bool,
+    pub regex: bool,
+    pub word_regexp: bool,
+    pub invert_match: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+    /// Stop after this many matching lines, or never if 0
+    pub max_count: u64,
+}
+
+impl From<&GrrsOptions> for SearchOptions {
+    fn from(options: &GrrsOptions) -> SearchOptions {
+        SearchOptions {
+            ignore_case: options.ignore_case,
+            smart_case: options.smart_case,
+            regex: options.regex,
+            word_regexp: options.word_regexp,
+            invert_match: options.invert_match,
+            before_context: options.before_context,
+            after_context: options.after_context,
+            context: None,
+            max_count: match options.max_count {
+                0 => None,
+                count => Some(count),
+            },
+            no_simd: false,
+            after_context_until: None,
+            passthru: false,
+        }
+    }
+}
+
+/// What a grrs_ffi::GrrsEvent reports.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrrsEventKind {
+    /// A line that matched the pattern
+    Match = 0,
+    /// A line printed as context around a match
+    Context = 1,
+    /// A gap between two groups of context that aren't adjacent
+    Break = 2,
+    /// The nearest heading above a group of lines, such as the function or
+    /// section they're in
+    Heading = 3,
+}
+
+/// A single line reported by a search. `line` points to `line_len` bytes of
+/// UTF-8, followed by a NUL terminator that isn't counted; it's null, with a
+/// `line_number` of 0, for breaks.
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrrsEvent {
+    pub kind: GrrsEventKind,
+    pub line_number: u64,
+    pub line: *const c_char,
+    pub line_len: usize,
+}
+
+/// Everything a search reported, owned by the library until passed to
+/// grrs_free_results().
+#[repr(C)]
+#[derive(Debug)]
+pub struct GrrsResults {
+    pub events: *const GrrsEvent,
+    pub events_len: usize,
+    pub matched_lines: u64,
+    pub total_lines: u64,
+}
+
+impl GrrsEvent {
+    fn new(event: SinkEvent) -> GrrsEvent {
+        let (kind, line_number, line) = match event {
+            SinkEvent::Match { line_number, line } => (GrrsEventKind::Match, line_number, line),
+            SinkEvent::Context { line_number, line } => (GrrsEventKind::Context, line_number, line),
+            SinkEvent::Heading { line_number, line } => (GrrsEventKind::Heading, line_number, line),
+            SinkEvent::Break => {
+                return GrrsEvent {
+                    kind: GrrsEventKind::Break,
+                    line_number: 0,
+                    line: ptr::null(),
+                    line_len: 0,
+                }
+            }
+        };
+        let mut bytes = line.into_owned().into_bytes();
+        let line_len = bytes.len();
+        bytes.push(0);
+
+        GrrsEvent {
+            kind,
+            line_number,
+            line: Box::into_raw(bytes.into_boxed_slice()) as *const c_char,
+            line_len,
+        }
+    }
+}
+
+impl Drop for GrrsEvent {
+    fn drop(&mut self) {
+        if !self.line.is_null() {
+            // Allocated by GrrsEvent::new() with the terminator.
+            let line = ptr::slice_from_raw_parts_mut(self.line as *mut u8, self.line_len + 1);
+            drop(unsafe { Box::from_raw(line) });
+        }
+    }
+}
+
+/// Searches `content_len` bytes of UTF-8 content for a NUL-terminated pattern,
+/// and on success stores results that must be released with
+/// grrs_free_results() through `results`. Null `options` use the defaults.
+///
+/// # Safety
+///
+/// `content` must point to `content_len` readable bytes, `pattern` to a
+/// NUL-terminated string, `options` to a grrs_ffi::GrrsOptions or be null, and
+/// `results` to writable storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn grrs_search_buffer(
+    content: *const u8,
+    content_len: usize,
+    pattern: *const c_char,
+    options: *const GrrsOptions,
+    results: *mut *mut GrrsResults,
+) -> GrrsStatus {
+    if (content.is_null() && content_len > 0) || pattern.is_null() || results.is_null() {
+        return GrrsStatus::InvalidArgument;
+    }
+    let content = match content_len {
+        0 => &[][..],
+        len => std::slice::from_raw_parts(content, len),
+    };
+    let pattern = match CStr::from_ptr(pattern).to_str() {
+        Ok(pattern) => pattern,
+        Err(_) => return GrrsStatus::InvalidArgument,
+    };
+    let options = options
+        .as_ref()
+        .map(SearchOptions::from)
+        .unwrap_or_default();
+
+    match search(content, pattern, options) {
+        Ok(found) => {
+            *results = Box::into_raw(Box::new(found));
+            GrrsStatus::Ok
+        }
+        Err(err) => GrrsStatus::from(&err),
+    }
+}
+
+/// Releases results stored by grrs_search_buffer(). Passing null does nothing.
+///
+/// # Safety
+///
+/// `results` must be null or have come from grrs_search_buffer(), and must not
+/// be used again afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn grrs_free_results(results: *mut GrrsResults) {
+    if results.is_null() {
+        return;
+    }
+    let results = Box::from_raw(results);
+    let events =
+        ptr::slice_from_raw_parts_mut(results.events as *mut GrrsEvent, results.events_len);
+    drop(Box::from_raw(events));
+}
+
+/// Runs a search and converts what it reports into C-compatible results.
+fn search(content: &[u8], pattern: &str, options: SearchOptions) -> Result<GrrsResults, GrrsError> {
+    let searcher = Searcher::new(options);
+    let mut sink = CollectSink::default();
+    let summary = searcher.search_sink(&searcher.matcher(pattern)?, content, &mut sink)?;
+    let events: Box<[GrrsEvent]> = sink.events.into_iter().map(GrrsEvent::new).collect();
+    let events_len = events.len();
+
+    Ok(GrrsResults {
+        events: Box::into_raw(events) as *const GrrsEvent,
+        events_len,
+        matched_lines: summary.matched_lines,
+        total_lines: summary.total_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_a_buffer() {
+        let content = b"lorem\nipsum\ndolor";
+        let options = GrrsOptions {
+            before_context: 1,
+            ..GrrsOptions::default()
+        };
+        let mut results = ptr::null_mut();
+        let status = unsafe {
+            grrs_search_buffer(
+                content.as_ptr(),
+                content.len(),
+                b"dolor\0".as_ptr() as *const c_char,
+                &options,
+                &mut results,
+            )
+        };
+        assert_eq!(status, GrrsStatus::Ok);
+        let found = unsafe { &*results };
+        assert_eq!((found.matched_lines, found.total_lines), (1, 3));
+        let events = unsafe { std::slice::from_raw_parts(found.events, found.events_len) };
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, GrrsEventKind::Context);
+        let line = unsafe { CStr::from_ptr(events[1].line) };
+        assert_eq!((events[1].line_number, line.to_bytes()), (3, &b"dolor"[..]));
+        unsafe { grrs_free_results(results) };
+    }
+
+    #[test]
+    fn report_errors() {
+        let options = GrrsOptions {
+            regex: true,
+            ..GrrsOptions::default()
+        };
+        let mut results = ptr::null_mut();
+        let pattern = b"(\0".as_ptr() as *const c_char;
+        let status =
+            unsafe { grrs_search_buffer(b"x".as_ptr(), 1, pattern, &options, &mut results) };
+        assert_eq!(status, GrrsStatus::InvalidPattern);
+        let status =
+            unsafe { grrs_search_buffer(b"\xff".as_ptr(), 1, pattern, ptr::null(), &mut results) };
+        assert_eq!(status, GrrsStatus::EncodingError);
+        assert!(results.is_null());
+    }
+
+    #[test]
+    fn header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/grrs.h"));
+        assert!(
+            generated == include_str!("../include/grrs.h"),
+            "include/grrs.h is out of date; copy {}/grrs.h over it",
+            env!("OUT_DIR")
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! Node.js bindings to grrs_core, built with `napi build` from this directory.
+//! Searches run on the libuv thread pool, so they never block the event loop.
+
+use grrs_core::sink::{CollectSink, SinkEvent};
+use grrs_core::{open_input, GrrsError, SearchOptions, Searcher};
+use napi::bindgen_prelude::AsyncTask;
+use napi::{Env, Task};
+use napi_derive::napi;
+
+/// How a search matches and which lines it reports, as for
+/// grrs_core::SearchOptions. Any field may be left out.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct SearchFileOptions {
+    pub ignore_case: Option<bool>,
+    pub smart_case: Option<bool>,
+    pub regex: Option<bool>,
+    pub word_regexp: Option<bool>,
+    pub invert_match: Option<bool>,
+    pub before_context: Option<u32>,
+    pub after_context: Option<u32>,
+    pub context: Option<u32>,
+    pub max_count: Option<u32>,
+}
+
+impl From<SearchFileOptions> for SearchOptions {
+    fn from(options: SearchFileOptions) -> SearchOptions {
+        SearchOptions {
+            ignore_case: options.ignore_case.unwrap_or(false),
+            smart_case: options.smart_case.unwrap_or(false),
+            regex: options.regex.unwrap_or(false),
+            word_regexp: options.word_regexp.unwrap_or(false),
+            invert_match: options.invert_match.unwrap_or(false),
+            before_context: options.before_context.unwrap_or(0) as usize,
+            after_context: options.after_context.unwrap_or(0) as usize,
+            context: options.context.map(|lines| lines as usize),
+            max_count: options.max_count.map(u64::from),
+            no_simd: false,
+            after_context_until: None,
+            passthru: false,
+        }
+    }
+}
+
+/// A line reported by a search, with a `type` of `match`, `context`, `break`
+/// or `heading`. Breaks have no line.
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchEvent {
+    #[napi(js_name = "type")]
+    pub kind: String,
+    pub line_number: Option<i64>,
+    pub line: Option<String>,
+}
+
+impl From<SinkEvent<'_>> for SearchEvent {
+    fn from(event: SinkEvent) -> SearchEvent {
+        let (kind, line) = match event {
+            SinkEvent::Match { line_number, line } => ("match", Some((line_number, line))),
+            SinkEvent::Context { line_number, line } => ("context", Some((line_number, line))),
+            SinkEvent::Break => ("break", None),
+            SinkEvent::Heading { line_number, line } => ("heading", Some((line_number, line))),
+        };
+        SearchEvent {
+            kind: kind.to_string(),
+            line_number: line.as_ref().map(|(line_number, _)| *line_number as i64),
+            line: line.map(|(_, line)| line.into_owned()),
+        }
+    }
+}
+
+/// How many lines a search read, and how many of them matched.
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchSummary {
+    pub matched_lines: i64,
+    pub total_lines: i64,
+}
+
+/// Everything a search reported, in the order lines were read.
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResults {
+    pub events: Vec<SearchEvent>,
+    pub summary: SearchSummary,
+}
+
+/// Searches the file at `path` for a pattern, resolving to the events and
+/// counts the search produced, or rejecting if the file can't be read or the
+/// pattern or options are invalid.
+#[napi]
+pub fn search_file(
+    path: String,
+    pattern: String,
+    options: Option<SearchFileOptions>,
+) -> AsyncTask<SearchFile> {
+    AsyncTask::new(SearchFile {
+        path,
+        pattern,
+        options: options.unwrap_or_default().into(),
+    })
+}
+
+/// A file search run off the JavaScript thread.
+pub struct SearchFile {
+    path: String,
+    pattern: String,
+    options: SearchOptions,
+}
+
+impl Task for SearchFile {
+    type Output = SearchResults;
+    type JsValue = SearchResults;
+
+    fn compute(&mut self) -> napi::Result<SearchResults> {
+        search_path(&self.path, &self.pattern, self.options.clone())
+            .map_err(|err| napi::Error::from_reason(err.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: SearchResults) -> napi::Result<SearchResults> {
+        Ok(output)
+    }
+}
+
+/// Searches a file, collecting the events the search produces.
+fn search_path(
+    path: &str,
+    pattern: &str,
+    options: SearchOptions,
+) -> Result<SearchResults, GrrsError> {
+    let searcher = Searcher::new(options);
+    let mut sink = CollectSink::default();
+    let summary =
+        searcher.search_sink(&searcher.matcher(pattern)?, open_input(path)?, &mut sink)?;
+
+    Ok(SearchResults {
+        events: sink.events.into_iter().map(SearchEvent::from).collect(),
+        summary: SearchSummary {
+            matched_lines: summary.matched_lines as i64,
+            total_lines: summary.total_lines as i64,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn search_a_path() -> Result<(), GrrsError> {
+        let mut file = tempfile::NamedTempFile::new()?;
+        writeln!(file, "lorem\nipsum\ndolor")?;
+        let options = SearchFileOptions {
+            before_context: Some(1),
+            ..SearchFileOptions::default()
+        };
+        let path = file.path().to_string_lossy();
+        let results = search_path(&path, "dolor", options.into())?;
+        assert_eq!(results.events.len(), 2);
+        assert_eq!(results.events[0].kind, "context");
+        assert_eq!(results.events[1].line.as_deref(), Some("dolor"));
+        assert_eq!(results.summary.total_lines, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! WebAssembly bindings to grrs_core, built for `wasm32-unknown-unknown` with
+//! `wasm-pack build grrs-wasm`. Functions that touch the file system are left
+//! out, as there is none in a browser.
+
+use grrs_core::sink::{CollectSink, SinkEvent};
+use grrs_core::{GrrsError, SearchOptions, SearchSummary, Searcher};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// The events produced by searching some content, and how many lines matched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Results {
+    pub events: Vec<SinkEvent<'static>>,
+    pub summary: SearchSummary,
+}
+
+/// Searches content for a pattern, collecting the events the search produces.
+///
+/// # Example
+///
+/// ```rust
+/// # use anyhow::{Error, Result};
+/// use grrs_core::sink::SinkEvent;
+/// # fn main() -> Result<(), Error> {
+/// let results = grrs_wasm::search_content("lorem\nipsum", "sum", Default::default())?;
+/// assert_eq!(results.events, vec![SinkEvent::Match { line_number: 2, line: "ipsum".into() }]);
+/// assert_eq!(results.summary.total_lines, 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn search_content(
+    content: &str,
+    pattern: &str,
+    options: SearchOptions,
+) -> Result<Results, GrrsError> {
+    let searcher = Searcher::new(options);
+    let mut sink = CollectSink::default();
+    let summary =
+        searcher.search_sink(&searcher.matcher(pattern)?, content.as_bytes(), &mut sink)?;
+
+    Ok(Results {
+        events: sink.events,
+        summary,
+    })
+}
+
+/// Searches content for a pattern from JavaScript. `options` is an object with
+/// any of the fields of grrs_core::SearchOptions, or is left `undefined`.
+/// Returns `{ events, summary }`, where each event has a `type` of `match`,
+/// `context` or `break`, and throws if the pattern or options are invalid.
+#[wasm_bindgen]
+pub fn search(content: &str, pattern: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options = match options.is_undefined() || options.is_null() {
+        true => SearchOptions::default(),
+        false => serde_wasm_bindgen::from_value(options)?,
+    };
+    let results =
+        search_content(content, pattern, options).map_err(|err| JsValue::from(err.to_string()))?;
+
+    Ok(serde_wasm_bindgen::to_value(&results)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_results() -> Result<(), GrrsError> {
+        let options = SearchOptions {
+            before_context: 1,
+            ..SearchOptions::default()
+        };
+        let results = search_content("lorem\nipsum\ndolor", "dolor", options)?;
+        assert_eq!(
+            results.events,
+            vec![
+                SinkEvent::Context {
+                    line_number: 2,
+                    line: "ipsum".into()
+                },
+                SinkEvent::Match {
+                    line_number: 3,
+                    line: "dolor".into()
+                },
+            ]
+        );
+        assert!(search_content(
+            "lorem",
+            "(",
+            SearchOptions {
+                regex: true,
+                ..SearchOptions::default()
+            }
+        )
+        .is_err());
+
+        Ok(())
+    }
+}
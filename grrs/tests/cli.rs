@@ -1,66 +0,0 @@
-use assert_cmd::prelude::*; // Add methods on commands
-use predicates::prelude::*; // Used for writing assertions
-use std::fs::{remove_file, File};
-use std::io::{Read, Write};
-use std::process::Command; // Run programs
-use tempfile::NamedTempFile;
-
-#[test]
-fn file_doesnt_exist() -> Result<(), Box<dyn std::error::Error>> {
-    let mut cmd = Command::cargo_bin("grrs")?;
-
-    cmd.arg("foobar").arg("test/file/doesnt/exist");
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("No such file or directory"));
-
-    Ok(())
-}
-
-#[test]
-fn find_content_in_file() -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = NamedTempFile::new()?;
-    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
-
-    let mut cmd = Command::cargo_bin("grrs")?;
-    cmd.arg("test").arg(file.path());
-    cmd.assert()
-        .success()
-        .stdout(predicate::str::contains("test\nLINE# 4: Another test"));
-
-    Ok(())
-}
-
-#[test]
-fn find_content_in_file_and_write_to_file() -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = NamedTempFile::new()?;
-    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
-
-    let mut cmd = Command::cargo_bin("grrs")?;
-    cmd.arg("--outfile")
-        .arg("test_outfile.txt")
-        .arg("test")
-        .arg(file.path());
-    cmd.assert().success();
-    let mut file = File::open("test_outfile.txt")?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    assert_eq!(contents, "LINE# 1: A test\nLINE# 4: Another test\n");
-    remove_file("test_outfile.txt")?;
-
-    Ok(())
-}
-
-#[test]
-fn empty_pattern_string() -> Result<(), Box<dyn std::error::Error>> {
-    let mut file = NamedTempFile::new()?;
-    writeln!(file, "A test\nActual content\nMore content\nAnother test")?;
-
-    let mut cmd = Command::cargo_bin("grrs")?;
-    cmd.arg("").arg(file.path());
-    cmd.assert()
-        .failure()
-        .stderr(predicate::str::contains("pattern appears to be empty"));
-
-    Ok(())
-}